@@ -0,0 +1,38 @@
+//! Execution of Javascript inside of GNOME Shell via its `org.gnome.Shell.Eval` D-Bus method.
+//!
+//! Since GNOME 41 `Eval` is only available if the shell runs in unsafe mode, e.g. after enabling it
+//! in looking glass with `global.context.unsafe_mode = true`. Otherwise every call fails with an access denied error.
+
+use include_js_core::JSStr;
+use zbus::blocking::Connection;
+
+/// Evaluates `js` in GNOME Shell on the session bus.
+///
+/// The result is the tuple returned by `Eval`: whether the evaluation succeeded, and the JSON encoded
+/// result of the last statement or the error message if it did not.
+///
+/// # Examples
+///
+/// ```no_run
+/// use include_js::{gnome, js};
+///
+/// let (success, result) = gnome::eval(&js! { global.get_window_actors().length; }).unwrap();
+/// assert!(success, "{}", result);
+/// ```
+pub fn eval(js: &JSStr) -> zbus::Result<(bool, String)> {
+    let connection = Connection::session()?;
+    eval_on(&connection, js)
+}
+
+/// Like `eval` but uses an existing connection to the session bus.
+pub fn eval_on(connection: &Connection, js: &JSStr) -> zbus::Result<(bool, String)> {
+    let reply = connection.call_method(
+        Some("org.gnome.Shell"),
+        "/org/gnome/Shell",
+        Some("org.gnome.Shell"),
+        "Eval",
+        &(js.as_str(),),
+    )?;
+
+    reply.body().deserialize()
+}
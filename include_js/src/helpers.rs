@@ -0,0 +1,144 @@
+//! Handlebars helpers that are registered for every `JSTemplate` derived template.
+
+use std::{collections::BTreeMap, io};
+
+use handlebars::{html_escape, Context, Handlebars, Helper, HelperResult, JsonRender, JsonValue, Output, RenderContext, RenderError};
+use include_js_core::JSString;
+
+// used by the derive for the bounds of generic types and to build the template data,
+// so it works without a direct dependency on serde
+#[doc(hidden)]
+pub use handlebars::{no_escape, to_json};
+#[doc(hidden)]
+pub use serde::Serialize;
+
+/// The data a derived template with named fields is rendered from
+#[doc(hidden)]
+pub type TemplateData = BTreeMap<String, JsonValue>;
+
+/// Lets the engines write into a `String` through `io::Write`, they only ever write whole `str`s
+#[doc(hidden)]
+pub struct StringWriter<'a>(pub &'a mut String);
+
+impl io::Write for StringWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let s = std::str::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.0.push_str(s);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Merges the fields of `value` into `data`, for fields with `#[include_js(flatten)]`.
+/// Values that don't serialize to an object, like `None`, provide no fields.
+#[doc(hidden)]
+pub fn flatten_into<T: Serialize>(data: &mut TemplateData, value: &T) {
+    if let JsonValue::Object(fields) = to_json(value) {
+        data.extend(fields);
+    }
+}
+
+/// `{{field}}` for a field with `#[include_js(json)]`, registered as a helper under the name of the field
+/// so it takes precedence over the value and writes its JSON without escaping.
+#[doc(hidden)]
+pub fn json_field(
+    h: &Helper,
+    _: &Handlebars,
+    ctx: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = ctx
+        .data()
+        .get(h.name())
+        .ok_or_else(|| RenderError::new(format!("missing field {}", h.name())))?;
+
+    out.write(&json(value))?;
+    Ok(())
+}
+
+fn json<T: Serialize>(value: &T) -> String {
+    // JSON allows these line terminators in strings, Javascript before ES2019 does not
+    to_json(value)
+        .to_string()
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029")
+}
+
+/// `{{js_string value}}` renders `value` as a properly quoted and escaped Javascript string literal.
+pub fn js_string(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = h
+        .param(0)
+        .ok_or_else(|| RenderError::new("js_string expects exactly one parameter"))?
+        .value();
+
+    out.write(js_string_literal(value).as_str())?;
+    Ok(())
+}
+
+/// `{{ value|js_string }}` for templates rendered by minijinja, like the `js_string` helper.
+#[cfg(feature = "minijinja")]
+pub fn js_string_filter(value: minijinja::Value) -> minijinja::Value {
+    let quoted = match value.as_str() {
+        Some(s) => JSString::quote(s),
+        None => JSString::quote(&value.to_string()),
+    };
+
+    // the literal is Javascript, escaping it would break it
+    minijinja::Value::from_safe_string(quoted.into_string())
+}
+
+fn js_string_literal(value: &JsonValue) -> JSString {
+    match value.as_str() {
+        Some(s) => JSString::quote(s),
+        None => JSString::quote(&value.render()),
+    }
+}
+
+// the rest is used by precompiled templates and renders exactly like the engine does
+
+/// Appends `value` like `{{value}}` renders it
+#[doc(hidden)]
+pub fn push_value<T: Serialize>(out: &mut String, value: &T, escape_html: bool) {
+    let rendered = to_json(value).render();
+
+    if escape_html {
+        out.push_str(&html_escape(&rendered));
+    } else {
+        out.push_str(&rendered);
+    }
+}
+
+/// Appends `value` like `{{value}}` renders a field with `#[include_js(json)]`
+#[doc(hidden)]
+pub fn push_json<T: Serialize>(out: &mut String, value: &T) {
+    out.push_str(&json(value));
+}
+
+/// Appends `value` like `{{js_string value}}` renders it
+#[doc(hidden)]
+pub fn push_js_string<T: Serialize>(out: &mut String, value: &T) {
+    out.push_str(js_string_literal(&to_json(value)).as_str());
+}
+
+/// Whether `{{#if value}}` renders its block
+#[doc(hidden)]
+pub fn is_truthy<T: Serialize>(value: &T) -> bool {
+    match to_json(value) {
+        JsonValue::Bool(b) => b,
+        JsonValue::Number(n) => n.as_f64().map(f64::is_normal).unwrap_or(false),
+        JsonValue::Null => false,
+        JsonValue::String(s) => !s.is_empty(),
+        JsonValue::Array(a) => !a.is_empty(),
+        JsonValue::Object(o) => !o.is_empty(),
+    }
+}
@@ -1,8 +1,152 @@
-pub use include_js_core::{JSStr, JSString, JSTemplate};
-pub use include_js_codegen::include_js;
+//! Inclusion of Javascript that is checked at compile time, see `include_js!` and the other macros.
+//!
+//! Without the default `std` feature the crate is `no_std` and only needs `alloc`. The macros parse the code at
+//! compile time with `include_js_core` and its `std` features, so a crate that builds for a target without `std`
+//! needs version 2 of the feature resolver, which keeps these features to the macros. It is the default since
+//! edition 2021, with earlier editions it has to be enabled with `resolver = "2"` in the `[package]` or
+//! `[workspace]` of Cargo.toml.
+//!
+#![cfg_attr(not(feature = "std"), doc = "```compile_fail")]
+#![cfg_attr(feature = "std", doc = "```ignore")]
+//! // built without `std`, the parts of include_js_core that need it don't exist although the macros use them
+//! let get = include_js_core::LazyJSStr::get;
+//! ```
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub use include_js_core::{check_balanced, ExportInfo, JSExpr, JSFunction, JSModule, JSProgram, JSStmt, JSStr, JSString, JSTemplate, JSWithIntegrity, JSWithSourceMap, MissingField, RenderError, ScriptBatch, ToJsArgs, ToJsLiteral, Unbalanced};
+pub use include_js_codegen::{format_js, include_js, include_js_bundle, include_js_dir, include_js_fn, include_js_glob, include_js_lazy, include_js_module, include_js_opt, include_js_with_integrity, include_js_with_map, include_json_js, js, js_str, ToJsLiteral};
+
+#[cfg(feature = "std")]
+pub use include_js_core::LazyJSStr;
+
+/// The paths the code generated by the macros uses. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use include_js_core::__private::*;
+
+    pub use crate::__register as register;
+
+    #[cfg(feature = "registry")]
+    pub use inventory;
+}
+
+/// Registers a script embedded by the macros with `registry`
+#[cfg(feature = "registry")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __register {
+    ($kind:ident, $path:expr, $size:expr, $sha256:expr) => {
+        $crate::__private::inventory::submit! {
+            $crate::ScriptInfo { path: $path, kind: $crate::ScriptKind::$kind, size: $size, sha256: $sha256 }
+        }
+    };
+}
+
+/// Without the `registry` feature the scripts are not registered
+#[cfg(not(feature = "registry"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __register {
+    ($($info:tt)*) => {};
+}
+
+/// # Examples
+///
+/// ```
+/// use include_js::{JSStr, include_ts};
+///
+/// const JS: &JSStr = include_ts!("src/ts/app.ts");
+/// assert!(JS.as_str().starts_with("var Mood = /*#__PURE__*/ function(Mood) {"));
+/// assert!(JS.as_str().contains("function greet(greeting) {"));
+/// assert!(!JS.as_str().contains("interface Greeting"));
+/// ```
+///
+/// ```compile_fail
+/// use include_js::{JSStr, include_ts};
+///
+/// // the emitted code is checked like any other included code
+/// const JS: &JSStr = include_ts!("src/ts/app.ts", require_fn = ["init"]);
+/// ```
+#[cfg(feature = "typescript")]
+pub use include_js_codegen::include_ts;
+
+#[cfg(feature = "__validation")]
+pub use include_js_core::{bundle, BundleError, Error, JsBuilder, ParseError, ParseErrorKind};
+
+#[cfg(any(feature = "exec", feature = "ast"))]
+pub use include_js_core::boa;
+
+#[cfg(feature = "quickjs")]
+pub use include_js_core::rquickjs;
+
+#[cfg(feature = "deno")]
+pub use include_js_core::JsRuntimeExt;
+
+#[cfg(feature = "wasm")]
+pub use include_js_core::js_sys;
+
+#[cfg(feature = "serde")]
+pub use include_js_core::{to_js_value, SerializeError};
 
 #[cfg(feature = "template")]
 pub use handlebars::Handlebars as TemplateEngine;
 
 #[cfg(feature = "template")]
-pub use include_js_codegen::JSTemplate;
+pub use include_js_codegen::{include_js_template, js_template, JSTemplate};
+
+#[cfg(feature = "template")]
+pub use template::{render, CheckedTemplate};
+
+/// The engine of the templates that are derived with `#[include_js(engine = "minijinja")]`, i.e. written in Jinja
+/// syntax. Strings are written as Javascript string literals with the `js_string` filter.
+///
+/// # Examples
+///
+/// `src/js/move_windows.js.jinja`
+/// ```javascript
+/// {% for class in window_classes %}
+/// global.get_window_actors()
+///     .map(a => a.meta_window)
+///     .filter(w => w.wm_class == {{ class|js_string }})
+///     .forEach(w => w.move_frame(true, {{ x }}, {{ y }}));
+/// {% endfor %}
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+/// use serde::Serialize;
+///
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(template = "src/js/move_windows.js.jinja", engine = "minijinja")]
+/// struct MoveWindowsCommand {
+///     window_classes: Vec<String>,
+///     x: u32,
+///     y: u32,
+/// }
+///
+/// let js = MoveWindowsCommand { window_classes: vec!["Nautilus".to_owned(), "Firefox".to_owned()], x: 0, y: 5 }
+///     .render_template();
+/// assert!(js.as_str().contains(".filter(w => w.wm_class == \"Firefox\")"));
+/// assert!(js.as_str().contains(".forEach(w => w.move_frame(true, 0, 5));"));
+/// ```
+#[cfg(feature = "minijinja")]
+pub use minijinja;
+
+#[cfg(feature = "template")]
+pub mod helpers;
+
+#[cfg(feature = "template")]
+mod template;
+
+#[cfg(feature = "registry")]
+pub use registry::{registry, ScriptInfo, ScriptKind};
+
+#[cfg(feature = "registry")]
+mod registry;
+
+#[cfg(feature = "gnome-shell")]
+pub mod gnome;
+
+#[cfg(feature = "webview")]
+pub mod webview;
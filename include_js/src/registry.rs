@@ -0,0 +1,46 @@
+//! The registry of all scripts embedded into the binary, see `registry`.
+
+/// What kind of code a `ScriptInfo` is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptKind {
+    /// A script, e.g. from `include_js!` or `include_js_dir!`
+    Script,
+    /// A module from `include_js_module!`
+    Module,
+    /// The source of a template, which is rendered at runtime
+    Template,
+}
+
+/// A script that was embedded by one of the include macros or by `#[derive(JSTemplate)]`.
+/// The hash is formatted like the hashes of `JSWithIntegrity`, i.e. `sha256-<base64 digest>`, and it and the size
+/// are computed over exactly the embedded bytes, for templates over their source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScriptInfo {
+    /// The path as given to the macro, the first one if several files are included at once,
+    /// for `include_js_dir!` and `include_js_glob!` the path of the file
+    pub path: &'static str,
+    pub kind: ScriptKind,
+    pub size: usize,
+    pub sha256: &'static str,
+}
+
+inventory::collect!(ScriptInfo);
+
+/// Iterates over every script embedded into the binary, in no particular order. Scripts register themselves
+/// when the `registry` feature is enabled, every expansion of a macro registers the code it embeds,
+/// so a script included twice is registered twice.
+///
+/// # Examples
+///
+/// ```
+/// use include_js::{JSStr, ScriptKind, include_js, registry};
+///
+/// const JS: &JSStr = include_js!("src/js/greet.js", source_url);
+///
+/// let script = registry().find(|script| script.path == "src/js/greet.js").unwrap();
+/// assert_eq!(script.kind, ScriptKind::Script);
+/// assert_eq!(script.size, JS.as_str().len());
+/// ```
+pub fn registry() -> impl Iterator<Item = &'static ScriptInfo> {
+    inventory::iter::<ScriptInfo>.into_iter()
+}
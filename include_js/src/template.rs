@@ -0,0 +1,53 @@
+//! Templates rendered from any serializable data instead of the fields of a type, see `include_js_template!`.
+
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+use crate::{helpers, JSString, RenderError, TemplateEngine};
+
+/// A template that was checked at compiletime by `include_js_template!` and is rendered with `render`.
+pub struct CheckedTemplate {
+    source: &'static str,
+    /// set up on the first render
+    engine: OnceLock<TemplateEngine<'static>>,
+}
+
+impl CheckedTemplate {
+    /// Wraps `source` without checking it
+    ///
+    /// # Safety
+    /// `source` must be a template that renders syntactically valid Javascript
+    #[doc(hidden)]
+    pub const unsafe fn new_unchecked(source: &'static str) -> Self {
+        CheckedTemplate { source, engine: OnceLock::new() }
+    }
+
+    /// The source of the template
+    pub fn source(&self) -> &'static str {
+        self.source
+    }
+
+    fn engine(&self) -> &TemplateEngine<'static> {
+        self.engine.get_or_init(|| {
+            let mut h = TemplateEngine::new();
+            h.set_strict_mode(true);
+            h.register_escape_fn(helpers::no_escape);
+            h.register_helper("js_string", Box::new(helpers::js_string));
+            h.register_template_string("template", self.source)
+                .expect("the template is compiled at compiletime");
+            h
+        })
+    }
+}
+
+/// Renders `template` from `data`, which has to provide every variable the template uses.
+pub fn render<T: Serialize>(template: &CheckedTemplate, data: &T) -> Result<JSString, RenderError> {
+    let s = template
+        .engine()
+        .render("template", data)
+        .map_err(|e| RenderError::Template(Box::new(e)))?;
+
+    // safety: the template was checked at compiletime like the templates of `JSTemplate`
+    Ok(unsafe { JSString::new_unchecked(s) })
+}
@@ -0,0 +1,15 @@
+interface Greeting {
+    name: string;
+    excited?: boolean;
+}
+
+enum Mood {
+    Happy,
+    Grumpy,
+}
+
+function greet(greeting: Greeting): string {
+    return "Hello " + greeting.name + (greeting.excited ? "!" : ".");
+}
+
+const mood: Mood = Mood.Happy;
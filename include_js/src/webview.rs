@@ -0,0 +1,49 @@
+//! Injection of Javascript into webviews of Tauri and wry.
+//!
+//! In Tauri 2 only webviews can evaluate scripts, so the trait is implemented for `tauri::Webview`
+//! and `tauri::WebviewWindow` but not for `tauri::Window`.
+
+use include_js_core::JSStr;
+
+/// Extension trait for webviews that can evaluate Javascript.
+pub trait WindowEvalExt {
+    type Error;
+
+    /// Evaluates `js` in the webview. The script runs asynchronously, so an `Ok` result only means that it was
+    /// handed to the webview, not that it ran without throwing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use include_js::{js, webview::WindowEvalExt};
+    ///
+    /// fn focus_search(window: &tauri::WebviewWindow) -> tauri::Result<()> {
+    ///     window.eval_js(&js! { document.getElementById("search").focus(); })
+    /// }
+    /// ```
+    fn eval_js(&self, js: &JSStr) -> Result<(), Self::Error>;
+}
+
+impl<R: tauri::Runtime> WindowEvalExt for tauri::Webview<R> {
+    type Error = tauri::Error;
+
+    fn eval_js(&self, js: &JSStr) -> tauri::Result<()> {
+        self.eval(js.as_str())
+    }
+}
+
+impl<R: tauri::Runtime> WindowEvalExt for tauri::WebviewWindow<R> {
+    type Error = tauri::Error;
+
+    fn eval_js(&self, js: &JSStr) -> tauri::Result<()> {
+        self.eval(js.as_str())
+    }
+}
+
+impl WindowEvalExt for wry::WebView {
+    type Error = wry::Error;
+
+    fn eval_js(&self, js: &JSStr) -> wry::Result<()> {
+        self.evaluate_script(js.as_str())
+    }
+}
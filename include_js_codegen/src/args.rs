@@ -0,0 +1,1315 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    parenthesized, token, Attribute, Expr, ExprCall, ExprCast, ExprLit, ExprMacro, ExprPath, Ident, Lit, LitBool, LitInt, LitStr, Token, Type, TypePath,
+    Visibility,
+};
+
+use super::{
+    comments,
+    encoding::Encoding,
+    lint::Lints,
+    parser::{self, Parser},
+    paths, registry,
+    source_map::{self, FileMap, SourceMapMode},
+    transform::{self, Jsx, Transform},
+};
+
+mod kw {
+    syn::custom_keyword!(parser);
+    syn::custom_keyword!(es);
+    syn::custom_keyword!(strict);
+    syn::custom_keyword!(strip_comments);
+    syn::custom_keyword!(preserve);
+    syn::custom_keyword!(max_bytes);
+    syn::custom_keyword!(relative_to);
+    syn::custom_keyword!(wrap);
+    syn::custom_keyword!(prelude);
+    syn::custom_keyword!(banner);
+    syn::custom_keyword!(footer);
+    syn::custom_keyword!(jsx);
+    syn::custom_keyword!(target);
+    syn::custom_keyword!(source_map);
+    syn::custom_keyword!(source_url);
+    syn::custom_keyword!(deny);
+    syn::custom_keyword!(forbid_globals);
+    syn::custom_keyword!(require_fn);
+    syn::custom_keyword!(json_parse);
+    syn::custom_keyword!(normalize);
+    syn::custom_keyword!(encoding);
+    syn::custom_keyword!(tolerate);
+    syn::custom_keyword!(allow_absolute);
+}
+
+/// A single `key = value` or flag argument of the include macros after the path
+enum IncludeArg {
+    Parser(kw::parser, Parser),
+    Es(kw::es, LitInt),
+    Strict(kw::strict),
+    StripComments(kw::strip_comments, LitBool),
+    Preserve(kw::preserve, LitStr),
+    MaxBytes(kw::max_bytes, LitInt),
+    RelativeTo(kw::relative_to, RelativeTo),
+    Wrap(kw::wrap, Wrap),
+    Prelude(kw::prelude, LitStr),
+    Banner(kw::banner, PathArg),
+    Footer(kw::footer, PathArg),
+    Jsx(kw::jsx, Jsx),
+    Target(kw::target, u16),
+    SourceMap(kw::source_map, SourceMapMode),
+    SourceUrl(kw::source_url, SourceUrl),
+    Deny(kw::deny, Vec<super::lint::Lint>),
+    ForbidGlobals(kw::forbid_globals, Vec<String>),
+    RequireFn(kw::require_fn, Vec<String>),
+    Normalize(kw::normalize, Normalize),
+    Encoding(kw::encoding, Encoding),
+    Tolerate(kw::tolerate, Tolerate),
+    AllowAbsolute(kw::allow_absolute),
+    Crate(Token![crate], LitStr),
+}
+
+impl Parse for IncludeArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lookahead = input.lookahead1();
+
+        if lookahead.peek(kw::parser) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::Parser(kw, input.parse()?))
+        } else if lookahead.peek(kw::es) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::Es(kw, input.parse()?))
+        } else if lookahead.peek(kw::strict) {
+            Ok(IncludeArg::Strict(input.parse()?))
+        } else if lookahead.peek(kw::strip_comments) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::StripComments(kw, input.parse()?))
+        } else if lookahead.peek(kw::preserve) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::Preserve(kw, input.parse()?))
+        } else if lookahead.peek(kw::max_bytes) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::MaxBytes(kw, input.parse()?))
+        } else if lookahead.peek(kw::relative_to) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::RelativeTo(kw, input.parse()?))
+        } else if lookahead.peek(kw::wrap) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::Wrap(kw, input.parse()?))
+        } else if lookahead.peek(kw::prelude) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::Prelude(kw, input.parse()?))
+        } else if lookahead.peek(kw::banner) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::Banner(kw, input.parse()?))
+        } else if lookahead.peek(kw::footer) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::Footer(kw, input.parse()?))
+        } else if lookahead.peek(kw::jsx) {
+            let kw: kw::jsx = input.parse()?;
+            Ok(IncludeArg::Jsx(kw, Jsx::parse_args(input, kw.span)?))
+        } else if lookahead.peek(kw::target) {
+            let kw: kw::target = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::Target(kw, transform::parse_target(input, kw.span)?))
+        } else if lookahead.peek(kw::source_map) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::SourceMap(kw, input.parse()?))
+        } else if lookahead.peek(kw::source_url) {
+            let kw = input.parse()?;
+
+            if input.peek(Token![=]) {
+                input.parse::<Token![=]>()?;
+                Ok(IncludeArg::SourceUrl(kw, input.parse()?))
+            } else {
+                Ok(IncludeArg::SourceUrl(kw, SourceUrl::Always))
+            }
+        } else if lookahead.peek(kw::deny) {
+            let kw: kw::deny = input.parse()?;
+            Ok(IncludeArg::Deny(kw, Lints::parse_deny(input, kw.span)?))
+        } else if lookahead.peek(kw::forbid_globals) {
+            let kw: kw::forbid_globals = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::ForbidGlobals(kw, Lints::parse_names(input, kw.span)?))
+        } else if lookahead.peek(kw::require_fn) {
+            let kw: kw::require_fn = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::RequireFn(kw, Lints::parse_names(input, kw.span)?))
+        } else if lookahead.peek(kw::normalize) {
+            Ok(IncludeArg::Normalize(input.parse()?, input.parse()?))
+        } else if lookahead.peek(kw::encoding) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::Encoding(kw, input.parse()?))
+        } else if lookahead.peek(kw::tolerate) {
+            Ok(IncludeArg::Tolerate(input.parse()?, input.parse()?))
+        } else if lookahead.peek(kw::allow_absolute) {
+            Ok(IncludeArg::AllowAbsolute(input.parse()?))
+        } else if lookahead.peek(Token![crate]) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(IncludeArg::Crate(kw, input.parse()?))
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+/// A path argument of the include macros. `${NAME}` in the path is replaced with the value of the
+/// environment variable `NAME` when the macro is expanded, e.g. `"${OUT_DIR}/bundle.js"`.
+///
+/// Like for `include_str!` the path can also be built with `concat!` and `env!`. `\\` is turned into `/`,
+/// so paths written on Windows work everywhere.
+pub(super) struct PathArg {
+    pub(super) value: String,
+    pub(super) span: Span,
+    /// whether the path is absolute as it is written, paths that start with an environment variable are not
+    pub(super) absolute: bool,
+}
+
+impl Parse for PathArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        PathArg::from_expr(&input.parse()?)
+    }
+}
+
+impl PathArg {
+    pub(super) fn from_expr(expr: &Expr) -> syn::Result<Self> {
+        let value = eval_path(expr)?.replace('\\', "/");
+        Ok(PathArg { value, span: expr.span(), absolute: is_written_absolute(expr) })
+    }
+
+    pub(super) fn from_lit(lit: &LitStr) -> syn::Result<Self> {
+        let value = expand_env(lit)?.replace('\\', "/");
+        Ok(PathArg { value, span: lit.span(), absolute: paths::is_absolute(&lit.value()) })
+    }
+
+    /// Fails if the path is written as an absolute path, which only exists on the machine it was written on.
+    /// `allowable` is whether the macro has the option `allow_absolute` to allow them.
+    pub(super) fn reject_absolute(&self, allowable: bool) -> syn::Result<()> {
+        if !self.absolute {
+            return Ok(());
+        }
+
+        let mut message = format!(
+            "'{}' is an absolute path, use a path relative to the crate or one that starts with an environment variable like `${{OUT_DIR}}`",
+            self.value,
+        );
+
+        if allowable {
+            message.push_str(", or allow it with `allow_absolute`");
+        }
+
+        Err(syn::Error::new(self.span, message))
+    }
+}
+
+/// The paths given by `expr`, which is a single path or `first_of(...)` with the paths to try in order.
+fn path_candidates(expr: &Expr) -> syn::Result<Vec<PathArg>> {
+    match expr {
+        Expr::Call(ExprCall { func, args, .. }) if matches!(&**func, Expr::Path(ExprPath { path, .. }) if path.is_ident("first_of")) => {
+            if args.is_empty() {
+                return Err(syn::Error::new(expr.span(), "`first_of` needs at least one path"));
+            }
+
+            args.iter().map(PathArg::from_expr).collect()
+        },
+        Expr::Group(group) => path_candidates(&group.expr),
+        expr => Ok(vec![PathArg::from_expr(expr)?]),
+    }
+}
+
+/// Whether the path `expr` evaluates to is absolute before environment variables are put into it.
+fn is_written_absolute(expr: &Expr) -> bool {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => paths::is_absolute(&lit.value()),
+        Expr::Group(group) => is_written_absolute(&group.expr),
+        Expr::Macro(ExprMacro { mac, .. }) if mac.path.segments.last().is_some_and(|segment| segment.ident == "concat") => mac
+            .parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+            .is_ok_and(|args| args.first().is_some_and(is_written_absolute)),
+        _ => false,
+    }
+}
+
+/// Replaces every `${NAME}` in `lit` with the value of the environment variable `NAME`.
+fn expand_env(lit: &LitStr) -> syn::Result<String> {
+    let span = lit.span();
+    let path = lit.value();
+    let mut value = String::with_capacity(path.len());
+    let mut rest = path.as_str();
+
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| syn::Error::new(span, "unterminated `${` in path"))?;
+        let name = &rest[start + 2..start + end];
+
+        value.push_str(&rest[..start]);
+        value.push_str(&env_var(name, span)?);
+        rest = &rest[start + end + 1..];
+    }
+
+    value.push_str(rest);
+
+    Ok(value)
+}
+
+fn env_var(name: &str, span: Span) -> syn::Result<String> {
+    std::env::var(name).map_err(|_| syn::Error::new(span, format!("environment variable `{}` is not set", name)))
+}
+
+/// Evaluates a string literal or a `concat!` or `env!` invocation at compiletime.
+fn eval_path(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => expand_env(lit),
+        // arguments of `macro_rules!` macros are wrapped in invisible groups
+        Expr::Group(group) => eval_path(&group.expr),
+        Expr::Macro(ExprMacro { mac, .. }) => {
+            let name = mac.path.segments.last().map(|segment| segment.ident.to_string()).unwrap_or_default();
+            let args = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)?;
+
+            match name.as_str() {
+                "concat" => args.iter().map(eval_concat_arg).collect(),
+                // the optional second argument of `env!` is the error message
+                "env" => match args.first() {
+                    Some(Expr::Lit(ExprLit { lit: Lit::Str(var), .. })) if args.len() <= 2 => {
+                        env_var(&var.value(), var.span())
+                    },
+                    _ => Err(syn::Error::new(mac.span(), "expected `env!(\"NAME\")`")),
+                },
+                _ => Err(syn::Error::new(mac.span(), "only `concat!` and `env!` can be used in paths")),
+            }
+        },
+        _ => Err(syn::Error::new(expr.span(), "expected a string literal, `concat!` or `env!`")),
+    }
+}
+
+/// Evaluates an argument of `concat!`, which can also be any other literal.
+fn eval_concat_arg(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(lit), .. }) => Ok(lit.base10_digits().to_owned()),
+        Expr::Lit(ExprLit { lit: Lit::Float(lit), .. }) => Ok(lit.base10_digits().to_owned()),
+        Expr::Lit(ExprLit { lit: Lit::Bool(lit), .. }) => Ok(lit.value.to_string()),
+        Expr::Lit(ExprLit { lit: Lit::Char(lit), .. }) => Ok(lit.value().to_string()),
+        expr => eval_path(expr),
+    }
+}
+
+/// What relative paths are resolved against
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum RelativeTo {
+    /// $CARGO_MANIFEST_DIR
+    Manifest,
+    /// the directory of the source file the path is written in
+    File,
+}
+
+impl Parse for RelativeTo {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lit = input.parse::<LitStr>()?;
+
+        match lit.value().as_str() {
+            "manifest" => Ok(RelativeTo::Manifest),
+            "file" => Ok(RelativeTo::File),
+            _ => Err(syn::Error::new(lit.span(), "expected one of \"manifest\" or \"file\"")),
+        }
+    }
+}
+
+impl RelativeTo {
+    /// The directory that paths written at `span` are resolved against.
+    pub(super) fn base_dir(self, span: Span) -> syn::Result<PathBuf> {
+        match self {
+            RelativeTo::Manifest => super::manifest_dir(span),
+            // the compiler only knows the file if the code comes from a file on disk,
+            // otherwise the path is resolved like the default
+            RelativeTo::File => match span.unwrap().local_file() {
+                Some(file) => {
+                    // the path is relative to the working directory of the compiler if the crate was given as a relative path
+                    let file = std::env::current_dir()
+                        .map_err(|e| syn::Error::new(span, format!("could not get the working directory: {}", e)))?
+                        .join(file);
+                    Ok(file.parent().map(Path::to_owned).unwrap_or(file))
+                },
+                None => super::manifest_dir(span),
+            },
+        }
+    }
+}
+
+/// What the included code is wrapped in
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Wrap {
+    /// `(function () { ... })();`, so the declarations of the script don't end up in the global scope
+    Iife,
+}
+
+impl Parse for Wrap {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident: Ident = input.parse()?;
+
+        match ident.to_string().as_str() {
+            "iife" => Ok(Wrap::Iife),
+            _ => Err(syn::Error::new(ident.span(), "expected `iife`")),
+        }
+    }
+}
+
+/// When the included code gets a `//# sourceURL=...` comment, given with `source_url` or `source_url = debug`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum SourceUrl {
+    Always,
+    /// only in builds with debug assertions, i.e. usually not in release builds
+    Debug,
+}
+
+impl Parse for SourceUrl {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident: Ident = input.parse()?;
+
+        match ident.to_string().as_str() {
+            "debug" => Ok(SourceUrl::Debug),
+            _ => Err(syn::Error::new(ident.span(), "expected `debug`")),
+        }
+    }
+}
+
+/// How the included files are normalized before anything else is done with them, given with `normalize(...)`
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct Normalize {
+    /// remove a byte order mark at the start
+    bom: bool,
+    /// turn `\r\n` and lone `\r` into `\n`
+    newlines: bool,
+    /// end the code with a newline if it doesn't already
+    trailing_newline: bool,
+}
+
+impl Parse for Normalize {
+    /// Parses the parenthesized list after `normalize`, which normalizes everything if it is left out
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if !input.peek(token::Paren) {
+            return Ok(Normalize { bom: true, newlines: true, trailing_newline: true });
+        }
+
+        let content;
+        parenthesized!(content in input);
+
+        let mut normalize = Normalize::default();
+        for ident in Punctuated::<Ident, Token![,]>::parse_terminated(&content)? {
+            let flag = match ident.to_string().as_str() {
+                "bom" => &mut normalize.bom,
+                "newlines" => &mut normalize.newlines,
+                "trailing_newline" => &mut normalize.trailing_newline,
+                _ => return Err(syn::Error::new(ident.span(), "expected one of `bom`, `newlines` or `trailing_newline`")),
+            };
+
+            if *flag {
+                return Err(syn::Error::new(ident.span(), format!("duplicate `{}`", ident)));
+            }
+            *flag = true;
+        }
+
+        Ok(normalize)
+    }
+}
+
+impl Normalize {
+    /// Normalizes `code`
+    pub(super) fn apply(self, mut code: String) -> String {
+        if self.bom && code.starts_with('\u{feff}') {
+            code.remove(0);
+        }
+
+        if self.newlines && code.contains('\r') {
+            code = code.replace("\r\n", "\n").replace('\r', "\n");
+        }
+
+        if self.trailing_newline && !code.is_empty() && !code.ends_with('\n') {
+            code.push('\n');
+        }
+
+        code
+    }
+}
+
+/// Syntax of Annex B and of scripts run as programs that is turned into ordinary comments, given with `tolerate(...)`
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct Tolerate {
+    /// a `#!` line at the start, like `#!/usr/bin/env node`
+    pub(super) hashbang: bool,
+    /// the HTML-like comments `<!--` and `-->`, which are not allowed in modules
+    pub(super) html_comments: bool,
+}
+
+impl Parse for Tolerate {
+    /// Parses the parenthesized list after `tolerate`, which tolerates everything if it is left out
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if !input.peek(token::Paren) {
+            return Ok(Tolerate { hashbang: true, html_comments: true });
+        }
+
+        let content;
+        parenthesized!(content in input);
+
+        let mut tolerate = Tolerate::default();
+        for ident in Punctuated::<Ident, Token![,]>::parse_terminated(&content)? {
+            let flag = match ident.to_string().as_str() {
+                "hashbang" => &mut tolerate.hashbang,
+                "html_comments" => &mut tolerate.html_comments,
+                _ => return Err(syn::Error::new(ident.span(), "expected `hashbang` or `html_comments`")),
+            };
+
+            if *flag {
+                return Err(syn::Error::new(ident.span(), format!("duplicate `{}`", ident)));
+            }
+            *flag = true;
+        }
+
+        Ok(tolerate)
+    }
+}
+
+impl Tolerate {
+    /// Turns the tolerated syntax in `code` into comments. Code with an unterminated literal is returned unchanged,
+    /// so validating it reports the actual error.
+    pub(super) fn apply(self, code: String) -> String {
+        if self == Tolerate::default() {
+            return code;
+        }
+
+        comments::comment_trivia(&code, self.hashbang, self.html_comments).unwrap_or(code)
+    }
+}
+
+/// A file that is put in front of or after the included code, given with `banner = "..."` or `footer = "..."`
+pub(super) struct Fragment {
+    path: PathBuf,
+    content: String,
+}
+
+impl Fragment {
+    /// Reads the fragment at `arg`, which is resolved and read like the included files
+    fn load(arg: &PathArg, options: &IncludeOptions) -> syn::Result<Self> {
+        let path = options.resolve(arg)?;
+        let content = options.read(&path, arg.span)?;
+
+        Ok(Fragment { path, content })
+    }
+
+    fn origin(&self) -> String {
+        self.path.display().to_string()
+    }
+}
+
+/// Appends `code` to `buf` and ends it with a newline, so the next code starts on its own line.
+fn push_line(buf: &mut String, code: &str) {
+    buf.push_str(code);
+
+    if !buf.ends_with('\n') {
+        buf.push('\n');
+    }
+}
+
+/// What happens to the comments of an included file
+pub(super) enum Comments {
+    Keep,
+    /// Remove all comments except the ones starting with `preserve`, e.g. `/*!` for license banners
+    Strip { preserve: Option<String> },
+}
+
+/// The `key = value` arguments of the include macros
+pub(super) struct IncludeOptions {
+    pub(super) parser: Parser,
+    /// whether scripts are parsed in strict mode
+    pub(super) strict: bool,
+    pub(super) comments: Comments,
+    pub(super) wrap: Option<Wrap>,
+    /// code that is put in front of the included code, inside of the wrapper if there is one
+    pub(super) prelude: Option<String>,
+    /// files that are put in front of and after the included code, outside of the wrapper
+    pub(super) banner: Option<Fragment>,
+    pub(super) footer: Option<Fragment>,
+    /// how the included files are compiled to plain Javascript, e.g. if they contain JSX
+    pub(super) transform: Transform,
+    /// whether a source map is put into the included code
+    pub(super) source_map: Option<SourceMapMode>,
+    /// whether the included code is named with a `//# sourceURL=...` comment
+    pub(super) source_url: Option<SourceUrl>,
+    /// the maximum size of the included code in bytes
+    pub(super) max_bytes: Option<usize>,
+    pub(super) relative_to: RelativeTo,
+    /// the constructs the included code must not contain
+    pub(super) lints: Lints,
+    /// how the included files are normalized when they are read
+    pub(super) normalize: Normalize,
+    /// the encoding of the included files, taken from their byte order mark if it is not given
+    pub(super) encoding: Option<Encoding>,
+    /// the syntax that is turned into comments when the included files are read
+    pub(super) tolerate: Tolerate,
+    /// whether paths can be written as absolute paths
+    pub(super) allow_absolute: bool,
+    /// the path of the include_js crate in the generated code, for crates that re-export the macros
+    pub(super) krate: syn::Path,
+}
+
+impl IncludeOptions {
+    /// The first of the paths given with `first_of(...)` that exists. If none does, the first is returned with
+    /// `optional` or a single path, so resolving it later reports the error, and otherwise they are all listed.
+    fn first_existing(&self, mut candidates: Vec<PathArg>, optional: bool) -> syn::Result<PathArg> {
+        for (i, path) in candidates.iter().enumerate() {
+            if !self.allow_absolute {
+                path.reject_absolute(true)?;
+            }
+
+            if self.relative_to.base_dir(path.span)?.join(&path.value).exists() {
+                return Ok(candidates.swap_remove(i));
+            }
+        }
+
+        if optional || candidates.len() == 1 {
+            return Ok(candidates.swap_remove(0));
+        }
+
+        let paths: Vec<_> = candidates.iter().map(|path| format!("'{}'", path.value)).collect();
+        Err(syn::Error::new(candidates[0].span, format!("none of {} exist", paths.join(", "))))
+    }
+
+    /// Resolves the path of an included file or directory, it has to exist.
+    pub(super) fn resolve(&self, path: &PathArg) -> syn::Result<PathBuf> {
+        if !self.allow_absolute {
+            path.reject_absolute(true)?;
+        }
+
+        super::resolve(&self.relative_to.base_dir(path.span)?, Path::new(&path.value), path.span)
+    }
+
+    /// Reads the included file `path`, decodes it, normalizes it with `normalize(...)` and turns the syntax
+    /// given with `tolerate(...)` into comments.
+    pub(super) fn read(&self, path: &Path, span: Span) -> syn::Result<String> {
+        self.read_verbatim(path, span).map(|(code, _)| code)
+    }
+
+    /// Like `read`, but also returns whether the code is the file unchanged, so it can be embedded with `include_str!`.
+    pub(super) fn read_verbatim(&self, path: &Path, span: Span) -> syn::Result<(String, bool)> {
+        let (file, encoding) = super::read_encoded(path, self.encoding, span)?;
+        let code = self.tolerate.apply(self.normalize.apply(file.clone()));
+        let verbatim = encoding == Encoding::Utf8 && code == file;
+        Ok((code, verbatim))
+    }
+
+    /// Makes cargo rebuild the crate when one of the files the options refer to changes.
+    pub(super) fn track(&self) -> TokenStream {
+        self.banner.iter().chain(&self.footer).map(|fragment| super::track(&fragment.path)).collect()
+    }
+
+    /// Compiles the content of the file `origin` to plain Javascript if it needs to be, e.g. with `jsx(...)`.
+    pub(super) fn compile(&self, content: String, module: bool, origin: &str, span: Span) -> syn::Result<String> {
+        self.compile_with(&self.transform, content, module, origin, span, false).map(|(code, _)| code)
+    }
+
+    /// Like `compile`, but with `transform` instead of the transformations of the options.
+    /// With `source_map` the map from the result to the file is returned as well.
+    pub(super) fn compile_with(
+        &self,
+        transform: &Transform,
+        content: String,
+        module: bool,
+        origin: &str,
+        span: Span,
+        source_map: bool,
+    ) -> syn::Result<(String, Option<FileMap>)> {
+        if transform.is_empty() {
+            let map = source_map.then(|| FileMap::identity(origin, &content));
+            return Ok((content, map));
+        }
+
+        let (js, map) = transform.apply(&content, origin, span, source_map)?;
+        Ok((self.check_emitted(js, module, origin, span)?, map))
+    }
+
+    /// Expands `expand` with the included code `content` of the file `path`, with `//# sourceURL=include_js:<path>`
+    /// appended if the options ask for it. With `source_url = debug` both are expanded and the one with the comment
+    /// is chosen in builds with debug assertions. The code is registered as `kind` with the `registry` feature.
+    pub(super) fn with_source_url(
+        &self,
+        content: &str,
+        path: &str,
+        kind: registry::Kind,
+        expand: impl Fn(&str) -> TokenStream,
+    ) -> TokenStream {
+        let named = || {
+            let mut named = content.to_owned();
+            push_line(&mut named, &format!("//# sourceURL=include_js:{}", path));
+            named
+        };
+
+        // only the code that ends up in the binary is registered, so the registration is under the same `cfg`
+        let embed = |content: &str, cfg: Option<&str>| {
+            let expanded = expand(content);
+
+            match registry::register(&self.krate, kind, path, content, cfg) {
+                register if register.is_empty() => expanded,
+                register => quote! {
+                    {
+                        #register
+                        #expanded
+                    }
+                },
+            }
+        };
+
+        match self.source_url {
+            None => embed(content, None),
+            Some(SourceUrl::Always) => embed(&named(), None),
+            Some(SourceUrl::Debug) => {
+                let named = embed(&named(), Some("debug_assertions"));
+                let unnamed = embed(content, Some("not(debug_assertions)"));
+                quote! {
+                    if cfg!(debug_assertions) { #named } else { #unnamed }
+                }
+            },
+        }
+    }
+
+    /// Fails if a source map was requested with `source_map = ...`, for the macros that don't support them.
+    pub(super) fn reject_source_map(&self, name: &str, span: Span) -> syn::Result<()> {
+        match self.source_map {
+            Some(_) => Err(syn::Error::new(span, format!("`{}` does not support source maps", name))),
+            None => Ok(()),
+        }
+    }
+
+    /// Validates the code that was compiled from the file `origin`, as the lines in errors refer to the emitted code
+    /// and not to the file, the error says so.
+    fn check_emitted(&self, js: String, module: bool, origin: &str, span: Span) -> syn::Result<String> {
+        self.check(&js, module, origin, span)
+            .map_err(|e| syn::Error::new(span, format!("the code emitted for '{}' is invalid: {}", origin, e)))?;
+        Ok(js)
+    }
+
+    /// Validates the code of several files in parallel, before they are checked and processed one after another,
+    /// see `parser::prepare`.
+    pub(super) fn prepare<'a>(&self, contents: impl IntoIterator<Item = &'a str>, module: bool) {
+        // compiled files are only validated after compiling them
+        if self.transform.is_empty() {
+            let contents: Vec<_> = contents.into_iter().collect();
+            parser::prepare(self.parser, &contents, module, self.strict);
+        }
+    }
+
+    /// Validates `content` as a script or module with the chosen parser.
+    pub(super) fn check(&self, content: &str, module: bool, origin: &str, span: Span) -> syn::Result<()> {
+        if module {
+            parser::check_module(self.parser, content, origin, span)
+        } else {
+            parser::check_script(self.parser, content, self.strict, origin, span)
+        }
+    }
+
+    /// Validates `content` as a script or module and returns it with all transformations applied.
+    pub(super) fn process(&self, content: String, module: bool, origin: &str, span: Span) -> syn::Result<String> {
+        self.process_mapped(content, module, origin, span).map(|(content, _)| content)
+    }
+
+    /// Like `process`, but also returns the byte offset at which `content` is part of the result unchanged,
+    /// i.e. unless comments are stripped, for source maps and `include_js_lazy!`.
+    fn process_mapped(
+        &self,
+        content: String,
+        module: bool,
+        origin: &str,
+        span: Span,
+    ) -> syn::Result<(String, Option<usize>)> {
+        let check = |content: &str, origin: &str| self.check(content, module, origin, span);
+        let mut offset = Some(0);
+
+        check(&content, origin)?;
+
+        let content = match &self.comments {
+            Comments::Keep => content,
+            Comments::Strip { preserve } => {
+                let stripped = comments::strip_comments(&content, preserve.as_deref())
+                    .ok_or_else(|| syn::Error::new(span, format!("could not strip comments from '{}'", origin)))?;
+
+                // the stripped code is checked again so a mistake while stripping can't embed invalid code
+                check(&stripped, origin)?;
+                offset = None;
+                stripped
+            },
+        };
+
+        // the lints are about the code as it is written, e.g. `require_fn` looks for top-level functions
+        self.lints.check(&content, module, origin, span)?;
+
+        let content = match (self.wrap, &self.prelude) {
+            (None, None) => content,
+            (Some(_), _) if module => {
+                return Err(syn::Error::new(span, "modules can't be wrapped, their imports and exports have to be at the top level"));
+            },
+            (wrap, prelude) => {
+                let mut wrapped = String::new();
+
+                if wrap == Some(Wrap::Iife) {
+                    wrapped.push_str("(function () {\n");
+                }
+
+                if let Some(prelude) = prelude {
+                    wrapped.push_str(prelude);
+                    wrapped.push('\n');
+                }
+
+                offset = offset.map(|offset| offset + wrapped.len());
+                wrapped.push_str(&content);
+
+                // on its own line so a line comment at the end of the code doesn't swallow it
+                if wrap == Some(Wrap::Iife) {
+                    if !wrapped.ends_with('\n') {
+                        wrapped.push('\n');
+                    }
+                    wrapped.push_str("})();\n");
+                }
+
+                check(&wrapped, origin)
+                    .map_err(|e| syn::Error::new(span, format!("the wrapped code is invalid: {}", e)))?;
+                wrapped
+            },
+        };
+
+        let content = match (&self.banner, &self.footer) {
+            (None, None) => content,
+            (banner, footer) => {
+                let mut combined = String::new();
+
+                // the fragments are checked on their own first, so errors in them are reported against their file
+                if let Some(banner) = banner {
+                    check(&banner.content, &banner.origin())?;
+                    push_line(&mut combined, &banner.content);
+                }
+
+                offset = offset.map(|offset| offset + combined.len());
+                push_line(&mut combined, &content);
+
+                if let Some(footer) = footer {
+                    check(&footer.content, &footer.origin())?;
+                    push_line(&mut combined, &footer.content);
+                }
+
+                check(&combined, origin).map_err(|e| {
+                    syn::Error::new(span, format!("the code combined with the banner and footer is invalid: {}", e))
+                })?;
+                combined
+            },
+        };
+
+        // the budget applies to what is actually embedded, so after all transformations
+        match self.max_bytes {
+            Some(max_bytes) if content.len() > max_bytes => Err(syn::Error::new(
+                span,
+                format!("'{}' is {} bytes, which exceeds the budget of {} bytes", origin, content.len(), max_bytes),
+            )),
+            _ => Ok((content, offset)),
+        }
+    }
+}
+
+/// The code loaded by `IncludeArgs::load_mapped`
+pub(super) struct Script {
+    pub(super) content: String,
+    /// the code that tracks the included files
+    pub(super) track: TokenStream,
+    /// the source map of the code, if it was asked for and not put into the code
+    pub(super) source_map: Option<String>,
+    /// the files whose content is part of `content` as it is, with its range in `content`
+    pub(super) verbatim: Vec<(Range<usize>, PathBuf)>,
+}
+
+/// The arguments of `include_js!` and `include_js_module!`, i.e. `"path"` optionally followed by `key = value` pairs
+pub(super) struct IncludeArgs {
+    pub(super) path: PathArg,
+    /// further files that are concatenated to the first, only for `include_js!` and `include_js_with_integrity!`
+    pub(super) more: Vec<PathArg>,
+    pub(super) options: IncludeOptions,
+}
+
+impl Parse for IncludeArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        IncludeArgs::parse_with(input, false)
+    }
+}
+
+impl IncludeArgs {
+    /// Parses the arguments of `include_js_opt!`, for which the first file does not have to exist
+    pub(super) fn parse_optional(input: ParseStream) -> syn::Result<Self> {
+        IncludeArgs::parse_with(input, true)
+    }
+
+    fn parse_with(input: ParseStream, optional: bool) -> syn::Result<Self> {
+        let candidates = path_candidates(&input.parse()?)?;
+        let more = parse_more_paths(input)?;
+        let options = parse_options(input)?;
+        let path = options.first_existing(candidates, optional)?;
+
+        Ok(IncludeArgs { path, more, options })
+    }
+
+    /// Whether the first included file exists, which it only does not for `include_js_opt!`
+    pub(super) fn path_exists(&self) -> syn::Result<bool> {
+        Ok(self.options.relative_to.base_dir(self.path.span)?.join(&self.path.value).exists())
+    }
+
+    /// Reads the included files, concatenates them and processes the result as a script.
+    pub(super) fn load_script(&self) -> syn::Result<Script> {
+        self.load_script_with(&self.options.transform)
+    }
+
+    /// Like `load_script`, but the files are compiled to Javascript with `transform` instead of the transformations
+    /// of the options. With `source_map = inline` the source map is put at the end of the code.
+    pub(super) fn load_script_with(&self, transform: &Transform) -> syn::Result<Script> {
+        let inline = self.options.source_map == Some(SourceMapMode::Inline);
+        let mut script = self.load_mapped(transform, inline)?;
+
+        if let Some(map) = script.source_map.take() {
+            push_line(&mut script.content, &source_map::inline_comment(&map));
+        }
+
+        Ok(script)
+    }
+
+    /// Like `load_script_with`, but with `source_map` the source map of the code is returned as well instead of
+    /// being put into it.
+    pub(super) fn load_mapped(&self, transform: &Transform, source_map: bool) -> syn::Result<Script> {
+        if source_map && matches!(self.options.comments, Comments::Strip { .. }) {
+            return Err(syn::Error::new(self.path.span, "source maps can't be created when comments are stripped"));
+        }
+
+        let mut content = String::new();
+        let mut track = self.options.track();
+        let mut maps = Vec::new();
+        let mut verbatim = Vec::new();
+
+        let files = std::iter::once(&self.path)
+            .chain(&self.more)
+            .map(|path| {
+                let file = self.options.resolve(path)?;
+                let (code, verbatim) = self.options.read_verbatim(&file, path.span)?;
+                Ok((path, file, code, verbatim))
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        if !self.more.is_empty() {
+            self.options.prepare(files.iter().map(|(_, _, code, _)| code.as_str()), false);
+        }
+
+        for (path, file, code, unchanged) in files {
+            let (code, map) = self.options.compile_with(transform, code, false, &path.value, path.span, source_map)?;
+
+            if !self.more.is_empty() {
+                // every file is checked on its own first, so errors are reported against the file they are in
+                self.options.check(&code, false, &path.value, path.span)?;
+                separate_statements(&mut content);
+            }
+
+            maps.extend(map.map(|map| (content.matches('\n').count(), map)));
+
+            // compiled or otherwise changed files are not the same as the file anymore
+            if transform.is_empty() && unchanged {
+                verbatim.push((content.len()..content.len() + code.len(), file.clone()));
+            }
+
+            content.push_str(&code);
+            track.extend(super::track(&file));
+        }
+
+        let origin: Vec<_> = std::iter::once(&self.path).chain(&self.more).map(|path| path.value.as_str()).collect();
+        let (content, offset) = self.options.process_mapped(content, false, &origin.join(", "), self.path.span)?;
+
+        let verbatim = match offset {
+            Some(offset) => verbatim
+                .into_iter()
+                .map(|(range, file)| (range.start + offset..range.end + offset, file))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let source_map = match source_map {
+            true => {
+                // comments can't be stripped with source maps, so the code is part of the result unchanged
+                let line = content[..offset.expect("the code is unchanged")].matches('\n').count();
+                Some(source_map::combine(maps.into_iter().map(|(start, map)| (line + start, map)).collect()))
+            },
+            false => None,
+        };
+
+        Ok(Script { content, track, source_map, verbatim })
+    }
+
+    /// The absolute path of the included file or directory, it has to exist.
+    pub(super) fn resolve_path(&self) -> syn::Result<PathBuf> {
+        self.options.resolve(&self.path)
+    }
+}
+
+/// The arguments of `include_js_module!`, which either includes the module as an expression
+/// or, with `"path" as Name`, declares a unit struct with the module and its exports as associated consts.
+pub(super) struct ModuleArgs {
+    /// the attributes, visibility and name of the struct to declare
+    pub(super) item: Option<(Vec<Attribute>, Visibility, Ident)>,
+    pub(super) args: IncludeArgs,
+}
+
+impl Parse for ModuleArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis: Visibility = input.parse()?;
+
+        // the path is an expression, so `"path" as Name` is parsed as a cast
+        let (candidates, name) = match input.parse::<Expr>()? {
+            Expr::Cast(ExprCast { expr, ty, .. }) => match *ty {
+                Type::Path(TypePath { qself: None, path }) if path.get_ident().is_some() => {
+                    (path_candidates(&expr)?, path.get_ident().cloned())
+                },
+                ty => return Err(syn::Error::new(ty.span(), "expected the name of the struct to declare")),
+            },
+            expr => (path_candidates(&expr)?, None),
+        };
+        let span = candidates[0].span;
+
+        let item = match name {
+            Some(name) => Some((attrs, vis, name)),
+            None if !attrs.is_empty() || !matches!(vis, Visibility::Inherited) => {
+                return Err(syn::Error::new(span, "expected `as Name` after the path"));
+            },
+            None => None,
+        };
+
+        let options = parse_options(input)?;
+        let path = options.first_existing(candidates, false)?;
+
+        Ok(ModuleArgs { item, args: IncludeArgs { path, more: Vec::new(), options } })
+    }
+}
+
+/// The arguments of `include_json_js!`, i.e. `"path"` optionally followed by `json_parse` and `key = value` pairs
+pub(super) struct JsonArgs {
+    pub(super) args: IncludeArgs,
+    /// whether the JSON is embedded as a string passed to `JSON.parse` instead of as an expression
+    pub(super) json_parse: bool,
+}
+
+impl Parse for JsonArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let candidates = path_candidates(&input.parse()?)?;
+
+        // only valid directly after the path, so it doesn't become an option of every other macro
+        let json_parse = input.peek(Token![,]) && input.peek2(kw::json_parse);
+        if json_parse {
+            input.parse::<Token![,]>()?;
+            input.parse::<kw::json_parse>()?;
+        }
+
+        let options = parse_options(input)?;
+        let path = options.first_existing(candidates, false)?;
+
+        Ok(JsonArgs { args: IncludeArgs { path, more: Vec::new(), options }, json_parse })
+    }
+}
+
+/// The arguments of `js_str!`, i.e. the code as a string literal optionally followed by `key = value` pairs
+pub(super) struct InlineArgs {
+    pub(super) code: LitStr,
+    pub(super) options: IncludeOptions,
+}
+
+impl Parse for InlineArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let code = input.parse()?;
+        let options = parse_options(input)?;
+
+        Ok(InlineArgs { code, options })
+    }
+}
+
+/// The arguments of the macros that take no options besides the path the generated code refers to the crate with,
+/// i.e. `"path"` optionally followed by `crate = "..."`
+pub(super) struct PathAndCrate<P> {
+    pub(super) path: P,
+    pub(super) krate: syn::Path,
+}
+
+impl<P: Parse> Parse for PathAndCrate<P> {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let mut krate = syn::parse_quote!(::include_js);
+
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            input.parse::<Token![crate]>()?;
+            input.parse::<Token![=]>()?;
+            krate = input.parse::<LitStr>()?.parse()?;
+            input.parse::<Option<Token![,]>>()?;
+        }
+
+        Ok(PathAndCrate { path, krate })
+    }
+}
+
+/// Ends `code` so that what is appended next is a separate statement, i.e. with a newline and, unless the code
+/// already ends with one, a semicolon on its own line. `}` is not enough, as it could end an object literal.
+fn separate_statements(code: &mut String) {
+    if code.is_empty() {
+        return;
+    }
+
+    if !code.ends_with('\n') {
+        code.push('\n');
+    }
+
+    if !code.trim_end().ends_with(';') {
+        code.push_str(";\n");
+    }
+}
+
+/// Parses the paths after the first, i.e. `, "path"` as long as the next argument is a string literal or a macro
+/// like `concat!` and not an option, which starts with `key`.
+pub(super) fn parse_more_paths(input: ParseStream) -> syn::Result<Vec<PathArg>> {
+    let mut paths = Vec::new();
+
+    while input.peek(Token![,]) && (input.peek2(LitStr) || (input.peek2(Ident) && input.peek3(Token![!]))) {
+        input.parse::<Token![,]>()?;
+        paths.push(input.parse()?);
+    }
+
+    Ok(paths)
+}
+
+/// Parses the optional `, key = value` pairs at the end of the arguments.
+pub(super) fn parse_options(input: ParseStream) -> syn::Result<IncludeOptions> {
+    let mut parser = None;
+    let mut es = None;
+    let mut strict = None;
+    let mut strip_comments = None;
+    let mut preserve = None;
+    let mut wrap = None;
+    let mut prelude = None;
+    let mut banner = None;
+    let mut footer = None;
+    let mut jsx = None;
+    let mut target = None;
+    let mut source_map = None;
+    let mut source_url = None;
+    let mut max_bytes = None;
+    let mut relative_to = None;
+    let mut deny = None;
+    let mut forbid_globals = None;
+    let mut require_fn = None;
+    let mut normalize = None;
+    let mut encoding = None;
+    let mut tolerate = None;
+    let mut allow_absolute = None;
+    let mut krate = None;
+
+    if !input.is_empty() {
+        input.parse::<Token![,]>()?;
+
+        for arg in Punctuated::<IncludeArg, Token![,]>::parse_terminated(input)? {
+            match arg {
+                IncludeArg::Parser(kw, _) if parser.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate parser specification"));
+                },
+                IncludeArg::Parser(kw, p) => parser = Some((kw, p)),
+                IncludeArg::Es(kw, _) if es.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate ECMAScript version specification"));
+                },
+                IncludeArg::Es(kw, version) => es = Some((kw, version)),
+                IncludeArg::Strict(kw) if strict.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate strict specification"));
+                },
+                IncludeArg::Strict(kw) => strict = Some(kw),
+                IncludeArg::StripComments(kw, _) if strip_comments.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate strip_comments specification"));
+                },
+                IncludeArg::StripComments(_, strip) => strip_comments = Some(strip.value),
+                IncludeArg::Preserve(kw, _) if preserve.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate preserve specification"));
+                },
+                IncludeArg::Preserve(kw, prefix) => preserve = Some((kw, prefix)),
+                IncludeArg::MaxBytes(kw, _) if max_bytes.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate max_bytes specification"));
+                },
+                IncludeArg::MaxBytes(_, bytes) => max_bytes = Some(bytes.base10_parse()?),
+                IncludeArg::RelativeTo(kw, _) if relative_to.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate relative_to specification"));
+                },
+                IncludeArg::RelativeTo(_, base) => relative_to = Some(base),
+                IncludeArg::Wrap(kw, _) if wrap.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate wrap specification"));
+                },
+                IncludeArg::Wrap(_, w) => wrap = Some(w),
+                IncludeArg::Prelude(kw, _) if prelude.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate prelude specification"));
+                },
+                IncludeArg::Prelude(_, code) => prelude = Some(code.value()),
+                IncludeArg::Banner(kw, _) if banner.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate banner specification"));
+                },
+                IncludeArg::Banner(_, path) => banner = Some(path),
+                IncludeArg::Footer(kw, _) if footer.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate footer specification"));
+                },
+                IncludeArg::Footer(_, path) => footer = Some(path),
+                IncludeArg::Jsx(kw, _) if jsx.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate jsx specification"));
+                },
+                IncludeArg::Jsx(_, config) => jsx = Some(config),
+                IncludeArg::Target(kw, _) if target.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate target specification"));
+                },
+                IncludeArg::Target(kw, version) => target = Some((kw, version)),
+                IncludeArg::SourceMap(kw, _) if source_map.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate source_map specification"));
+                },
+                IncludeArg::SourceMap(_, mode) => source_map = Some(mode),
+                IncludeArg::SourceUrl(kw, _) if source_url.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate source_url specification"));
+                },
+                IncludeArg::SourceUrl(_, when) => source_url = Some(when),
+                IncludeArg::Deny(kw, _) if deny.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate deny specification"));
+                },
+                IncludeArg::Deny(_, lints) => deny = Some(lints),
+                IncludeArg::ForbidGlobals(kw, _) if forbid_globals.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate forbid_globals specification"));
+                },
+                IncludeArg::ForbidGlobals(_, globals) => forbid_globals = Some(globals),
+                IncludeArg::RequireFn(kw, _) if require_fn.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate require_fn specification"));
+                },
+                IncludeArg::RequireFn(_, names) => require_fn = Some(names),
+                IncludeArg::Normalize(kw, _) if normalize.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate normalize specification"));
+                },
+                IncludeArg::Normalize(_, n) => normalize = Some(n),
+                IncludeArg::Encoding(kw, _) if encoding.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate encoding specification"));
+                },
+                IncludeArg::Encoding(_, e) => encoding = Some(e),
+                IncludeArg::Tolerate(kw, _) if tolerate.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate tolerate specification"));
+                },
+                IncludeArg::Tolerate(_, t) => tolerate = Some(t),
+                IncludeArg::AllowAbsolute(kw) if allow_absolute.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate allow_absolute specification"));
+                },
+                IncludeArg::AllowAbsolute(kw) => allow_absolute = Some(kw),
+                IncludeArg::Crate(kw, _) if krate.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate crate specification"));
+                },
+                IncludeArg::Crate(_, path) => krate = Some(path.parse()?),
+            }
+        }
+    }
+
+    let explicit_boa = matches!(parser, Some((_, Parser::Boa)));
+
+    let parser = match (parser, es) {
+        (parser, None) => parser.map(|(_, p)| p).unwrap_or(Parser::Boa),
+        (Some((kw, Parser::Boa)), Some(_)) => {
+            return Err(syn::Error::new(kw.span, "only the swc parser can check the ECMAScript version"));
+        },
+        // the version check needs swc's syntax tree, so `es` implies `parser = "swc"`
+        (_, Some((kw, version))) => Parser::Swc(Some(es_version(kw, &version)?)),
+    };
+
+    // boa rejects every function declaration in strict mode, so `strict` implies `parser = "swc"` as well
+    let parser = match (parser, strict) {
+        (parser, None) => parser,
+        (_, Some(kw)) if !cfg!(feature = "parser-swc") => {
+            return Err(syn::Error::new(kw.span, "parsing in strict mode requires the `parser-swc` feature of include_js"));
+        },
+        (Parser::Boa, Some(kw)) if explicit_boa => {
+            return Err(syn::Error::new(kw.span, "only the swc parser can parse in strict mode"));
+        },
+        (Parser::Boa, Some(_)) => Parser::Swc(None),
+        (parser, Some(_)) => parser,
+    };
+
+    // boa rejects the strict mode code the transpiled classes are in, so `target` implies `parser = "swc"` as well
+    let parser = match (parser, target) {
+        (Parser::Boa, Some((kw, _))) if explicit_boa => {
+            return Err(syn::Error::new(kw.span, "only the swc parser can check transpiled code"));
+        },
+        (Parser::Boa, Some(_)) => Parser::Swc(None),
+        (parser, _) => parser,
+    };
+
+    let relative_to = relative_to.unwrap_or(RelativeTo::Manifest);
+
+    let comments = match (strip_comments.unwrap_or(false), preserve) {
+        (false, None) => Comments::Keep,
+        (false, Some((kw, _))) => {
+            return Err(syn::Error::new(kw.span, "`preserve` only has an effect with `strip_comments = true`"));
+        },
+        (true, preserve) => Comments::Strip { preserve: preserve.map(|(_, prefix)| prefix.value()) },
+    };
+
+    let mut options = IncludeOptions {
+        parser,
+        strict: strict.is_some(),
+        comments,
+        wrap,
+        prelude,
+        banner: None,
+        footer: None,
+        transform: Transform { typescript: false, jsx, target: target.map(|(_, version)| version) },
+        source_map,
+        source_url,
+        max_bytes,
+        relative_to,
+        lints: Lints {
+            deny: deny.unwrap_or_default(),
+            forbid_globals: forbid_globals.unwrap_or_default(),
+            require_fn: require_fn.unwrap_or_default(),
+        },
+        normalize: normalize.unwrap_or_default(),
+        encoding,
+        tolerate: tolerate.unwrap_or_default(),
+        allow_absolute: allow_absolute.is_some(),
+        krate: krate.unwrap_or_else(|| syn::parse_quote!(::include_js)),
+    };
+
+    options.banner = banner.map(|path| Fragment::load(&path, &options)).transpose()?;
+    options.footer = footer.map(|path| Fragment::load(&path, &options)).transpose()?;
+
+    Ok(options)
+}
+
+fn es_version(kw: kw::es, version: &LitInt) -> syn::Result<u16> {
+    if !cfg!(feature = "parser-swc") {
+        return Err(syn::Error::new(kw.span, "checking the ECMAScript version requires the `parser-swc` feature of include_js"));
+    }
+
+    match version.base10_parse()? {
+        5 => Ok(5),
+        v @ 2015..=2024 => Ok(v),
+        _ => Err(syn::Error::new(version.span(), "expected 5 or a year between 2015 and 2024")),
+    }
+}
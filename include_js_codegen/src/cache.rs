@@ -0,0 +1,63 @@
+//! A cache of the code that was validated successfully, so code that didn't change isn't parsed again.
+//!
+//! Code is identified by the SHA-256 hash of its content, how it was validated, e.g. with which parser, and the
+//! version of this crate. The hashes are remembered for the rest of the compilation of a crate and as an empty file
+//! per hash in the directory `$INCLUDE_JS_CACHE_DIR`, or `$OUT_DIR/include_js_cache` if the crate has a build script,
+//! so they are kept between builds. An empty `INCLUDE_JS_CACHE_DIR` only keeps them in memory.
+//! Only successes are cached, errors are reported as usual.
+
+use std::{
+    collections::BTreeSet,
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+};
+
+use sha2::{Digest, Sha256};
+
+/// The hashes of the code that was validated successfully during this compilation
+static VALID: Mutex<BTreeSet<[u8; 32]>> = Mutex::new(BTreeSet::new());
+
+/// The directory the hashes are kept in between builds, if there is one
+fn dir() -> Option<PathBuf> {
+    match std::env::var_os("INCLUDE_JS_CACHE_DIR") {
+        Some(dir) if dir.is_empty() => None,
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => std::env::var_os("OUT_DIR").map(|dir| PathBuf::from(dir).join("include_js_cache")),
+    }
+}
+
+/// Validates `content` with `validate`, unless it was validated successfully the same way before.
+/// `mode` describes how `validate` validates the code, e.g. which parser it uses.
+pub(super) fn validated<E>(mode: &str, content: &str, validate: impl FnOnce() -> Result<(), E>) -> Result<(), E> {
+    let hash: [u8; 32] = Sha256::new()
+        .chain_update(env!("CARGO_PKG_VERSION"))
+        .chain_update([0])
+        .chain_update(mode)
+        .chain_update([0])
+        .chain_update(content)
+        .finalize()
+        .into();
+
+    let valid = || VALID.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if valid().contains(&hash) {
+        return Ok(());
+    }
+
+    let entry = dir().map(|dir| dir.join(hash.iter().map(|b| format!("{:02x}", b)).collect::<String>()));
+
+    if !entry.as_ref().is_some_and(|entry| entry.is_file()) {
+        validate()?;
+
+        // the cache only saves time, so it is not an error if it can't be written
+        if let Some(entry) = &entry {
+            let _ = entry
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| std::fs::write(entry, ""));
+        }
+    }
+
+    valid().insert(hash);
+    Ok(())
+}
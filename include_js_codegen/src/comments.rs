@@ -0,0 +1,261 @@
+//! Removal of comments from Javascript source text, and conversion of the comments of Annex B of the
+//! specification into ordinary ones.
+//!
+//! The scanner only needs to know enough about the lexical grammar to not mistake the content of string,
+//! template and regex literals for comments.
+
+/// What the last significant token was, to decide whether a `/` starts a regex or is a division
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Last {
+    /// start of input or an operator, a `/` starts a regex
+    Operator,
+    /// an identifier, literal or closing bracket, a `/` is a division
+    Operand,
+}
+
+enum Nesting {
+    Brace,
+    TemplateSubstitution,
+}
+
+/// Keywords after which an expression, and thus a regex, can start
+const EXPRESSION_KEYWORDS: &[&str] = &[
+    "return", "typeof", "instanceof", "in", "of", "new", "delete", "void", "throw", "case", "do", "else", "yield",
+    "await",
+];
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$' || c == '\\'
+}
+
+/// What is done with the comments in the source
+enum Mode<'a> {
+    /// remove them, except for the ones starting with `preserve`
+    Strip { preserve: Option<&'a str> },
+    /// keep them, but turn the HTML-like comments `<!--` and `-->` into `//` comments
+    HtmlToLine,
+}
+
+fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+struct Stripper<'a> {
+    src: &'a str,
+    pos: usize,
+    out: String,
+    mode: Mode<'a>,
+    last: Last,
+    nesting: Vec<Nesting>,
+    /// whether only whitespace and comments are in front of the current char on its line
+    line_start: bool,
+}
+
+impl<'a> Stripper<'a> {
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.src[self.pos..].chars().nth(offset)
+    }
+
+    /// Copies the source up to `end` to the output.
+    fn copy_to(&mut self, end: usize) {
+        self.out.push_str(&self.src[self.pos..end]);
+        self.pos = end;
+    }
+
+    /// Copies a string or regex literal delimited by `quote`, the current char is the opening quote.
+    fn quoted(&mut self, quote: char) -> Option<()> {
+        let mut chars = self.src[self.pos..].char_indices().skip(1);
+        let mut in_class = false;
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                },
+                '[' if quote == '/' => in_class = true,
+                ']' if quote == '/' => in_class = false,
+                '\n' => return None,
+                c if c == quote && !in_class => {
+                    self.copy_to(self.pos + i + c.len_utf8());
+                    return Some(());
+                },
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    /// Copies template characters up to and including the closing backtick or the next `${`.
+    fn template_chars(&mut self) -> Option<()> {
+        let mut chars = self.src[self.pos..].char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                },
+                '`' => {
+                    self.copy_to(self.pos + i + 1);
+                    self.last = Last::Operand;
+                    return Some(());
+                },
+                '$' if chars.peek().is_some_and(|&(_, c)| c == '{') => {
+                    self.copy_to(self.pos + i + 2);
+                    self.nesting.push(Nesting::TemplateSubstitution);
+                    self.last = Last::Operator;
+                    return Some(());
+                },
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    fn comment(&mut self, end: usize) {
+        let comment = &self.src[self.pos..end];
+
+        // a comment with a line break counts as one for automatic semicolon insertion,
+        // any other comment still separates the tokens around it
+        let line_break = comment.contains(is_line_terminator);
+        self.line_start |= line_break;
+
+        let keep = match self.mode {
+            Mode::Strip { preserve } => preserve.is_some_and(|prefix| comment.starts_with(prefix)),
+            Mode::HtmlToLine => true,
+        };
+
+        if keep {
+            self.copy_to(end);
+            return;
+        }
+
+        if !comment.starts_with("//") {
+            self.out.push(if line_break { '\n' } else { ' ' });
+        }
+        self.pos = end;
+    }
+
+    /// The end of the line the current char is on, without the line terminator
+    fn line_end(&self) -> usize {
+        self.src[self.pos..].find(is_line_terminator).map_or(self.src.len(), |i| self.pos + i)
+    }
+
+    fn strip(mut self) -> Option<String> {
+        while let Some(c) = self.peek(0) {
+            let start = self.pos;
+            let line_start = std::mem::replace(&mut self.line_start, false);
+
+            match c {
+                '/' if self.peek(1) == Some('/') => {
+                    let end = self.src[start..].find('\n').map_or(self.src.len(), |i| start + i);
+                    self.line_start = line_start;
+                    self.comment(end);
+                },
+                '<' if matches!(self.mode, Mode::HtmlToLine) && self.src[start..].starts_with("<!--") => {
+                    self.out.push_str("//");
+                    self.copy_to(self.line_end());
+                    self.line_start = line_start;
+                },
+                // `-->` only starts a comment at the start of a line, otherwise it is `--` followed by `>`
+                '-' if matches!(self.mode, Mode::HtmlToLine) && line_start && self.src[start..].starts_with("-->") => {
+                    self.out.push_str("//");
+                    self.copy_to(self.line_end());
+                    self.line_start = true;
+                },
+                '/' if self.peek(1) == Some('*') => {
+                    let end = start + 2 + self.src[start + 2..].find("*/")? + 2;
+                    self.line_start = line_start;
+                    self.comment(end);
+                },
+                '/' if self.last == Last::Operator => {
+                    self.quoted('/')?;
+                    let flags = self.src[self.pos..].find(|c| !is_ident_char(c)).map_or(self.src.len(), |i| self.pos + i);
+                    self.copy_to(flags);
+                    self.last = Last::Operand;
+                },
+                '"' | '\'' => {
+                    self.quoted(c)?;
+                    self.last = Last::Operand;
+                },
+                '`' => {
+                    self.copy_to(start + 1);
+                    self.template_chars()?;
+                },
+                c if c.is_whitespace() => {
+                    self.line_start = line_start || is_line_terminator(c);
+                    self.copy_to(start + c.len_utf8());
+                },
+                c if is_ident_char(c) => {
+                    // numbers are split at a `.`, which does not matter since both halves are operands
+                    let end = self.src[start..].find(|c| !is_ident_char(c)).map_or(self.src.len(), |i| start + i);
+                    let word = &self.src[start..end];
+                    self.last = if EXPRESSION_KEYWORDS.contains(&word) { Last::Operator } else { Last::Operand };
+                    self.copy_to(end);
+                },
+                '}' if matches!(self.nesting.last(), Some(Nesting::TemplateSubstitution)) => {
+                    self.nesting.pop();
+                    self.copy_to(start + 1);
+                    self.template_chars()?;
+                },
+                c => {
+                    match c {
+                        '{' => self.nesting.push(Nesting::Brace),
+                        '}' => {
+                            self.nesting.pop();
+                        },
+                        _ => (),
+                    }
+                    self.last = if matches!(c, ')' | ']') { Last::Operand } else { Last::Operator };
+                    self.copy_to(start + c.len_utf8());
+                },
+            }
+        }
+
+        Some(self.out)
+    }
+}
+
+/// Removes all comments from `src` except the ones starting with `preserve`.
+/// Returns `None` if `src` contains an unterminated literal or comment.
+pub(super) fn strip_comments(src: &str, preserve: Option<&str>) -> Option<String> {
+    Stripper {
+        src,
+        pos: 0,
+        out: String::with_capacity(src.len()),
+        mode: Mode::Strip { preserve },
+        last: Last::Operator,
+        nesting: Vec::new(),
+        line_start: true,
+    }
+    .strip()
+}
+
+/// Turns a `#!` line at the start of `src` with `hashbang` and the HTML-like comments of Annex B with `html_comments`
+/// into `//` comments, so parsers that don't support them accept the code and it means the same everywhere.
+/// Returns `None` if `src` contains an unterminated literal or comment.
+pub(super) fn comment_trivia(src: &str, hashbang: bool, html_comments: bool) -> Option<String> {
+    let mut stripper = Stripper {
+        src,
+        pos: 0,
+        out: String::with_capacity(src.len() + 2),
+        mode: Mode::HtmlToLine,
+        last: Last::Operator,
+        nesting: Vec::new(),
+        line_start: true,
+    };
+
+    // the rest of the line is a comment, whatever it contains
+    if hashbang && src.starts_with("#!") {
+        stripper.out.push_str("//");
+        stripper.copy_to(stripper.line_end());
+    }
+
+    if !html_comments {
+        stripper.copy_to(src.len());
+        return Some(stripper.out);
+    }
+
+    stripper.strip()
+}
@@ -0,0 +1,8 @@
+function Counter(props) {
+    return (
+        <>
+            <span class="count">{props.count}</span>
+            <button onClick={props.increment}>+1</button>
+        </>
+    );
+}
@@ -0,0 +1,179 @@
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+
+use super::{
+    args::{self, IncludeArgs, IncludeOptions, PathArg},
+    registry,
+};
+
+/// The arguments of `include_js_glob!`, i.e. one or more patterns optionally followed by `key = value` pairs
+pub(super) struct GlobArgs {
+    patterns: Vec<PathArg>,
+    options: IncludeOptions,
+}
+
+impl Parse for GlobArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut patterns = vec![input.parse()?];
+        patterns.extend(args::parse_more_paths(input)?);
+
+        let options = args::parse_options(input)?;
+
+        Ok(GlobArgs { patterns, options })
+    }
+}
+
+/// Recursively collects all `.js` files in `dir`, together with their path relative to `dir`.
+fn collect_js_files(dir: &Path, rel: &str, span: Span, files: &mut Vec<(String, PathBuf)>) -> syn::Result<()> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| syn::Error::new(span, format!("could not read '{}': {}", dir.display(), e)))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| syn::Error::new(span, format!("could not read '{}': {}", dir.display(), e)))?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        // paths always use `/` so the keys are the same on every platform
+        let rel = if rel.is_empty() { name } else { format!("{}/{}", rel, name) };
+
+        if path.is_dir() {
+            collect_js_files(&path, &rel, span, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "js") {
+            files.push((rel, path));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks every file in `files` and expands to a `&'static [(&'static str, &'static JSStr)]` sorted by key.
+/// The files are named like `prefix` followed by their key in `//# sourceURL=...` comments.
+fn expand_entries(
+    mut files: Vec<(String, PathBuf)>,
+    prefix: &str,
+    options: &IncludeOptions,
+    span: Span,
+) -> syn::Result<TokenStream> {
+    files.sort();
+    files.dedup();
+
+    let contents = files
+        .iter()
+        .map(|(_, path)| options.read(path, span))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    options.prepare(contents.iter().map(String::as_str), false);
+
+    let krate = &options.krate;
+    let entries = files
+        .iter()
+        .zip(contents)
+        .map(|((key, path), content)| {
+            let origin = path.display().to_string();
+            let content = options.compile(content, false, &origin, span)?;
+            let content = options.process(content, false, &origin, span)?;
+            let js = options.with_source_url(&content, &format!("{}{}", prefix, key), registry::Kind::Script, |content| {
+                quote! { unsafe { #krate::JSStr::new_unchecked(#content) } }
+            });
+
+            Ok(quote! {
+                (#key, #js)
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let tracks = files.iter().map(|(_, path)| super::track(path)).chain(Some(options.track()));
+
+    // the slice is put into a const so it is `'static` regardless of where the macro is used
+    Ok(quote! {
+        {
+            #(#tracks)*
+            const FILES: &[(&str, &#krate::JSStr)] = &[#(#entries),*];
+            FILES
+        }
+    })
+}
+
+pub(super) fn expand_include_js_dir(args: &IncludeArgs) -> syn::Result<TokenStream> {
+    let span = args.path.span;
+
+    if let Some(path) = args.more.first() {
+        return Err(syn::Error::new(path.span, "only a single directory can be included"));
+    }
+
+    args.options.reject_source_map("include_js_dir!", span)?;
+
+    let dir = args.resolve_path()?;
+
+    if !dir.is_dir() {
+        return Err(syn::Error::new(span, format!("'{}' is not a directory", dir.display())));
+    }
+
+    let mut files = Vec::new();
+    collect_js_files(&dir, "", span, &mut files)?;
+
+    let prefix = format!("{}/", args.path.value.trim_end_matches('/'));
+    expand_entries(files, &prefix, &args.options, span)
+}
+
+pub(super) fn expand_include_js_glob(args: &GlobArgs) -> syn::Result<TokenStream> {
+    args.options.reject_source_map("include_js_glob!", args.patterns[0].span)?;
+    let crate_root = args.options.relative_to.base_dir(args.patterns[0].span)?;
+
+    let mut includes = Vec::new();
+    let mut excludes = Vec::new();
+
+    for pattern in &args.patterns {
+        match pattern.value.strip_prefix('!') {
+            Some(exclude) => excludes.push(
+                glob::Pattern::new(exclude)
+                    .map_err(|e| syn::Error::new(pattern.span, format!("invalid pattern: {}", e)))?,
+            ),
+            None => includes.push(pattern),
+        }
+    }
+
+    if includes.is_empty() {
+        return Err(syn::Error::new(args.patterns[0].span, "at least one pattern has to include files"));
+    }
+
+    let mut files = Vec::new();
+
+    for pattern in includes {
+        if !args.options.allow_absolute {
+            pattern.reject_absolute(true)?;
+        }
+
+        let full_pattern = crate_root.join(&pattern.value);
+        let paths = glob::glob(&full_pattern.to_string_lossy())
+            .map_err(|e| syn::Error::new(pattern.span, format!("invalid pattern: {}", e)))?;
+
+        for path in paths {
+            let path = path.map_err(|e| {
+                syn::Error::new(pattern.span, format!("could not read '{}': {}", e.path().display(), e.error()))
+            })?;
+
+            if !path.is_file() {
+                continue;
+            }
+
+            // paths always use `/` so the keys are the same on every platform
+            let rel = path
+                .strip_prefix(&crate_root)
+                .unwrap_or(&path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            if !excludes.iter().any(|exclude| exclude.matches(&rel)) {
+                files.push((rel, path));
+            }
+        }
+    }
+
+    expand_entries(files, "", &args.options, args.patterns[0].span)
+}
@@ -0,0 +1,83 @@
+//! Decoding of included files that are not UTF-8.
+//!
+//! The encoding of a file is taken from its byte order mark if it has one, or given with `encoding = "..."`.
+//! Files are turned into UTF-8 before anything else is done with them, so the rest of the macros only ever
+//! see a `String`.
+
+use std::fmt;
+
+use syn::{
+    parse::{Parse, ParseStream},
+    LitStr,
+};
+
+/// The encoding of an included file
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Parse for Encoding {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lit = input.parse::<LitStr>()?;
+
+        match lit.value().to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+            _ => Err(syn::Error::new(lit.span(), "expected one of \"utf-8\", \"utf-16le\" or \"utf-16be\"")),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::Utf8 => f.write_str("UTF-8"),
+            Encoding::Utf16Le => f.write_str("UTF-16LE"),
+            Encoding::Utf16Be => f.write_str("UTF-16BE"),
+        }
+    }
+}
+
+impl Encoding {
+    /// The encoding named by the byte order mark at the start of `bytes`, UTF-8 if there is none
+    pub(super) fn sniff(bytes: &[u8]) -> Self {
+        match bytes {
+            [0xff, 0xfe, ..] => Encoding::Utf16Le,
+            [0xfe, 0xff, ..] => Encoding::Utf16Be,
+            _ => Encoding::Utf8,
+        }
+    }
+
+    /// Decodes `bytes` to UTF-8. A UTF-8 byte order mark is kept, so the result is the file unchanged,
+    /// a UTF-16 one is removed as it only names the encoding.
+    pub(super) fn decode(self, bytes: Vec<u8>) -> Result<String, String> {
+        let from_bytes = match self {
+            Encoding::Utf8 => return String::from_utf8(bytes).map_err(|e| e.utf8_error().to_string()),
+            Encoding::Utf16Le => u16::from_le_bytes,
+            Encoding::Utf16Be => u16::from_be_bytes,
+        };
+
+        if !bytes.len().is_multiple_of(2) {
+            return Err(format!("odd number of bytes ({})", bytes.len()));
+        }
+
+        let units = bytes.chunks_exact(2).map(|unit| from_bytes([unit[0], unit[1]]));
+        let mut code = String::with_capacity(bytes.len() / 2);
+        let mut offset = 0;
+
+        for c in char::decode_utf16(units) {
+            match c {
+                Ok('\u{feff}') if offset == 0 => {},
+                Ok(c) => code.push(c),
+                Err(e) => return Err(format!("unpaired surrogate {:#06x} at byte {}", e.unpaired_surrogate(), offset)),
+            }
+            offset += 2 * c.map_or(1, char::len_utf16);
+        }
+
+        Ok(code)
+    }
+}
@@ -0,0 +1,275 @@
+use swc_common::{Span, Spanned};
+use swc_ecma_ast::*;
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// Formats a version like it is usually written, i.e. `ES5` or `ES2017`
+pub(super) fn display(version: u16) -> String {
+    format!("ES{}", version)
+}
+
+/// A construct that is newer than the targeted ECMAScript version
+pub(super) struct Violation {
+    pub(super) span: Span,
+    pub(super) feature: &'static str,
+    pub(super) version: u16,
+}
+
+/// Returns the first construct in `program` that needs an ECMAScript version newer than `target`.
+pub(super) fn check<N: VisitWith<Checker>>(program: &N, target: u16) -> Option<Violation> {
+    let mut checker = Checker { target, fn_depth: 0, violation: None };
+    program.visit_with(&mut checker);
+    checker.violation
+}
+
+pub(super) struct Checker {
+    target: u16,
+    /// how many functions the visitor is currently in, used to detect top-level `await`
+    fn_depth: usize,
+    violation: Option<Violation>,
+}
+
+impl Checker {
+    fn require(&mut self, span: Span, feature: &'static str, version: u16) {
+        if version > self.target && self.violation.is_none() {
+            self.violation = Some(Violation { span, feature, version });
+        }
+    }
+}
+
+impl Visit for Checker {
+    fn visit_script(&mut self, node: &Script) {
+        if node.shebang.is_some() {
+            self.require(node.span, "hashbang comments", 2023);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_module(&mut self, node: &Module) {
+        if node.shebang.is_some() {
+            self.require(node.span, "hashbang comments", 2023);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_module_decl(&mut self, node: &ModuleDecl) {
+        self.require(node.span(), "modules", 2015);
+        node.visit_children_with(self);
+    }
+
+    fn visit_var_decl(&mut self, node: &VarDecl) {
+        match node.kind {
+            VarDeclKind::Var => {},
+            VarDeclKind::Let => self.require(node.span, "`let` declarations", 2015),
+            VarDeclKind::Const => self.require(node.span, "`const` declarations", 2015),
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_function(&mut self, node: &Function) {
+        match (node.is_async, node.is_generator) {
+            (true, true) => self.require(node.span, "async generators", 2018),
+            (true, false) => self.require(node.span, "async functions", 2017),
+            (false, true) => self.require(node.span, "generators", 2015),
+            (false, false) => {},
+        }
+
+        self.fn_depth += 1;
+        node.visit_children_with(self);
+        self.fn_depth -= 1;
+    }
+
+    fn visit_arrow_expr(&mut self, node: &ArrowExpr) {
+        self.require(node.span, "arrow functions", 2015);
+        if node.is_async {
+            self.require(node.span, "async functions", 2017);
+        }
+
+        self.fn_depth += 1;
+        node.visit_children_with(self);
+        self.fn_depth -= 1;
+    }
+
+    fn visit_class(&mut self, node: &Class) {
+        self.require(node.span, "classes", 2015);
+        node.visit_children_with(self);
+    }
+
+    fn visit_class_prop(&mut self, node: &ClassProp) {
+        self.require(node.span, "class fields", 2022);
+        node.visit_children_with(self);
+    }
+
+    fn visit_private_name(&mut self, node: &PrivateName) {
+        self.require(node.span, "private class members", 2022);
+        node.visit_children_with(self);
+    }
+
+    fn visit_static_block(&mut self, node: &StaticBlock) {
+        self.require(node.span, "class static blocks", 2022);
+        node.visit_children_with(self);
+    }
+
+    fn visit_super(&mut self, node: &Super) {
+        self.require(node.span, "`super`", 2015);
+    }
+
+    fn visit_meta_prop_expr(&mut self, node: &MetaPropExpr) {
+        match node.kind {
+            MetaPropKind::NewTarget => self.require(node.span, "`new.target`", 2015),
+            MetaPropKind::ImportMeta => self.require(node.span, "`import.meta`", 2020),
+        }
+    }
+
+    fn visit_callee(&mut self, node: &Callee) {
+        if let Callee::Import(import) = node {
+            self.require(import.span, "dynamic `import()`", 2020);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_await_expr(&mut self, node: &AwaitExpr) {
+        if self.fn_depth == 0 {
+            self.require(node.span, "top-level `await`", 2022);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_tpl(&mut self, node: &Tpl) {
+        self.require(node.span, "template literals", 2015);
+        node.visit_children_with(self);
+    }
+
+    fn visit_tagged_tpl(&mut self, node: &TaggedTpl) {
+        self.require(node.span, "tagged templates", 2015);
+        node.visit_children_with(self);
+    }
+
+    fn visit_bin_expr(&mut self, node: &BinExpr) {
+        match node.op {
+            BinaryOp::Exp => self.require(node.span, "the exponentiation operator", 2016),
+            BinaryOp::NullishCoalescing => self.require(node.span, "nullish coalescing", 2020),
+            _ => {},
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_assign_expr(&mut self, node: &AssignExpr) {
+        match node.op {
+            AssignOp::ExpAssign => self.require(node.span, "the exponentiation operator", 2016),
+            AssignOp::AndAssign | AssignOp::OrAssign | AssignOp::NullishAssign => {
+                self.require(node.span, "logical assignment", 2021)
+            },
+            _ => {},
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_opt_chain_expr(&mut self, node: &OptChainExpr) {
+        self.require(node.span, "optional chaining", 2020);
+        node.visit_children_with(self);
+    }
+
+    fn visit_object_pat(&mut self, node: &ObjectPat) {
+        self.require(node.span, "destructuring", 2015);
+        node.visit_children_with(self);
+    }
+
+    fn visit_object_pat_prop(&mut self, node: &ObjectPatProp) {
+        if let ObjectPatProp::Rest(rest) = node {
+            self.require(rest.span, "object rest properties", 2018);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_array_pat(&mut self, node: &ArrayPat) {
+        self.require(node.span, "destructuring", 2015);
+        node.visit_children_with(self);
+    }
+
+    fn visit_assign_pat(&mut self, node: &AssignPat) {
+        self.require(node.span, "default parameters", 2015);
+        node.visit_children_with(self);
+    }
+
+    fn visit_rest_pat(&mut self, node: &RestPat) {
+        self.require(node.span, "rest parameters", 2015);
+        node.visit_children_with(self);
+    }
+
+    fn visit_expr_or_spread(&mut self, node: &ExprOrSpread) {
+        if let Some(span) = node.spread {
+            self.require(span, "spread syntax", 2015);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_prop_or_spread(&mut self, node: &PropOrSpread) {
+        if let PropOrSpread::Spread(spread) = node {
+            self.require(spread.dot3_token, "object spread properties", 2018);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_prop(&mut self, node: &Prop) {
+        match node {
+            Prop::Shorthand(ident) => self.require(ident.span, "shorthand properties", 2015),
+            Prop::Method(method) => self.require(method.function.span, "shorthand methods", 2015),
+            _ => {},
+        }
+        node.visit_children_with(self);
+    }
+
+    // not `visit_computed_prop_name`, swc uses `ComputedPropName` for member accesses like `a[b]` as well
+    fn visit_prop_name(&mut self, node: &PropName) {
+        if let PropName::Computed(computed) = node {
+            self.require(computed.span, "computed property names", 2015);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_for_of_stmt(&mut self, node: &ForOfStmt) {
+        if node.is_await {
+            self.require(node.span, "`for await`", 2018);
+        }
+        self.require(node.span, "`for of` loops", 2015);
+        node.visit_children_with(self);
+    }
+
+    fn visit_catch_clause(&mut self, node: &CatchClause) {
+        if node.param.is_none() {
+            self.require(node.span, "optional catch binding", 2019);
+        }
+        node.visit_children_with(self);
+    }
+
+    fn visit_big_int(&mut self, node: &BigInt) {
+        self.require(node.span, "BigInt literals", 2020);
+    }
+
+    fn visit_number(&mut self, node: &Number) {
+        let raw = node.raw.as_deref().unwrap_or_default();
+
+        if raw.contains('_') {
+            self.require(node.span, "numeric separators", 2021);
+        }
+        if raw.starts_with("0b") || raw.starts_with("0B") || raw.starts_with("0o") || raw.starts_with("0O") {
+            self.require(node.span, "binary and octal literals", 2015);
+        }
+    }
+
+    fn visit_regex(&mut self, node: &Regex) {
+        for flag in node.flags.chars() {
+            match flag {
+                'u' | 'y' => self.require(node.span, "the regex flags `u` and `y`", 2015),
+                's' => self.require(node.span, "the regex flag `s`", 2018),
+                'd' => self.require(node.span, "the regex flag `d`", 2022),
+                'v' => self.require(node.span, "the regex flag `v`", 2024),
+                _ => {},
+            }
+        }
+
+        if node.exp.contains("(?<") {
+            self.require(node.span, "regex named groups and lookbehind", 2018);
+        }
+    }
+}
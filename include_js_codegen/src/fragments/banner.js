@@ -0,0 +1,2 @@
+/*! some-plugin v1.0 | MIT License */
+var DEBUG = false;
@@ -0,0 +1 @@
+some_function();
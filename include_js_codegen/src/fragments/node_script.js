@@ -0,0 +1,5 @@
+#!/usr/bin/env node
+<!-- hidden from old browsers
+var tag = "<!-- not a comment -->";
+var n = 2; n-->0;
+-->
@@ -0,0 +1,2 @@
+﻿var answer = 42;
+var question = null;
\ No newline at end of file
@@ -0,0 +1,4 @@
+(width, name) => {
+    const actor = global.get_window_actors().find(actor => actor.meta_window.get_wm_class() === name);
+    actor.meta_window.move_resize_frame(false, 0, 0, width, width * 9 / 16);
+}
@@ -0,0 +1,6 @@
+/*! some-lib v1.0 | MIT License */
+// adds two numbers
+function add(a, b) {
+    return a /* left */ + b; // right
+}
+var url = "http://example.com";
@@ -0,0 +1 @@
+const size = settings?.window?.size ?? { width: 800, height: 600 };
@@ -0,0 +1,3 @@
+function clamp(x, min, max) {
+    return Math.min(Math.max(x, min), max);
+}
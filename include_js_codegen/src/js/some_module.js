@@ -0,0 +1,5 @@
+import { some_function } from './some_script.js';
+
+export default function () {
+    return some_function();
+}
@@ -0,0 +1,3 @@
+function some_function() {
+    return 42;
+}
@@ -1,162 +1,2486 @@
 use std::{
-    collections::HashMap,
-    path::{Path, PathBuf},
+    collections::{BTreeSet, HashMap},
+    path::{Component, Path, PathBuf},
 };
 
 use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use include_js_core::{BundleError, ParseError};
+use base64::{prelude::BASE64_STANDARD, Engine};
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, LitStr};
+use sha2::{Digest, Sha256, Sha384};
+use syn::{parse_macro_input, Data, DeriveInput};
+
+use encoding::Encoding;
 
 #[cfg(feature = "template")]
 use handlebars::Handlebars;
 
+#[cfg(feature = "parser-swc")]
+mod es_version;
+mod args;
+mod cache;
+mod comments;
+mod dir;
+mod encoding;
+mod lint;
+mod literal;
+mod manifest;
+mod parser;
+mod paths;
+mod quasi;
+mod registry;
+mod source_map;
+
 #[cfg(feature = "template")]
 mod template;
+mod transform;
+
+fn manifest_dir(span: Span) -> syn::Result<PathBuf> {
+    std::env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .map_err(|_| syn::Error::new(span, "CARGO_MANIFEST_DIR is not set"))
+}
 
-fn read_to_string_relative(rel_path: &Path) -> String {
-    let crate_root = std::env::var("CARGO_MANIFEST_DIR").unwrap();
-    let path = PathBuf::from(crate_root).join(rel_path);
+/// Resolves `rel_path` relative to $CARGO_MANIFEST_DIR, the path has to exist.
+fn resolve_relative(rel_path: &Path, span: Span) -> syn::Result<PathBuf> {
+    resolve(&manifest_dir(span)?, rel_path, span)
+}
+
+/// Resolves `rel_path` relative to `base`, the path has to exist. Otherwise the error lists the paths that were
+/// probably meant, e.g. because the case of a file name is wrong.
+fn resolve(base: &Path, rel_path: &Path, span: Span) -> syn::Result<PathBuf> {
+    let path = base.join(rel_path);
 
     if !path.exists() {
-        panic!("'{}' does not exist", path.display());
+        let mut message = format!("'{}' does not exist", path.display());
+        let candidates = paths::near_misses(base, rel_path);
+
+        if !candidates.is_empty() {
+            let candidates: Vec<_> = candidates.iter().map(|path| format!("'{}'", path.display())).collect();
+            message.push_str(&format!(", did you mean {}?", candidates.join(" or ")));
+        }
+
+        return Err(syn::Error::new(span, message));
+    }
+
+    Ok(path)
+}
+
+/// Makes cargo rebuild the crate when the file at `path` changes. Proc macros can't declare dependencies on stable,
+/// so the file is included with `include_bytes!` into a constant that is never used.
+fn track(path: &Path) -> TokenStream2 {
+    let path = path.to_string_lossy();
+    quote! {
+        const _: &[u8] = ::core::include_bytes!(#path);
     }
+}
+
+/// Like `track` but for a path relative to $CARGO_MANIFEST_DIR.
+fn track_relative(rel_path: &Path, span: Span) -> syn::Result<TokenStream2> {
+    Ok(track(&resolve_relative(rel_path, span)?))
+}
 
-    std::fs::read_to_string(path).expect("could not read file")
+/// Reads the file at `path` and decodes it with `encoding`, or with the encoding named by its byte order mark.
+/// Returns the encoding the file was decoded with as well.
+fn read_encoded(path: &Path, encoding: Option<Encoding>, span: Span) -> syn::Result<(String, Encoding)> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| syn::Error::new(span, format!("could not read '{}': {}", path.display(), e)))?;
+    let encoding = encoding.unwrap_or_else(|| Encoding::sniff(&bytes));
+    let code = encoding
+        .decode(bytes)
+        .map_err(|e| syn::Error::new(span, format!("could not decode '{}' as {}: {}", path.display(), encoding, e)))?;
+
+    Ok((code, encoding))
+}
+
+fn read_to_string(path: &Path, span: Span) -> syn::Result<String> {
+    read_encoded(path, None, span).map(|(code, _)| code)
+}
+
+fn read_to_string_relative(rel_path: &Path, span: Span) -> syn::Result<String> {
+    read_to_string(&resolve_relative(rel_path, span)?, span)
+}
+
+/// Parses `content` with boa and converts a syntax error into a compile error at `span`.
+fn check_syntax(content: &str, origin: &str, span: Span) -> syn::Result<()> {
+    include_js_core::JSStr::new(content)
+        .map(|_| ())
+        .map_err(|e| syntax_error(e.into_parse_error(), origin, span))
+}
+
+/// Converts a syntax error in `origin` into a compile error at `span`.
+fn syntax_error(e: ParseError, origin: &str, span: Span) -> syn::Error {
+    syn::Error::new(span, format!("syntax error in '{}': {}", origin, e))
 }
 
 /// Simmilar to `include_str!` from the stdlib but instead of including arbitrary files as `&str`
 /// it includes sytactically valid javascript from files as `&JSStr`. If the file contains invalid
 /// Javascript you will get a compiletime error.
 /// 
-/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR.
+/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR, unless `relative_to = "file"` is given.
+/// Then the path is relative to the directory of the source file containing the invocation, which also works
+/// for macros re-exported from other crates in a workspace. If the compiler does not know the source file,
+/// e.g. because the invocation was generated by another macro without a file, $CARGO_MANIFEST_DIR is used instead.
+///
+/// `${NAME}` in the path is replaced with the value of the environment variable `NAME` at compiletime,
+/// e.g. `include_js!("${OUT_DIR}/bundle.js")` includes a file generated by a build script. This works for the
+/// paths of all include macros and the `template` attribute. Like for `include_str!`, the path can also be
+/// built with `concat!` and `env!`, e.g. `include_js!(concat!(env!("OUT_DIR"), "/bundle.js"))`.
+///
+/// Paths can be written with `/` or `\\` as separators, both work on every platform. Absolute paths only exist on
+/// the machine they were written on, so they are rejected unless `allow_absolute` is given. Paths that start with
+/// an environment variable don't count as absolute. If the file does not exist, the error lists the files with
+/// a similar name, e.g. with a different case, which only matters on case-sensitive filesystems.
+///
+/// Several files can be included at once with `include_js!("a.js", "b.js", "c.js")`. They are checked on their own,
+/// concatenated in the given order and the result is checked again. A semicolon is put on its own line between
+/// files that don't end with one, so the end of a file can't continue the statement at the end of the previous one.
+/// All options apply to the concatenated code.
+///
+/// With `first_of(...)` instead of a path the first of the given files that exists is included, e.g.
+/// `include_js!(first_of("dist/app.min.js", "dist/app.js"))` for an artifact that is only minified in some builds.
+/// It is an error if none of them exist. Like `include_js_opt!` the choice is made when the crate is compiled,
+/// so the crate is not rebuilt when a file that is tried first appears later, unless a build script generates it.
+///
+/// The file is validated with boa by default. boa does not understand some newer syntax like
+/// optional chaining, with the `parser-swc` feature the parser can be switched to swc per invocation
+/// with `include_js!("path", parser = "swc")`.
+///
+/// If the script runs in an environment that only supports an older version of Javascript, the version can be
+/// given with `include_js!("path", es = 2017)` and everything newer than ES2017 becomes a compile error.
+/// Valid versions are `5` and the years from `2015` to `2024`. This check is done by swc, so it also needs
+/// the `parser-swc` feature.
+///
+/// Newer Javascript can also be transpiled to an older version at compiletime with `target = es5`, or `es2015`
+/// to `es2022`, e.g. arrow functions, classes, `let` and `const` or template literals. The helpers the transpiled
+/// code needs are put into it, but built-ins like `Promise` or `Object.entries` are not polyfilled. It is a compile
+/// error if something can't be transpiled. boa rejects the code of transpiled classes, so the transpiled code is
+/// validated with swc. This needs the `transpile` feature.
+///
+/// With the flag `strict`, e.g. `include_js!("path", strict)`, the script is parsed in strict mode, as if it
+/// started with `"use strict";`. Modules are always strict. boa does not support parsing in strict mode, so
+/// `strict` uses swc as well and needs the `parser-swc` feature.
+///
+/// Comments can be removed from the included code with `strip_comments = true`, comments starting with
+/// the prefix given by `preserve` are kept, e.g. `preserve = "/*!"` for license banners.
+///
+/// With `normalize` the included files are normalized before they are validated and embedded: a byte order mark
+/// at the start is removed, `\r\n` and lone `\r` line endings are turned into `\n` and a newline is added at the end
+/// if there is none, so the constant is the same no matter how the file was checked out. `normalize(bom, newlines)`
+/// only does some of that, the names are `bom`, `newlines` and `trailing_newline`. This applies to banners and
+/// footers as well.
+///
+/// Files that start with a UTF-16 byte order mark are decoded as UTF-16 and embedded as UTF-8. Files without one are
+/// expected to be UTF-8, unless the encoding is given with `encoding = "utf-16le"`, `"utf-16be"` or `"utf-8"`.
+/// Like `normalize` this applies to banners and footers, and it happens before the code is validated.
+///
+/// Scripts meant to be run as programs, e.g. by node, can start with a `#!` line, and legacy scripts can contain
+/// the HTML-like comments `<!--` and `-->` of Annex B of the specification. The parsers reject both, so
+/// `tolerate(hashbang, html_comments)`, or just `tolerate` for both, turns them into `//` comments when the file is read.
+/// The comments mean the same, but the code can be parsed and embedded anywhere. Modules can't contain HTML-like comments.
+///
+/// With `wrap = iife` the code is wrapped in `(function () { ... })();`, so its declarations don't end up in
+/// the global scope, and `prelude = "..."` puts code in front of it, e.g. `prelude = "'use strict';"`. With both,
+/// the prelude is put at the start of the function, so directives like `'use strict'` apply to the included code.
+/// The result is validated again. Modules can't be wrapped, as their imports and exports have to be at the top level.
+/// boa rejects all function declarations in strict mode, so a `'use strict'` prelude needs `parser = "swc"`.
+///
+/// `banner = "..."` and `footer = "..."` put the content of other files in front of and after the included code,
+/// outside of the wrapper if there is one, e.g. for version comments or polyfills. Their paths are resolved like
+/// the path of the included file. The files are checked on their own and together with the included code.
+///
+/// JSX is compiled to plain Javascript with `jsx(pragma = "h", pragma_frag = "Fragment")`, where `pragma` and
+/// `pragma_frag` are the functions elements and fragments are created with, `React.createElement` and `React.Fragment`
+/// if they are omitted. The compiled code is validated instead of the file. This needs the `jsx` feature.
+///
+/// With `source_url` the code gets a `//# sourceURL=include_js:<path>` comment at the end, so it shows up with
+/// the path of the included file in the devtools of browsers and engines instead of as anonymous code.
+/// With `source_url = debug` the comment is only added in builds with debug assertions, i.e. not in release builds.
+///
+/// With `source_map = inline` a source map is put at the end of the code as a `//# sourceMappingURL=data:...`
+/// comment, like `include_js_with_map!` creates it. It can't be combined with `strip_comments`.
+///
+/// With `max_bytes = ...` the build fails if the included code is larger than the given number of bytes.
+/// The size is checked after all other transformations, like stripping comments, but before an inline source map
+/// or a `sourceURL` comment is added.
+///
+/// Constructs that should never end up in the included code can be denied with `deny(...)`, e.g.
+/// `include_js!("path", deny(eval, with, debugger, asi))`. `eval` denies every reference to the global `eval`,
+/// properties named `eval` like `window.eval` or `globalThis["eval"]` and calls of the `Function` constructor,
+/// `with` and `debugger` the statements and `asi` statements that rely on automatic semicolon insertion.
+/// The lints only look at the syntax, so code that computes the name, like `window["ev" + "al"]`, or gets
+/// the constructor another way, like `(function () {}).constructor`, is not detected.
+/// Globals that don't exist where the script runs can be forbidden with `forbid_globals = ["document", "localStorage"]`,
+/// which fails the build if the code references them without declaring them itself. Accesses through another object, like `window.document`, are not detected.
+/// Conversely, `require_fn = ["init", "teardown"]` fails the build if the code does not define these functions
+/// at the top level, either as function declarations or as variables initialized with a function.
+/// Like `es` the lints walk the syntax tree of swc and need the `parser-swc` feature.
+///
+/// Code that was validated successfully is cached by its hash, so unchanged files are not parsed again, in memory for
+/// the compilation of a crate and in the directory `$INCLUDE_JS_CACHE_DIR` between builds, or in `$OUT_DIR` if the
+/// crate has a build script. An empty `INCLUDE_JS_CACHE_DIR` keeps the cache in memory only. When several files are
+/// included at once, e.g. by `include_js_dir!`, they are parsed in parallel.
+///
+/// With the `registry` feature every included script registers itself with its path, size and hash, so
+/// `include_js::registry()` can list all scripts embedded into the binary at runtime, e.g. for auditing.
+/// This applies to the code of all include macros and the sources of templates.
+///
+/// With the `manifest` feature the path, kind, size and hash of every included script are written to
+/// `$OUT_DIR/include_js_manifest.json` as well, so release tooling can compare the scripts embedded into two
+/// versions of a binary without running it. `OUT_DIR` is only set for crates with a build script, an empty
+/// `build.rs` is enough. Scripts that are only embedded in some builds, e.g. with `source_url = debug`, are listed
+/// with the `cfg` they are embedded under.
+///
+/// The generated code refers to the types of this crate as `::include_js::...`. Crates that re-export the
+/// macros can change that path with `crate = "..."`, e.g. `crate = "::my_crate::js"`. With the `registry` feature
+/// the path has to provide the hidden `__private` module of include_js as well.
 ///
 /// # Examples
 /// 
-/// ```no_run
+/// ```
 /// use include_js::{JSStr, include_js};
 ///
 /// const JS: &JSStr = include_js!("src/js/some_script.js");
 /// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// # #[cfg(feature = "parser-swc")]
+/// const JS: &JSStr = include_js!("src/js/modern_script.js", parser = "swc");
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// # #[cfg(feature = "transpile")]
+/// const JS: &JSStr = include_js!("src/js/modern_script.js", target = es5);
+/// # #[cfg(feature = "transpile")]
+/// assert!(JS.as_str().contains("var size = (_ref = (_settings = settings) === null || _settings === void 0 ? void 0 :"));
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// const JS: &JSStr = include_js!("src/js/licensed_script.js", strip_comments = true, preserve = "/*!");
+/// assert_eq!(JS.as_str(), "/*! some-lib v1.0 | MIT License */\n\nfunction add(a, b) {\n    return a   + b; \n}\nvar url = \"http://example.com\";\n");
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// const JS: &JSStr = include_js!("src/fragments/windows_script.js", normalize);
+/// assert_eq!(JS.as_str(), "var answer = 42;\nvar question = null;\n");
+///
+/// const NO_BOM: &JSStr = include_js!("src/fragments/windows_script.js", normalize(bom));
+/// assert_eq!(NO_BOM.as_str(), "var answer = 42;\r\nvar question = null;");
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// const JS: &JSStr = include_js!("src/fragments/utf16_script.js");
+/// assert_eq!(JS.as_str(), "var greeting = \"grüß dich\";\n");
+///
+/// const LE: &JSStr = include_js!("src/fragments/utf16_script.js", encoding = "utf-16le");
+/// assert_eq!(JS.as_str(), LE.as_str());
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// const JS: &JSStr = include_js!("src/fragments/node_script.js", tolerate);
+/// assert_eq!(
+///     JS.as_str(),
+///     "//#!/usr/bin/env node\n//<!-- hidden from old browsers\nvar tag = \"<!-- not a comment -->\";\nvar n = 2; n-->0;\n//-->\n",
+/// );
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// const JS: &JSStr = include_js!("src\\js\\some_script.js");
+///
+/// const FIRST: &JSStr = include_js!(first_of("src/js/some_script.min.js", "src/js/some_script.js"));
+/// assert_eq!(JS.as_str(), FIRST.as_str());
+/// ```
+///
+/// ```compile_fail
+/// use include_js::{JSStr, include_js};
+///
+/// const JS: &JSStr = include_js!("/usr/share/js/some_script.js");
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// const JS: &JSStr = include_js!("src/js/some_script.js", wrap = iife);
+/// assert_eq!(JS.as_str(), "(function () {\nfunction some_function() {\n    return 42;\n}\n})();\n");
+///
+/// # #[cfg(feature = "parser-swc")]
+/// const STRICT: &JSStr = include_js!("src/js/some_script.js", wrap = iife, prelude = "'use strict';", parser = "swc");
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// const JS: &JSStr = include_js!("src/js/some_script.js", banner = "src/fragments/banner.js", footer = "src/fragments/footer.js");
+/// assert!(JS.as_str().starts_with("/*! some-plugin v1.0 | MIT License */\nvar DEBUG = false;\nfunction some_function() {"));
+/// assert!(JS.as_str().ends_with("}\nsome_function();\n"));
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// # #[cfg(feature = "jsx")]
+/// const JS: &JSStr = include_js!("src/components/counter.jsx", jsx(pragma = "h", pragma_frag = "Fragment"));
+/// # #[cfg(feature = "jsx")]
+/// assert!(JS.as_str().contains("return /*#__PURE__*/ h(Fragment, null, /*#__PURE__*/ h(\"span\", {"));
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// const JS: &JSStr = include_js!("src/js/scripts/greet.js", "src/js/scripts/util/clamp.js");
+/// assert_eq!(JS.as_str(), "function greet(name) {\n    return \"Hello \" + name;\n}\n;\nfunction clamp(x, min, max) {\n    return Math.min(Math.max(x, min), max);\n}\n");
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// const JS: &JSStr = include_js!("${CARGO_MANIFEST_DIR}/src/js/some_script.js");
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// macro_rules! include_script {
+///     ($name:expr) => {
+///         include_js!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/js/", $name, ".js"))
+///     };
+/// }
+///
+/// const JS: &JSStr = include_script!("some_script");
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// // relative to the file this doc comment is in
+/// const JS: &JSStr = include_js!("js/some_script.js", relative_to = "file");
+/// ```
+///
+/// ```
+/// const JS: &include_js::JSStr = include_js::include_js!("src/js/some_script.js");
+/// ```
+///
+/// ```
+/// mod js {
+///     pub use include_js::{include_js, JSStr};
+/// #   #[cfg(feature = "registry")]
+/// #   pub use include_js::__private;
+/// }
+///
+/// const JS: &js::JSStr = js::include_js!("src/js/some_script.js", crate = "js");
+/// ```
+///
+#[cfg_attr(feature = "parser-swc", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "parser-swc"), doc = "```ignore")]
+/// use include_js::{JSStr, include_js};
+///
+/// // optional chaining and nullish coalescing are ES2020
+/// const JS: &JSStr = include_js!("src/js/modern_script.js", es = 2017);
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// const JS: &JSStr = include_js!("src/js/some_script.js", source_url);
+/// assert!(JS.as_str().ends_with("}\n//# sourceURL=include_js:src/js/some_script.js\n"));
+///
+/// const DEBUG_ONLY: &JSStr = include_js!("src/js/some_script.js", source_url = debug);
+/// assert_eq!(DEBUG_ONLY.as_str().contains("sourceURL"), cfg!(debug_assertions));
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// const JS: &JSStr = include_js!("src/js/some_script.js", max_bytes = 64);
+/// ```
+///
+/// ```compile_fail
+/// use include_js::{JSStr, include_js};
+///
+/// // the file is 44 bytes
+/// const JS: &JSStr = include_js!("src/js/some_script.js", max_bytes = 32);
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js};
+///
+/// # #[cfg(feature = "parser-swc")]
+/// const JS: &JSStr = include_js!("src/js/some_script.js", strict, deny(eval, with, debugger, asi));
+///
+/// # #[cfg(feature = "parser-swc")]
+/// const GJS: &JSStr = include_js!("src/js/some_script.js", forbid_globals = ["document", "localStorage"]);
+///
+/// # #[cfg(feature = "parser-swc")]
+/// const PLUGIN: &JSStr = include_js!("src/js/some_script.js", require_fn = ["some_function"]);
+/// ```
+///
+#[cfg_attr(feature = "parser-swc", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "parser-swc"), doc = "```ignore")]
+/// use include_js::{JSStr, include_js};
+///
+/// // the script only defines `some_function`
+/// const PLUGIN: &JSStr = include_js!("src/js/some_script.js", require_fn = ["init", "teardown"]);
+/// ```
+///
+#[cfg_attr(feature = "parser-swc", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "parser-swc"), doc = "```ignore")]
+/// use include_js::{JSStr, include_js};
+///
+/// // the script calls `eval`
+/// const JS: &JSStr = include_js!("src/js/eval_script.js", deny(eval));
+/// ```
+///
+#[cfg_attr(feature = "parser-swc", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "parser-swc"), doc = "```ignore")]
+/// use include_js::{JSStr, js_str};
+///
+/// const JS: &JSStr = js_str!("window.eval(code);", deny(eval));
+/// ```
+///
+#[cfg_attr(feature = "parser-swc", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "parser-swc"), doc = "```ignore")]
+/// use include_js::{JSStr, js_str};
+///
+/// const JS: &JSStr = js_str!("globalThis[\"eval\"](code);", deny(eval));
+/// ```
+///
+#[cfg_attr(feature = "parser-swc", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "parser-swc"), doc = "```ignore")]
+/// use include_js::{JSStr, js_str};
+///
+/// const JS: &JSStr = js_str!("var f = new Function(\"a\", \"return a;\");", deny(eval));
+/// ```
+///
+#[cfg_attr(feature = "parser-swc", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "parser-swc"), doc = "```ignore")]
+/// use include_js::{JSStr, js_str};
+///
+/// const JS: &JSStr = js_str!("var f = Function(\"return this;\");", deny(eval));
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, js_str};
+///
+/// // only the global is denied, other names containing `eval` are fine
+/// # #[cfg(feature = "parser-swc")]
+/// const JS: &JSStr = js_str!("var evaluate = x.evaluation; var f = function () {};", deny(eval));
+/// ```
 #[proc_macro]
 pub fn include_js(item: TokenStream) -> TokenStream {
-    let input_path = parse_macro_input!(item as LitStr).value();
+    let args = parse_macro_input!(item as args::IncludeArgs);
 
-    let content = read_to_string_relative(Path::new(&input_path));
-    let _ = boa::parse(&content, false).expect("syntax error");
+    expand_include_js(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_include_js(args: &args::IncludeArgs) -> syn::Result<TokenStream2> {
+    let args::Script { content, track, .. } = args.load_script()?;
+    let krate = &args.options.krate;
+    let js = args.options.with_source_url(&content, &args.path.value, registry::Kind::Script, |content| {
+        quote! { unsafe { #krate::JSStr::new_unchecked(#content) } }
+    });
 
-    TokenStream::from(quote! {
-        unsafe { JSStr::new_unchecked(#content) }
+    Ok(quote! {
+        {
+            #track
+            #js
+        }
     })
 }
 
-/// Derives the `JSTemplate` trait for a struct with named fields.
-/// This is simmilar to plain `include_js!` with the difference that
-/// the Javascript is not yet fully filled in, so a template engine (in this case `Handlebars`)
-/// to fill in the values at runtime.
+/// Like `include_js!` but the file does not have to exist. The result is an `Option<&'static JSStr>`, `None` if
+/// the file does not exist when the crate is compiled, e.g. for an artifact that only some builds produce.
+/// With `first_of(...)` it is `None` if none of the files exist. The same arguments as for `include_js!` are accepted,
+/// but only the first file is optional.
 ///
-/// **Note:** Currently the only supported attribute is `#[include_js(template = "SOME/PATH")]` and it is
-/// required to specify it. The capabilities may be expanded in the future.
-/// 
-/// **Warning:** The ability of this macro to actually prove that the file contains valid Javascript once filled
-/// in is kind of limited. It assumes that you will only fill-in expressions via the template engine; so to be able to
-/// atleast do some kind of check it will use `[]` as a placeholder for every expression.
-/// I might add the ability to disable the compiletime check or to enable an optional runtime check at some point, but this is
-/// not implemented yet.
-/// 
-/// # Examples
+/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR, or to the invoking file with `relative_to = "file"`.
+/// Cargo can't be told to watch a file that does not exist, so the crate is not rebuilt when the file appears,
+/// unless it is generated by the build script of the crate.
 ///
-/// Let this be your JS template script.
-/// 
-/// `src/js/move_window.js.handlebars`
-/// ```javascript
-/// let w = global
-//      .get_window_actors()
-///     .map(a => a.meta_window)
-///     .filter(w => w.wm_class == "{{window_class}}")
-///     .reduce((acc, x) => (acc && acc.id > x.id) ? acc : x, null);
+/// # Examples
 ///
-/// w.move_resize_frame(true, {{x}}, {{y}}, {{width}}, {{height}});
 /// ```
+/// use include_js::{JSStr, include_js_opt};
 ///
-/// You can then do the following. 
+/// const JS: Option<&JSStr> = include_js_opt!("src/js/some_script.js");
+/// assert!(JS.is_some());
 ///
-/// ```no_run
-/// use include_js::{JSString, JSTemplate};
+/// const MISSING: Option<&JSStr> = include_js_opt!("src/js/optional_script.js");
+/// assert!(MISSING.is_none());
+/// ```
+#[proc_macro]
+pub fn include_js_opt(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item with args::IncludeArgs::parse_optional);
+
+    expand_include_js_opt(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_include_js_opt(args: &args::IncludeArgs) -> syn::Result<TokenStream2> {
+    let krate = &args.options.krate;
+
+    if !args.path_exists()? {
+        return Ok(quote! { ::core::option::Option::None::<&'static #krate::JSStr> });
+    }
+
+    let js = expand_include_js(args)?;
+    Ok(quote! { ::core::option::Option::Some(#js) })
+}
+
+/// Like `include_js!` but also computes the subresource integrity hashes of the included code at compiletime.
+/// The result is a `JSWithIntegrity` with the code and its `sha256` and `sha384` hashes, ready to be used
+/// as the `integrity` attribute of a `<script>` element. The same arguments as for `include_js!` are accepted
+/// and the hashes are computed over the code after all transformations, i.e. exactly what is embedded.
 ///
-/// #[derive(JSTemplate)]
-/// #[include_js(template = "src/js/move_window.js.handlebars")]
-/// struct MoveWindowCommand {
-///     x: u32,
-///     y: u32,
-///     width: u32,
-///     height: u32,
-///     window_class: String,
-/// }
+/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR, or to the invoking file with `relative_to = "file"`.
 ///
-/// let js: JSString = MoveWindowCommand { 
-///     x: 0,
-///     y: 5,
-///     width: 100,
-///     height: 200,
-///     window_class: "org.gnome.Nautilus".to_owned(),
-/// }.render_template();
-/// 
-/// let expected = r#"
-/// let w = global
-//      .get_window_actors()
-///     .map(a => a.meta_window)
-///     .filter(w => w.wm_class == "org.gnome.Nautilus")
-///     .reduce((acc, x) => (acc && acc.id > x.id) ? acc : x, null);
+/// # Examples
 ///
-/// w.move_resize_frame(true, 0, 5, 100, 200);
-/// "#;
+/// ```
+/// use include_js::{JSWithIntegrity, include_js_with_integrity};
+///
+/// const JS: JSWithIntegrity = include_js_with_integrity!("src/js/some_script.js");
 ///
-/// assert_eq!(expected, &*js);
+/// let tag = format!(r#"<script integrity="{}">{}</script>"#, JS.sha384, JS.code.as_str());
+/// assert_eq!(JS.sha384, "sha384-keHdFNIAVbULWlo4tq5j1Mik4/2AgOB4bteHFRfee6f7ezvsdLVdD7BgIH1xeaNc");
 /// ```
-#[cfg(feature = "template")]
-#[proc_macro_derive(JSTemplate, attributes(include_js))]
-pub fn derive_js_template(item: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(item as DeriveInput);
+#[proc_macro]
+pub fn include_js_with_integrity(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as args::IncludeArgs);
 
-    let template_path = {
-        let template_attr = template::get_attr(&input);
-        let template = template_attr.parse_args::<template::TemplatePathInput>().unwrap();
+    expand_include_js_with_integrity(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-        template.path.value()
-    };
+/// Formats the `D` digest of `content` like the `integrity` attribute expects it, i.e. `<algorithm>-<base64 digest>`.
+fn integrity<D: Digest>(algorithm: &str, content: &str) -> String {
+    format!("{}-{}", algorithm, BASE64_STANDARD.encode(D::digest(content.as_bytes())))
+}
 
-    let struct_name = &input.ident;
-    let content = read_to_string_relative(Path::new(&template_path));
+fn expand_include_js_with_integrity(args: &args::IncludeArgs) -> syn::Result<TokenStream2> {
+    let args::Script { content, track, .. } = args.load_script()?;
+    let krate = &args.options.krate;
 
-    let data: HashMap<String, [(); 0]> = {
-        let field_names = match &input.data {
-            Data::Struct(ds) => template::struct_field_names(&ds),
-            _ => panic!("only structs supported"),
-        };
+    // the hashes have to match the code, so they are computed for each variant of it
+    let js = args.options.with_source_url(&content, &args.path.value, registry::Kind::Script, |content| {
+        let sha256 = integrity::<Sha256>("sha256", content);
+        let sha384 = integrity::<Sha384>("sha384", content);
 
-        field_names.into_iter().zip(std::iter::repeat([])).collect()
+        quote! {
+            #krate::JSWithIntegrity {
+                code: unsafe { #krate::JSStr::new_unchecked(#content) },
+                sha256: #sha256,
+                sha384: #sha384,
+            }
+        }
+    });
+
+    Ok(quote! {
+        {
+            #track
+            #js
+        }
+    })
+}
+
+/// Like `include_js!` but also creates a source map of the included code at compiletime. The result is a
+/// `JSWithSourceMap` with the code and its source map as JSON, which maps the code to the included files,
+/// e.g. for transpiled code or code that is put after a banner. The same arguments as for `include_js!` are accepted,
+/// except for `strip_comments` and `source_map`.
+///
+/// Files that are compiled by swc, e.g. with `jsx(...)` or `target = ...`, get the detailed map swc creates,
+/// other files a map from every line of the code to the line of the file it comes from. If the code consists of
+/// several files or doesn't start with the file, the map is an index map with a section per file.
+/// The source maps contain the content of the files.
+///
+/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR, or to the invoking file with `relative_to = "file"`.
+///
+/// # Examples
+///
+/// ```
+/// use include_js::{JSWithSourceMap, include_js_with_map};
+///
+/// const JS: JSWithSourceMap = include_js_with_map!("src/js/some_script.js");
+///
+/// assert!(JS.source_map.starts_with(r#"{"version":3,"sources":["src/js/some_script.js"],"sourcesContent":["#));
+/// assert!(JS.source_map.ends_with(r#""mappings":"AAAA;AACA;AACA"}"#));
+/// ```
+#[proc_macro]
+pub fn include_js_with_map(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as args::IncludeArgs);
+
+    expand_include_js_with_map(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_include_js_with_map(args: &args::IncludeArgs) -> syn::Result<TokenStream2> {
+    args.options.reject_source_map("include_js_with_map!", args.path.span)?;
+
+    let args::Script { content, track, source_map, .. } = args.load_mapped(&args.options.transform, true)?;
+    let source_map = source_map.expect("a source map was requested");
+    let krate = &args.options.krate;
+    let js = args.options.with_source_url(&content, &args.path.value, registry::Kind::Script, |content| {
+        quote! { unsafe { #krate::JSStr::new_unchecked(#content) } }
+    });
+
+    Ok(quote! {
+        {
+            #track
+            #krate::JSWithSourceMap {
+                code: #js,
+                source_map: #source_map,
+            }
+        }
+    })
+}
+
+/// Like `include_js!` but the result is a `LazyJSStr`, whose code is put together from its parts the first time it is
+/// used instead of being a single constant. The same arguments as for `include_js!` are accepted and everything is
+/// validated at compiletime as usual.
+///
+/// The included files are embedded with `include_str!`, unless they are changed, e.g. by `strip_comments` or by
+/// compiling them, and only what is added to them, like a banner, the code of `wrap` or the separators between
+/// several files, is part of the macro's expansion. Including a large script this way keeps it from being
+/// one giant literal in the expansion and the code is only concatenated when it is needed.
+///
+/// The result is meant to be kept in a `static`, as the code lives as long as the `LazyJSStr`. `LazyJSStr` needs the
+/// `std` feature, it is not available in `no_std` crates.
+///
+/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR, or to the invoking file with `relative_to = "file"`.
+///
+/// # Examples
+///
+/// ```
+/// use include_js::{JSStr, LazyJSStr, include_js_lazy};
+///
+/// static JS: LazyJSStr = include_js_lazy!("src/js/some_script.js", "src/js/scripts/greet.js", wrap = iife);
+///
+/// let js: &JSStr = &JS;
+/// assert!(js.as_str().starts_with("(function () {\nfunction some_function() {"));
+/// assert!(js.as_str().contains("}\n;\nfunction greet(name) {"));
+/// assert!(js.as_str().ends_with("}\n})();\n"));
+/// ```
+#[proc_macro]
+pub fn include_js_lazy(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as args::IncludeArgs);
+
+    expand_include_js_lazy(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_include_js_lazy(args: &args::IncludeArgs) -> syn::Result<TokenStream2> {
+    let args::Script { content, track, verbatim, .. } = args.load_script()?;
+    let krate = &args.options.krate;
+
+    // the `sourceURL` comment is only ever appended, so the ranges of the files stay the same
+    let js = args.options.with_source_url(&content, &args.path.value, registry::Kind::Script, |content| {
+        let mut parts = Vec::new();
+        let mut pos = 0;
+
+        for (range, file) in &verbatim {
+            parts.extend(lazy_part(&content[pos..range.start]));
+
+            let file = file.to_string_lossy();
+            parts.push(quote! { ::core::include_str!(#file) });
+            pos = range.end;
+        }
+
+        parts.extend(lazy_part(&content[pos..]));
+
+        quote! { unsafe { #krate::LazyJSStr::new_unchecked(&[#(#parts),*]) } }
+    });
+
+    Ok(quote! {
+        {
+            #track
+            #js
+        }
+    })
+}
+
+/// The literal part of a `LazyJSStr` with `code`, if there is any code.
+fn lazy_part(code: &str) -> Option<TokenStream2> {
+    match code {
+        "" => None,
+        code => Some(quote! { #code }),
+    }
+}
+
+/// Includes a file containing a single function expression as a `&JSFunction<Args>`, which turns Rust values into
+/// a call of the function with `call_with`. `Args` is the tuple of the argument types and is inferred from the type
+/// the result is assigned to. The file is checked to be a single expression, but neither that it evaluates to a
+/// function nor how many arguments it takes can be checked.
+///
+/// The options of `include_js!` are accepted, except for the ones that put other code around the expression, e.g.
+/// `wrap`, and source maps. The checks and transformations apply to the expression in parentheses, if they change
+/// it the parentheses are embedded as well.
+///
+/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR, or to the invoking file with `relative_to = "file"`.
+///
+/// # Examples
+///
+/// `src/functions/resize.js`
+/// ```javascript
+/// (width, name) => {
+///     const actor = global.get_window_actors().find(actor => actor.meta_window.get_wm_class() === name);
+///     actor.meta_window.move_resize_frame(false, 0, 0, width, width * 9 / 16);
+/// }
+/// ```
+///
+/// ```
+/// use include_js::{JSFunction, include_js_fn};
+///
+/// const RESIZE: &JSFunction<(u32, String)> = include_js_fn!("src/functions/resize.js");
+///
+/// let js = RESIZE.call_with((800, "Firefox".into()));
+/// assert!(js.as_str().starts_with("((width, name) => {\n"));
+/// assert!(js.as_str().ends_with("})(800, \"Firefox\");"));
+/// ```
+///
+/// ```compile_fail
+/// use include_js::{JSFunction, include_js_fn};
+///
+/// // a statement and not an expression
+/// const F: &JSFunction<()> = include_js_fn!("src/fragments/banner.js");
+/// ```
+#[proc_macro]
+pub fn include_js_fn(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as args::IncludeArgs);
+
+    expand_include_js_fn(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_include_js_fn(args: &args::IncludeArgs) -> syn::Result<TokenStream2> {
+    let path = &args.path.value;
+    let span = args.path.span;
+    let options = &args.options;
+    options.reject_source_map("include_js_fn!", span)?;
+
+    if !args.more.is_empty() {
+        return Err(syn::Error::new(args.more[0].span, "`include_js_fn!` includes a single file"));
+    }
+
+    if options.wrap.is_some() || options.prelude.is_some() || options.banner.is_some() || options.footer.is_some() {
+        return Err(syn::Error::new(span, "`include_js_fn!` includes a single expression, it can't have code around it"));
+    }
+
+    let file = args.resolve_path()?;
+    let content = options.read(&file, span)?;
+    let expr = content.trim();
+    include_js_core::JSExpr::new(expr).map_err(|e| syntax_error(e.into_parse_error(), path, span))?;
+
+    // the expression on its own is not necessarily a valid script, e.g. an anonymous function
+    let parenthesized = format!("({}\n)", expr);
+    let processed = options.process(parenthesized.clone(), false, path, span)?;
+    let expr = match processed == parenthesized {
+        true => expr,
+        false => processed.as_str(),
+    };
+
+    let mut track = track(&file);
+    track.extend(options.track());
+    let krate = &options.krate;
+    let js = options.with_source_url(expr, path, registry::Kind::Script, |expr| {
+        quote! { unsafe { #krate::JSFunction::new_unchecked(#expr) } }
+    });
+
+    Ok(quote! {
+        {
+            #track
+            #js
+        }
+    })
+}
+
+/// Checks Javascript written inline as a string literal at compiletime, like `include_js!` does for files.
+/// The result is a `&'static JSStr`, so short snippets don't need a file of their own to be validated.
+///
+/// The options of `include_js!` are accepted, except for source maps and `source_url` as there is no file to refer to.
+///
+/// # Examples
+///
+/// ```
+/// use include_js::{JSStr, js_str};
+///
+/// const JS: &JSStr = js_str!("function f() { return 42; }");
+/// assert_eq!(JS.as_str(), "function f() { return 42; }");
+///
+/// const WRAPPED: &JSStr = js_str!("let x = 1;", wrap = iife);
+/// assert_eq!(WRAPPED.as_str(), "(function () {\nlet x = 1;\n})();\n");
+/// ```
+///
+/// ```compile_fail
+/// use include_js::{JSStr, js_str};
+///
+/// const JS: &JSStr = js_str!("function f() { return 42;");
+/// ```
+#[proc_macro]
+pub fn js_str(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as args::InlineArgs);
+
+    expand_js_str(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_js_str(args: &args::InlineArgs) -> syn::Result<TokenStream2> {
+    let span = args.code.span();
+    let options = &args.options;
+    options.reject_source_map("js_str!", span)?;
+
+    if options.source_url.is_some() {
+        return Err(syn::Error::new(span, "inline code has no path to name it with `source_url`"));
+    }
+
+    if options.encoding.is_some() {
+        return Err(syn::Error::new(span, "inline code is not read from a file, it has no `encoding`"));
+    }
+
+    let content = options.compile(options.tolerate.apply(options.normalize.apply(args.code.value())), false, "<inline>", span)?;
+    let content = options.process(content, false, "<inline>", span)?;
+    let track = options.track();
+    let krate = &options.krate;
+
+    Ok(quote! {
+        {
+            #track
+            unsafe { #krate::JSStr::new_unchecked(#content) }
+        }
+    })
+}
+
+/// Compiles a TypeScript file to Javascript at compiletime and includes the result like `include_js!`.
+/// The types are removed by swc and constructs like enums are compiled to plain Javascript, but no type checking
+/// is done. The emitted code is validated and all options of `include_js!` apply to it, e.g. `parser = "swc"`
+/// or `strict`. Several files can be given as well, each of them is compiled on its own. With `jsx(...)` the files
+/// may contain JSX, i.e. they are TSX.
+///
+/// This macro is only available with the `typescript` feature, which implies `parser-swc`.
+///
+/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR, or to the invoking file with `relative_to = "file"`.
+#[cfg(feature = "typescript")]
+#[proc_macro]
+pub fn include_ts(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as args::IncludeArgs);
+
+    expand_include_ts(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[cfg(feature = "typescript")]
+fn expand_include_ts(args: &args::IncludeArgs) -> syn::Result<TokenStream2> {
+    let transform = transform::Transform { typescript: true, ..args.options.transform.clone() };
+    let args::Script { content, track, .. } = args.load_script_with(&transform)?;
+    let krate = &args.options.krate;
+    let js = args.options.with_source_url(&content, &args.path.value, registry::Kind::Script, |content| {
+        quote! { unsafe { #krate::JSStr::new_unchecked(#content) } }
+    });
+
+    Ok(quote! {
+        {
+            #track
+            #js
+        }
+    })
+}
+
+/// Includes every `.js` file in a directory, validating each of them like `include_js!`.
+/// The directory is searched recursively and the result is a `&'static [(&'static str, &'static JSStr)]`
+/// of the path of each file relative to the directory and its content, sorted by path.
+/// Paths are always separated by `/`.
+///
+/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR, or to the invoking file with `relative_to = "file"`.
+///
+/// The parser, the targeted ECMAScript version and the lints can be chosen with `parser = "..."`, `es = ...`
+/// and `deny(...)` just like for `include_js!`, they apply to every file.
+///
+/// # Examples
+///
+/// ```
+/// use include_js::{JSStr, include_js_dir};
+///
+/// static SCRIPTS: &[(&str, &JSStr)] = include_js_dir!("src/js/scripts");
+///
+/// let names: Vec<&str> = SCRIPTS.iter().map(|(name, _)| *name).collect();
+/// assert_eq!(names, ["greet.js", "util/clamp.js"]);
+/// ```
+#[proc_macro]
+pub fn include_js_dir(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as args::IncludeArgs);
+
+    dir::expand_include_js_dir(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Includes every file matching a glob pattern, validating each of them like `include_js!`.
+/// The result is a `&'static [(&'static str, &'static JSStr)]` of the path of each file relative
+/// to $CARGO_MANIFEST_DIR and its content, sorted by path. Paths are always separated by `/`.
+/// With `relative_to = "file"` the patterns and paths are relative to the invoking file instead.
+///
+/// Several patterns can be given, a file is included if it matches any of them. Patterns starting
+/// with `!` exclude the files they match instead, e.g. `"!**/*.test.js"`.
+///
+/// The parser, the targeted ECMAScript version and the lints can be chosen with `parser = "..."`, `es = ...`
+/// and `deny(...)` after the patterns, just like for `include_js!`, they apply to every file.
+///
+/// # Examples
+///
+/// ```
+/// use include_js::{JSStr, include_js_glob};
+///
+/// static SCRIPTS: &[(&str, &JSStr)] = include_js_glob!("src/js/**/*.js", "!src/js/*module*.js", "!**/modern_*.js");
+///
+/// let names: Vec<&str> = SCRIPTS.iter().map(|(name, _)| *name).collect();
+/// assert_eq!(names, [
+///     "src/js/eval_script.js",
+///     "src/js/licensed_script.js",
+///     "src/js/scripts/greet.js",
+///     "src/js/scripts/util/clamp.js",
+///     "src/js/some_script.js",
+/// ]);
+/// ```
+#[proc_macro]
+pub fn include_js_glob(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as dir::GlobArgs);
+
+    dir::expand_include_js_glob(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Like `include_js!` but validates the file as an ES module, so `import` and `export` declarations
+/// are allowed. The file is included as `&JSModule`.
+///
+/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR, or to the invoking file with `relative_to = "file"`.
+///
+/// The parser, the targeted ECMAScript version and the lints can be chosen with `parser = "..."`, `es = ...`
+/// and `deny(...)` just like for `include_js!`.
+///
+/// With `"path" as Name` instead of just the path, a unit struct `Name` is declared instead, with the module
+/// as `Name::MODULE` and its exports as `Name::EXPORTS`, a `&[ExportInfo]` that is determined at compiletime
+/// like `JSModule::exports` does at runtime. The struct can be preceded by attributes and a visibility.
+///
+/// # Examples
+///
+/// ```
+/// use include_js::{JSModule, include_js_module};
+///
+/// const JS: &JSModule = include_js_module!("src/js/some_module.js");
+/// ```
+///
+/// ```
+/// use include_js::{ExportInfo, include_js_module};
+///
+/// include_js_module!(pub "src/modules/math.js" as MathModule);
+///
+/// assert_eq!(MathModule::EXPORTS, [ExportInfo::Named("square".into())]);
+/// assert!(MathModule::MODULE.as_str().contains("return x * x;"));
+/// ```
+#[proc_macro]
+pub fn include_js_module(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as args::ModuleArgs);
+
+    expand_include_js_module(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_include_js_module(module_args: &args::ModuleArgs) -> syn::Result<TokenStream2> {
+    let args = &module_args.args;
+    let path = &args.path.value;
+    let span = args.path.span;
+    let file = args.resolve_path()?;
+    args.options.reject_source_map("include_js_module!", span)?;
+
+    if args.options.tolerate.html_comments {
+        return Err(syn::Error::new(span, "modules can't contain HTML-like comments, only `tolerate(hashbang)` applies to them"));
+    }
+    let content = args.options.compile(args.options.read(&file, span)?, true, path, span)?;
+    let content = args.options.process(content, true, path, span)?;
+    let mut track = track(&file);
+    track.extend(args.options.track());
+    let krate = &args.options.krate;
+    let js = args.options.with_source_url(&content, path, registry::Kind::Module, |content| {
+        quote! { unsafe { #krate::JSModule::new_unchecked(#content) } }
+    });
+
+    let module = quote! {
+        {
+            #track
+            #js
+        }
+    };
+
+    let (attrs, vis, ident) = match &module_args.item {
+        Some(item) => item,
+        None => return Ok(module),
+    };
+
+    // SAFETY: `process` made sure that the content is a valid module
+    let exports = unsafe { include_js_core::JSModule::new_unchecked(&content) }
+        .exports()
+        .into_iter()
+        .map(|export| match export {
+            include_js_core::ExportInfo::Named(name) => {
+                let name = name.as_ref();
+                quote! { #krate::ExportInfo::named(#name) }
+            },
+            include_js_core::ExportInfo::Default => quote! { #krate::ExportInfo::Default },
+            include_js_core::ExportInfo::All(specifier) => {
+                let specifier = specifier.as_ref();
+                quote! { #krate::ExportInfo::all(#specifier) }
+            },
+        });
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis struct #ident;
+
+        impl #ident {
+            /// The included module
+            #vis const MODULE: &'static #krate::JSModule = #module;
+            /// The exports of the module, in the order they are declared in
+            #vis const EXPORTS: &'static [#krate::ExportInfo] = &[#(#exports),*];
+        }
+    })
+}
+
+/// Includes a JSON file as a Javascript expression, e.g. for `const CONFIG = <json>;`. The file is checked to be
+/// valid JSON at compiletime and the result is a `&JSStr` like for `include_js!`, whose options are accepted as well.
+///
+/// The JSON is put into parentheses, so an object is not parsed as a block when the code is used on its own.
+/// With `json_parse` directly after the path, it is embedded as a string literal passed to `JSON.parse` instead,
+/// which some engines parse faster than an object literal. This also keeps `"__proto__"` keys an ordinary property,
+/// while in an object literal they set the prototype.
+///
+/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR, or to the invoking file with `relative_to = "file"`.
+///
+/// # Examples
+///
+/// `src/json/config.json`
+/// ```json
+/// {
+///     "window": { "width": 800, "height": 600 },
+///     "classes": ["Nautilus", "Firefox"],
+///     "debug": false
+/// }
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_json_js};
+///
+/// const CONFIG: &JSStr = include_json_js!("src/json/config.json");
+/// assert!(CONFIG.as_str().starts_with("({\n    \"window\": { \"width\": 800, \"height\": 600 },"));
+/// assert!(CONFIG.as_str().ends_with("\"debug\": false\n})"));
+///
+/// const PARSED: &JSStr = include_json_js!("src/json/config.json", json_parse);
+/// assert!(PARSED.as_str().starts_with(r#"JSON.parse("{\n    \"window\": {"#));
+///
+/// let js = format!("const CONFIG = {};", PARSED);
+/// ```
+///
+/// ```compile_fail
+/// use include_js::{JSStr, include_json_js};
+///
+/// // not JSON, even though it is a valid expression
+/// const JS: &JSStr = include_json_js!("src/js/some_script.js");
+/// ```
+#[proc_macro]
+pub fn include_json_js(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as args::JsonArgs);
+
+    expand_include_json_js(&args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_include_json_js(json_args: &args::JsonArgs) -> syn::Result<TokenStream2> {
+    let args = &json_args.args;
+    let path = &args.path.value;
+    let span = args.path.span;
+    args.options.reject_source_map("include_json_js!", span)?;
+
+    let file = args.resolve_path()?;
+    let json = args.options.read(&file, span)?;
+    serde_json::from_str::<serde_json::Value>(&json)
+        .map_err(|e| syn::Error::new(span, format!("invalid JSON in '{}': {}", path, e)))?;
+
+    let expr = match json_args.json_parse {
+        true => format!("JSON.parse({})", include_js_core::JSString::quote(json.trim())),
+        false => format!("({})", json.trim()),
+    };
+    let content = args.options.process(expr, false, path, span)?;
+    let mut track = track(&file);
+    track.extend(args.options.track());
+    let krate = &args.options.krate;
+    let js = args.options.with_source_url(&content, path, registry::Kind::Script, |content| {
+        quote! { unsafe { #krate::JSStr::new_unchecked(#content) } }
+    });
+
+    Ok(quote! {
+        {
+            #track
+            #js
+        }
+    })
+}
+
+/// Includes an ES module together with all modules it imports as a single `&JSStr`.
+/// Imports are resolved relative to the importing file, so only relative specifiers
+/// like `./util.js` or `../util.js` are supported. Every imported module is included only once
+/// and wrapped in a function returning its exports, see `include_js_core::bundle` for the details and limitations.
+///
+/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR.
+///
+/// Like for `include_js!` the path of the crate in the generated code can be changed with `crate = "..."`.
+///
+/// # Examples
+///
+/// `src/modules/main.js`
+/// ```javascript
+/// import { square } from './math.js';
+///
+/// console.log(square(4));
+/// ```
+///
+/// `src/modules/math.js`
+/// ```javascript
+/// export function square(x) {
+///     return x * x;
+/// }
+/// ```
+///
+/// ```
+/// use include_js::{JSStr, include_js_bundle};
+///
+/// const JS: &JSStr = include_js_bundle!("src/modules/main.js");
+/// assert!(JS.as_str().contains("const square = $module0.square;"));
+/// ```
+///
+/// ```
+/// mod js {
+///     pub use include_js::{include_js_bundle, JSStr};
+/// #   #[cfg(feature = "registry")]
+/// #   pub use include_js::__private;
+/// }
+///
+/// const JS: &js::JSStr = js::include_js_bundle!("src/modules/main.js", crate = "js");
+/// ```
+#[proc_macro]
+pub fn include_js_bundle(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as args::PathAndCrate<args::PathArg>);
+
+    expand_include_js_bundle(&args.path, &args.krate)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Removes `.` and `..` from `path` without touching the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            },
+            c => normalized.push(c),
+        }
+    }
+
+    normalized
+}
+
+fn expand_include_js_bundle(input_path: &args::PathArg, krate: &syn::Path) -> syn::Result<TokenStream2> {
+    input_path.reject_absolute(false)?;
+    let path = &input_path.value;
+    let span = input_path.span;
+    let content = read_to_string_relative(Path::new(path), span)?;
+    let mut tracks = vec![track_relative(Path::new(path), span)?];
+
+    // modules are identified by their normalized path relative to $CARGO_MANIFEST_DIR
+    let load = |importer: &str, specifier: &str| {
+        if !specifier.starts_with("./") && !specifier.starts_with("../") {
+            return Err(syn::Error::new(
+                span,
+                format!("can't bundle import of '{}' in '{}', only relative imports are supported", specifier, importer),
+            ));
+        }
+
+        let id = normalize(&Path::new(importer).parent().unwrap_or(Path::new("")).join(specifier));
+        let content = read_to_string_relative(&id, span)?;
+        tracks.push(track_relative(&id, span)?);
+        Ok((id.to_string_lossy().into_owned(), content))
+    };
+
+    let bundle = include_js_core::bundle(&normalize(Path::new(path)).to_string_lossy(), &content, load)
+        .map_err(|e| match e {
+            BundleError::Load(e) => e,
+            BundleError::Syntax { module, error } => syntax_error(error.into_parse_error(), &module, span),
+            e => syn::Error::new(span, e.to_string()),
+        })?
+        .into_string();
+
+    let register = registry::register(krate, registry::Kind::Script, path, &bundle, None);
+
+    Ok(quote! {
+        {
+            #(#tracks)*
+            #register
+            unsafe { #krate::JSStr::new_unchecked(#bundle) }
+        }
+    })
+}
+
+/// Quasi-quoting for small Javascript snippets. The Javascript is written inline and checked at compiletime,
+/// Rust values can be spliced in with `#ident` or `#(expr)` and are converted to Javascript with `ToJsLiteral`
+/// at runtime. The result is a `JSString`.
+///
+/// **Note:** The Javascript has to be tokenizable as Rust, so string literals need double quotes,
+/// template literals are not supported and statements have to be terminated with `;`.
+///
+/// # Examples
+///
+/// ```
+/// use include_js::{js, JSString};
+///
+/// let msg = "it's \"quoted\"";
+/// let count = 3;
+///
+/// let js: JSString = js! { console.log(#msg, #(count * 2)); };
+/// assert_eq!(js.as_str(), r#"console.log("it\'s \"quoted\"", 6);"#);
+/// ```
+#[proc_macro]
+pub fn js(item: TokenStream) -> TokenStream {
+    quasi::expand_js(item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Like `format!` but for Javascript: the template is checked at compiletime with `[]` in place of every
+/// placeholder and the arguments are converted to Javascript with `ToJsLiteral` at runtime.
+/// The result is a `JSString`.
+///
+/// Placeholders are written like in `format!`: `{}` for positional arguments, `{0}` for an argument by index
+/// and `{name}` for a named argument or a variable in scope. Literal braces need to be escaped as `{{` and `}}`.
+///
+/// # Examples
+///
+/// ```
+/// use include_js::{format_js, JSString};
+///
+/// let title = "Nautilus";
+///
+/// let js: JSString = format_js!("w.move_resize_frame(true, {x}, {y}); w.set_title({title});", x = 1, y = 2);
+/// assert_eq!(js.as_str(), r#"w.move_resize_frame(true, 1, 2); w.set_title("Nautilus");"#);
+/// ```
+#[proc_macro]
+pub fn format_js(item: TokenStream) -> TokenStream {
+    quasi::expand_format_js(item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives the `ToJsLiteral` trait for a struct with named fields, turning it into a Javascript
+/// object literal with one property per field.
+///
+/// # Examples
+///
+/// ```
+/// use include_js::ToJsLiteral;
+///
+/// #[derive(ToJsLiteral)]
+/// struct Size {
+///     width: u32,
+///     height: Option<u32>,
+/// }
+///
+/// let js = Size { width: 800, height: None }.to_js_literal();
+/// assert_eq!(js.as_str(), r#"({"width": 800, "height": null})"#);
+/// ```
+#[proc_macro_derive(ToJsLiteral)]
+pub fn derive_to_js_literal(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    literal::expand_to_js_literal(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives the `JSTemplate` trait for a struct or enum.
+/// This is simmilar to plain `include_js!` with the difference that
+/// the Javascript is not yet fully filled in, so a template engine (in this case `Handlebars`)
+/// to fill in the values at runtime.
+///
+/// **Note:** The attribute `#[include_js(template = "SOME/PATH")]` is required. Templates short enough to not need
+/// their own file can be given with `#[include_js(inline = "...")]` instead, which are checked in the same way.
+/// 
+/// **Warning:** The ability of this macro to actually prove that the file contains valid Javascript once filled
+/// in is kind of limited. It assumes that you will only fill-in expressions via the template engine; so to be able to
+/// atleast do some kind of check it will use `[]` as a placeholder for every expression.
+/// For fields in other positions a more realistic placeholder can be given with `#[include_js(placeholder = "...")]`
+/// on the field, e.g. `#[include_js(placeholder = "x")]` for a field that is used as an identifier.
+/// Sequences of primitive values like `Vec<String>` that are iterated with `{{#each field}}` are substituted
+/// with an array containing a single placeholder instead, so the body of the loop is checked as well.
+/// Loops over other values, e.g. a `Vec` of structs, are not rendered by the check.
+/// `bool` fields are substituted with `true` and `false` in all combinations, so both branches of
+/// `{{#if field}}` and `{{#unless field}}` are checked. With more than 8 of them the check only renders the template
+/// with all of them `true` and all of them `false`.
+/// The types of the fields are not known to the check beyond that, so for paths into a field like `{{config.width}}`
+/// it substitutes an object with a placeholder at every path the template uses. Whether the nested field actually
+/// exists only shows when rendering.
+///
+/// Values that should end up in a Javascript string should be rendered with the `js_string` helper
+/// (`{{js_string field}}`) which outputs a properly quoted and escaped string literal. Interpolating
+/// them between quotes in the template (`"{{field}}"`) breaks as soon as the value contains a quote.
+/// If that assumption does not hold for your template, the check can be changed with `#[include_js(check = "...")]`:
+/// - `"placeholder"` (the default) does the compiletime check described above
+/// - `"runtime"` skips the compiletime check and instead parses every rendered template,
+///   `try_render_template` returns `RenderError::Syntax` if the result is not valid Javascript.
+///   This needs the `validator-boa` or `validator-ressa` feature of `include_js`
+/// - `"none"` disables the check entirely, making sure the rendered templates are valid Javascript is up to you
+///
+/// Unlike plain handlebars the values are not escaped as HTML when rendering, as entities like `&quot;` would
+/// corrupt the Javascript. Templates that end up embedded in HTML can opt back into it with
+/// `#[include_js(escape = "html")]`, `escape = "none"` is the default.
+///
+/// Further handlebars helpers can be registered with `#[include_js(helper(name = "...", fn = "..."))]`,
+/// where `fn` is the path of anything implementing `HelperDef`, usually a function with the signature of a helper.
+/// The helpers can't run at compiletime, so the check substitutes `[]` for their output,
+/// or the Javascript given with `placeholder = "..."` in `helper(...)`.
+///
+/// Partials are registered with `#[include_js(partial(name = "...", path = "..."))]`, the path is resolved like the
+/// path of the template. The variables used by the partials a template includes count as used by the template.
+///
+/// With `#[include_js(layout = "...")]` the template is embedded into a layout, which contains `{{{body}}}`
+/// exactly once where the template goes. The two are composed at compiletime and checked as a single template,
+/// so the variables of the layout have to be fields as well.
+///
+/// The variables referenced by the template are also checked against the fields of the struct:
+/// a variable without a matching field is a compile error and a field that is never used in the template
+/// produces a warning.
+///
+/// The template data is built from the fields, which can be adjusted with attributes on them:
+/// - `#[include_js(rename = "...")]` makes the field available under a different name
+/// - `#[include_js(skip)]` leaves the field out of the template data, it does not need to be `Serialize`
+/// - `#[include_js(flatten)]` makes the fields of the value available instead of the value itself.
+///   These are only known at runtime, so variables without a matching field are no error if there is a flattened
+///   field and its placeholder is used for them
+/// - `#[include_js(json)]` makes `{{field}}` output the value as JSON, i.e. as a Javascript object, array or
+///   primitive literal, without any escaping. Paths into the value like `{{field.x}}` are rendered as usual
+/// - `#[include_js(default)]` and `#[include_js(default = "...")]` give the field a default, `Default::default()`
+///   or the given Rust expression, like `#[serde(default)]`. The builder uses it for the field if it is not set
+///   and if every field of a struct has one, the struct implements `Default` with them
+///
+/// The `#[serde(...)]` attributes `rename`, `rename_all`, `skip`, `skip_serializing` and `flatten` are honored
+/// in the same way, so a struct that is also serialized elsewhere does not need to repeat them. If both are given,
+/// the `#[include_js(...)]` attribute takes precedence. The template data is built from the fields one by one, so
+/// `serialize_with`, `with`, `skip_serializing_if` and `getter` can't be honored and are a compile error.
+///
+/// The generated code refers to this crate as `::include_js`. If it is renamed in Cargo.toml or re-exported
+/// through another crate, the path can be changed with `#[include_js(crate = "...")]`. With the `registry` feature
+/// the path has to provide the hidden `__private` module of include_js as well, and `MissingField` with `builder`.
+///
+/// Templates that only consist of text, comments, variables of the struct, `js_string` and `if`/`unless` blocks
+/// on variables of the struct are translated into code that builds the string directly, so they are not parsed
+/// again on every render. Everything else, e.g. `each`, partials or custom helpers, is rendered by handlebars.
+/// The engine for such a template is set up and the template compiled on its first render and reused afterwards.
+/// The output is the same either way.
+///
+/// With the `minijinja` feature of `include_js` the templates can be written in Jinja syntax instead,
+/// by selecting the engine with `#[include_js(engine = "minijinja")]` (`"handlebars"` is the default, see
+/// `include_js::minijinja` for an example).
+/// They are checked in the same way, values are quoted as Javascript strings with the `js_string` filter
+/// (`{{ field|js_string }}`) and embedded as JSON with the builtin `tojson` filter. Helpers, partials, layouts,
+/// `json` fields and tuple fields are specific to the handlebars engine.
+///
+/// Besides `try_render_template`, the derive implements `try_render_into`, which appends the rendered template to
+/// a buffer without allocating a new one. Templates that are precompiled are built in the buffer directly.
+///
+/// The fields have to be `Serialize`, generic structs are supported as long as they are for the given
+/// type parameters.
+///
+/// The fields of tuple structs are referenced by their position (`{{0}}`, `{{1}}`, ...).
+/// Unit structs have no fields, their template is rendered as is.
+///
+/// A struct can have further templates that are rendered from the same fields, given with
+/// `#[include_js(template(name = "...", path = "..."))]`, e.g. a script that undoes another one. Each of them is
+/// rendered with `render_<name>` and `try_render_<name>`, or with `render_template_named` and
+/// `try_render_template_named` by its name. They are checked like the main template, but a field only has to be
+/// used by one of the templates. The main template can be left out, then the struct doesn't implement `JSTemplate`
+/// and `fn` isn't available.
+///
+/// For enums every variant has its own template, given with `#[include_js(template = "...")]` on the variant,
+/// which is rendered from the fields of the variant like for a struct.
+/// `check`, `escape`, `engine`, `helper`, `partial`, `layout`, `deny`, `forbid_globals`, `require_fn` and `crate`
+/// are still given on the enum.
+///
+/// For scripts that are rendered right where their values are known, `#[include_js(fn)]` on a struct generates
+/// a function as well, which takes the fields as arguments in order and returns the rendered template.
+/// It is named after the struct in snake_case, e.g. `move_window` for `MoveWindow`, or as given with
+/// `#[include_js(fn = "...")]`, and has the same visibility as the struct.
+///
+/// Structs with many fields can get a builder with `#[include_js(builder)]`, e.g. `MoveWindowBuilder` for
+/// `MoveWindow`, which is started with `MoveWindow::builder()`. It has a setter for every field, named like the field,
+/// and is finished with `build`, which returns the struct or the `MissingField` that was not set, or `render`,
+/// which renders the template right away and panics instead. Fields with a default don't need to be set.
+///
+/// With the `parser-swc` feature, the lints of `include_js!` are available for the rendered templates as well,
+/// e.g. `#[include_js(deny(eval, with, debugger), forbid_globals = ["document"], require_fn = ["init"])]`.
+/// They are checked on the renderings with placeholders, so this needs `check = "placeholder"`.
+/// 
+/// # Examples
+///
+/// Let this be your JS template script.
+/// 
+/// `src/js/move_window.js.handlebars`
+/// ```javascript
+/// let w = global
+///     .get_window_actors()
+///     .map(a => a.meta_window)
+///     .filter(w => w.wm_class == {{js_string window_class}})
+///     .reduce((acc, x) => (acc && acc.id > x.id) ? acc : x, null);
+///
+/// w.move_resize_frame(true, {{x}}, {{y}}, {{width}}, {{height}});
+/// ```
+///
+/// You can then do the following. 
+///
+/// ```
+/// use include_js::{JSString, JSTemplate};
+/// use serde::Serialize;
+///
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(template = "src/js/move_window.js.handlebars")]
+/// struct MoveWindowCommand {
+///     x: u32,
+///     y: u32,
+///     width: u32,
+///     height: u32,
+///     window_class: String,
+/// }
+///
+/// let js: JSString = MoveWindowCommand { 
+///     x: 0,
+///     y: 5,
+///     width: 100,
+///     height: 200,
+///     window_class: "org.gnome.Nautilus".to_owned(),
+/// }.render_template();
+/// 
+/// let expected = r#"
+/// let w = global
+///     .get_window_actors()
+///     .map(a => a.meta_window)
+///     .filter(w => w.wm_class == "org.gnome.Nautilus")
+///     .reduce((acc, x) => (acc && acc.id > x.id) ? acc : x, null);
+///
+/// w.move_resize_frame(true, 0, 5, 100, 200);
+/// "#;
+///
+/// assert_eq!(expected, js.as_str());
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+/// use serde::Serialize;
+/// use std::fmt::Display;
+///
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(template = "src/js/move_window.js.handlebars")]
+/// struct MoveWindowCommand<'a, T: Display> {
+///     x: T,
+///     y: T,
+///     width: T,
+///     height: T,
+///     window_class: &'a str,
+/// }
+///
+/// let js = MoveWindowCommand { x: 0u8, y: 5, width: 100, height: 200, window_class: "Nautilus" }.render_template();
+/// assert!(js.as_str().contains("w.move_resize_frame(true, 0, 5, 100, 200);"));
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+/// use serde::Serialize;
+///
+/// // the generated code doesn't use the names in scope
+/// type Result<T> = std::result::Result<T, std::fmt::Error>;
+///
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(template = "src/js/move_window.js.handlebars")]
+/// struct MoveWindowCommand {
+///     x: u32,
+///     y: u32,
+///     width: u32,
+///     height: u32,
+///     window_class: String,
+/// }
+///
+/// fn render(command: &MoveWindowCommand) -> Result<String> {
+///     Ok(command.render_template().to_string())
+/// }
+///
+/// let command = MoveWindowCommand { x: 0, y: 5, width: 100, height: 200, window_class: "Nautilus".to_owned() };
+/// assert!(render(&command).unwrap().contains("w.move_resize_frame(true, 0, 5, 100, 200);"));
+/// ```
+///
+#[cfg_attr(feature = "parser-swc", doc = "```compile_fail")]
+#[cfg_attr(not(feature = "parser-swc"), doc = "```ignore")]
+/// use include_js::JSTemplate;
+/// use serde::Serialize;
+///
+/// // the template gets the windows from `global`
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(template = "src/js/move_window.js.handlebars", forbid_globals = ["global"])]
+/// struct MoveWindowCommand {
+///     x: u32,
+///     y: u32,
+///     width: u32,
+///     height: u32,
+///     window_class: String,
+/// }
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/resize_window.js.handlebars")]
+/// struct ResizeWindow(u32, u32);
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/activate_window.js.handlebars")]
+/// struct ActivateWindow(usize);
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/maximize_window.js.handlebars")]
+/// struct MaximizeWindow;
+///
+/// assert_eq!(
+///     ResizeWindow(100, 200).render_template().as_str(),
+///     "global.display.focus_window.move_resize_frame(true, 0, 0, 100, 200);\n",
+/// );
+/// assert_eq!(
+///     ActivateWindow(3).render_template().as_str(),
+///     "global.get_window_actors()[3].meta_window.activate(0);\n",
+/// );
+/// assert_eq!(
+///     MaximizeWindow.render_template().as_str(),
+///     "global.display.focus_window.maximize(Meta.MaximizeFlags.BOTH);\n",
+/// );
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Position {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/move_windows.js.handlebars")]
+/// struct MoveWindows {
+///     #[include_js(rename = "windowClass")]
+///     window_class: String,
+///     #[include_js(flatten)]
+///     position: Position,
+///     #[include_js(skip)]
+///     started: std::time::Instant,
+/// }
+///
+/// let js = MoveWindows {
+///     window_class: "Nautilus".to_owned(),
+///     position: Position { x: 10, y: 20 },
+///     started: std::time::Instant::now(),
+/// }.render_template();
+///
+/// assert!(js.as_str().contains("a.meta_window.wm_class == \"Nautilus\""));
+/// assert!(js.as_str().contains("move_frame(true, 10, 20)"));
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+/// use serde::Serialize;
+///
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(template = "src/js/move_windows.js.handlebars")]
+/// #[serde(rename_all = "camelCase")]
+/// struct MoveWindows {
+///     window_class: String,
+///     #[serde(rename = "x")]
+///     left: i32,
+///     #[serde(rename = "y")]
+///     top: i32,
+/// }
+///
+/// let js = MoveWindows { window_class: "Nautilus".to_owned(), left: 10, top: 20 }.render_template();
+/// assert!(js.as_str().contains("move_frame(true, 10, 20)"));
+/// ```
+///
+/// ```compile_fail
+/// use include_js::JSTemplate;
+/// use serde::{Serialize, Serializer};
+///
+/// fn upper<S: Serializer>(class: &str, serializer: S) -> Result<S::Ok, S::Error> {
+///     serializer.serialize_str(&class.to_uppercase())
+/// }
+///
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(template = "src/js/move_windows.js.handlebars")]
+/// #[serde(rename_all = "camelCase")]
+/// struct MoveWindows {
+///     #[serde(serialize_with = "upper")]
+///     window_class: String,
+///     #[serde(rename = "x")]
+///     left: i32,
+///     #[serde(rename = "y")]
+///     top: i32,
+/// }
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Settings {
+///     enabled: bool,
+///     blocklist: Vec<String>,
+/// }
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/apply_settings.js.handlebars")]
+/// struct ApplySettings {
+///     name: String,
+///     #[include_js(json)]
+///     config: Settings,
+/// }
+///
+/// let js = ApplySettings {
+///     name: "tiling".to_owned(),
+///     config: Settings { enabled: true, blocklist: vec!["<Firefox>".to_owned()] },
+/// }.render_template();
+///
+/// assert!(js.as_str().starts_with(r#"const config = {"blocklist":["<Firefox>"],"enabled":true};"#));
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/filter_windows.js.handlebars")]
+/// struct FilterWindows {
+///     #[include_js(placeholder = "true")]
+///     condition: &'static str,
+/// }
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/filter_windows.js.handlebars", escape = "html", check = "none")]
+/// struct FilterWindowsHtml {
+///     condition: &'static str,
+/// }
+///
+/// let condition = "a.visible && !a.meta_window.minimized";
+///
+/// assert!(FilterWindows { condition }.render_template().as_str().contains("a => a.visible && !a.meta"));
+/// assert!(FilterWindowsHtml { condition }.render_template().as_str().contains("a => a.visible &amp;&amp; !a.meta"));
+/// ```
+///
+/// `src/js/configure_window.js.handlebars`
+/// ```javascript
+/// global.display.focus_window.move_resize_frame(true, {{config.x}}, {{config.y}}, {{config.size.width}}, {{config.size.height}});
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Size {
+///     width: u32,
+///     height: u32,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct WindowConfig {
+///     x: u32,
+///     y: u32,
+///     size: Size,
+/// }
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/configure_window.js.handlebars")]
+/// struct ConfigureWindow {
+///     config: WindowConfig,
+/// }
+///
+/// let config = WindowConfig { x: 0, y: 5, size: Size { width: 100, height: 200 } };
+/// let js = ConfigureWindow { config }.render_template();
+/// assert!(js.as_str().contains("move_resize_frame(true, 0, 5, 100, 200)"));
+/// ```
+///
+/// `src/js/close_windows.js.handlebars`
+/// ```javascript
+/// global.get_window_actors()
+///     .map(a => a.meta_window)
+/// {{#each classes}}
+///     .filter(w => w.wm_class != {{js_string this}})
+/// {{/each}}
+///     .forEach(w => w.delete(global.get_current_time()));
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/close_windows.js.handlebars")]
+/// struct CloseWindows {
+///     /// the classes of the windows to keep open
+///     classes: Vec<&'static str>,
+/// }
+///
+/// let js = CloseWindows { classes: vec!["Nautilus", "Firefox"] }.render_template();
+/// assert!(js.as_str().contains(".filter(w => w.wm_class != \"Nautilus\")"));
+/// assert!(js.as_str().contains(".filter(w => w.wm_class != \"Firefox\")"));
+/// ```
+///
+/// ```
+/// use handlebars::{Context, Handlebars, Helper, HelperResult, Output, RenderContext};
+/// use include_js::{JSString, JSTemplate};
+///
+/// fn shout(h: &Helper, _: &Handlebars, _: &Context, _: &mut RenderContext, out: &mut dyn Output) -> HelperResult {
+///     let value = h.param(0).and_then(|p| p.value().as_str()).unwrap_or_default();
+///     out.write(JSString::quote(&value.to_uppercase()).as_str())?;
+///     Ok(())
+/// }
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/notify.js.handlebars")]
+/// #[include_js(helper(name = "shout", fn = "shout", placeholder = "''"))]
+/// struct Notify {
+///     title: String,
+///     body: String,
+/// }
+///
+/// let js = Notify { title: "Update".to_owned(), body: "Restart now".to_owned() }.render_template();
+/// assert_eq!(js.as_str(), "Main.notify(\"UPDATE\", \"Restart now\");\n");
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/close_window.js.handlebars")]
+/// #[include_js(partial(name = "find_window", path = "src/js/partials/find_window.js.handlebars"))]
+/// struct CloseWindow {
+///     window_class: String,
+/// }
+///
+/// let js = CloseWindow { window_class: "Nautilus".to_owned() }.render_template();
+/// assert!(js.as_str().contains(".find(w => w.wm_class == \"Nautilus\");"));
+/// assert!(js.as_str().contains("window.delete(global.get_current_time());"));
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/maximize_window.js.handlebars", layout = "src/js/layouts/guarded.js.handlebars")]
+/// struct MaximizeWindow {
+///     name: &'static str,
+/// }
+///
+/// let js = MaximizeWindow { name: "maximize" }.render_template();
+/// assert!(js.as_str().contains("try {\n        global.display.focus_window.maximize(Meta.MaximizeFlags.BOTH);\n"));
+/// assert!(js.as_str().contains("log(\"maximize\" + \": \" + e);"));
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+///
+/// #[derive(JSTemplate)]
+/// enum WindowCommand {
+///     #[include_js(template = "src/js/move_window.js.handlebars")]
+///     Move { x: u32, y: u32, width: u32, height: u32, window_class: String },
+///     #[include_js(template = "src/js/resize_window.js.handlebars")]
+///     Resize(u32, u32),
+///     #[include_js(template = "src/js/maximize_window.js.handlebars")]
+///     Maximize,
+/// }
+///
+/// let js = WindowCommand::Resize(100, 200).render_template();
+/// assert_eq!(js.as_str(), "global.display.focus_window.move_resize_frame(true, 0, 0, 100, 200);\n");
+///
+/// let js = WindowCommand::Move { x: 0, y: 5, width: 100, height: 200, window_class: "Nautilus".to_owned() }
+///     .render_template();
+/// assert!(js.as_str().contains(".filter(w => w.wm_class == \"Nautilus\")"));
+///
+/// // a single buffer for many commands
+/// let mut script = String::new();
+/// for size in [100, 200, 300] {
+///     let js = WindowCommand::Resize(size, size).render_into(&mut script);
+///     assert!(js.as_str().ends_with(&format!("{0}, {0});\n", size)));
+/// }
+/// assert_eq!(script.lines().count(), 3);
+/// ```
+///
+/// ```
+/// use include_js::{JSString, JSTemplate};
+/// use serde::Serialize;
+///
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(template = "src/js/move_window.js.handlebars", fn)]
+/// pub struct MoveWindow {
+///     x: u32,
+///     y: u32,
+///     width: u32,
+///     height: u32,
+///     window_class: String,
+/// }
+///
+/// #[derive(JSTemplate)]
+/// #[include_js(template = "src/js/resize_window.js.handlebars", fn = "resize")]
+/// struct ResizeWindow(u32, u32);
+///
+/// let js: JSString = move_window(0, 5, 100, 200, "Nautilus".to_owned());
+/// assert!(js.as_str().contains("w.move_resize_frame(true, 0, 5, 100, 200);"));
+///
+/// assert_eq!(resize(100, 200).as_str(), "global.display.focus_window.move_resize_frame(true, 0, 0, 100, 200);\n");
+/// ```
+///
+/// ```
+/// use include_js::{JSTemplate, MissingField};
+/// use serde::Serialize;
+///
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(template = "src/js/move_window.js.handlebars", builder)]
+/// struct MoveWindow {
+///     #[include_js(default = "0")]
+///     x: u32,
+///     #[include_js(default = "0")]
+///     y: u32,
+///     width: u32,
+///     height: u32,
+///     #[include_js(default = "\"Firefox\".to_owned()")]
+///     window_class: String,
+/// }
+///
+/// let js = MoveWindow::builder().width(800).height(600).y(5).render();
+/// assert!(js.as_str().contains(".filter(w => w.wm_class == \"Firefox\")"));
+/// assert!(js.as_str().contains("w.move_resize_frame(true, 0, 5, 800, 600);"));
+///
+/// let missing = MoveWindow::builder().width(800).build().err();
+/// assert_eq!(missing, Some(MissingField { template: "MoveWindow", field: "height" }));
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+/// use serde::Serialize;
+///
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(template = "src/js/move_window.js.handlebars")]
+/// struct MoveWindow {
+///     #[include_js(default)]
+///     x: u32,
+///     #[include_js(default)]
+///     y: u32,
+///     #[include_js(default = "800")]
+///     width: u32,
+///     #[include_js(default = "600")]
+///     height: u32,
+///     #[include_js(default = "\"Firefox\".to_owned()")]
+///     window_class: String,
+/// }
+///
+/// let js = MoveWindow { x: 100, ..MoveWindow::default() }.render_template();
+/// assert!(js.as_str().contains("w.move_resize_frame(true, 100, 0, 800, 600);"));
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+/// use serde::Serialize;
+///
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(inline = "global.display.focus_window.move_frame(true, {{x}}, {{y}});")]
+/// struct MoveFocused {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// let js = MoveFocused { x: 10, y: -20 }.render_template();
+/// assert_eq!(js.as_str(), "global.display.focus_window.move_frame(true, 10, -20);");
+/// ```
+///
+/// ```compile_fail
+/// use include_js::JSTemplate;
+/// use serde::Serialize;
+///
+/// // the inline template is checked like a file, the call is missing its `)`
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(inline = "global.display.focus_window.move_frame(true, {{x}}")]
+/// struct MoveFocused {
+///     x: i32,
+/// }
+/// ```
+///
+/// `src/js/enable_extension.js.handlebars`
+/// ```javascript
+/// Main.extensionManager.enableExtension({{js_string uuid}});
+/// ```
+///
+/// `src/js/disable_extension.js.handlebars`
+/// ```javascript
+/// Main.extensionManager.disableExtension({{js_string uuid}});
+/// ```
+///
+/// ```
+/// use include_js::JSTemplate;
+/// use serde::Serialize;
+///
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(template(name = "enable", path = "src/js/enable_extension.js.handlebars"))]
+/// #[include_js(template(name = "disable", path = "src/js/disable_extension.js.handlebars"))]
+/// struct Extension {
+///     uuid: String,
+/// }
+///
+/// let extension = Extension { uuid: "dash-to-dock@micxgx.gmail.com".to_owned() };
+/// assert_eq!(
+///     extension.render_enable().as_str(),
+///     "Main.extensionManager.enableExtension(\"dash-to-dock@micxgx.gmail.com\");\n",
+/// );
+/// assert_eq!(extension.render_template_named("disable"), extension.render_disable());
+/// assert!(extension.try_render_template_named("toggle").is_err());
+/// ```
+///
+/// ```
+/// mod facade {
+///     pub use include_js::{helpers, JSStr, JSString, JSTemplate, RenderError, TemplateEngine};
+/// #   #[cfg(feature = "registry")]
+/// #   pub use include_js::__private;
+/// }
+///
+/// use facade::JSTemplate;
+/// use serde::Serialize;
+///
+/// #[derive(JSTemplate, Serialize)]
+/// #[include_js(template = "src/js/move_window.js.handlebars", crate = "facade")]
+/// struct MoveWindowCommand {
+///     x: u32,
+///     y: u32,
+///     width: u32,
+///     height: u32,
+///     window_class: String,
+/// }
+///
+/// let js = MoveWindowCommand { x: 0, y: 5, width: 100, height: 200, window_class: "Nautilus".to_owned() }
+///     .render_template();
+/// ```
+#[cfg(feature = "template")]
+#[proc_macro_derive(JSTemplate, attributes(include_js))]
+pub fn derive_js_template(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    expand_js_template(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generates a struct with a field for every variable of a template and derives `JSTemplate` for it,
+/// so the two can't get out of sync. The input is the path of the template followed by `as` and the name of the struct,
+/// optionally preceded by attributes and a visibility for the struct.
+///
+/// The fields are `String`s, or `Vec<String>` for variables that are iterated with `{{#each variable}}`.
+/// Fields that should have another type or attributes are declared in braces after the name, variables with paths
+/// into them like `{{config.width}}` have to be declared like this. The declared fields are used as they are,
+/// for variables without a matching declared field a field is generated. All fields have the visibility of the
+/// struct unless they are declared with another one.
+///
+/// Further `#[include_js(...)]` attributes on the struct are passed on to the derive, except for `template`.
+/// The generated code refers to the derive as `::include_js::JSTemplate`.
+///
+/// # Examples
+///
+/// ```
+/// use include_js::{js_template, JSTemplate};
+///
+/// js_template! {
+///     /// Moves the newest window of a class
+///     #[derive(Debug, Clone)]
+///     pub "src/js/move_window.js.handlebars" as MoveWindowCommand {
+///         x: u32,
+///         y: u32,
+///         width: u32,
+///         height: u32,
+///     }
+/// }
+///
+/// js_template!("src/js/close_windows.js.handlebars" as CloseWindows);
+///
+/// let js = MoveWindowCommand { x: 0, y: 5, width: 100, height: 200, window_class: "Nautilus".to_owned() }
+///     .render_template();
+/// assert!(js.as_str().contains("w.move_resize_frame(true, 0, 5, 100, 200);"));
+///
+/// let js = CloseWindows { classes: vec!["Nautilus".to_owned()] }.render_template();
+/// assert!(js.as_str().contains(".filter(w => w.wm_class != \"Nautilus\")"));
+/// ```
+#[cfg(feature = "template")]
+#[proc_macro]
+pub fn js_template(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as template::generate::TemplateStruct);
+
+    template::generate::expand_js_template_struct(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Includes a template that is not rendered from the fields of a type but from any serializable data,
+/// e.g. a `serde_json::Value`, with `include_js::render`. The result is a `&'static CheckedTemplate`.
+///
+/// **Note:** The path must be relative to $CARGO_MANIFEST_DIR.
+///
+/// As nothing is known about the data, the template is checked at compiletime by substituting the placeholder `[]`
+/// for every variable it uses, like `#[derive(JSTemplate)]` does for fields of an unknown type.
+/// It is rendered like the templates of the derive, i.e. without escaping and with the `js_string` helper.
+/// Like for `include_js!` the path of the crate in the generated code can be changed with `crate = "..."`.
+///
+/// # Examples
+///
+/// ```
+/// use include_js::{include_js_template, CheckedTemplate};
+///
+/// static MOVE_WINDOW: &CheckedTemplate = include_js_template!("src/js/move_window.js.handlebars");
+///
+/// let data = serde_json::json!({ "x": 0, "y": 5, "width": 100, "height": 200, "window_class": "Nautilus" });
+/// let js = include_js::render(MOVE_WINDOW, &data).unwrap();
+/// assert!(js.as_str().contains("w.move_resize_frame(true, 0, 5, 100, 200);"));
+///
+/// // every variable of the template has to be given
+/// assert!(include_js::render(MOVE_WINDOW, &serde_json::json!({ "x": 0 })).is_err());
+/// ```
+///
+/// ```
+/// mod facade {
+///     pub use include_js::{include_js_template, CheckedTemplate};
+/// #   #[cfg(feature = "registry")]
+/// #   pub use include_js::__private;
+/// }
+///
+/// static MOVE_WINDOW: &facade::CheckedTemplate =
+///     facade::include_js_template!("src/js/move_window.js.handlebars", crate = "facade");
+/// ```
+#[cfg(feature = "template")]
+#[proc_macro]
+pub fn include_js_template(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as args::PathAndCrate<syn::LitStr>);
+
+    template::checked::expand_include_js_template(&args.path, &args.krate)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Reads the template at `template`, embeds it into `layout` and checks it against `fields`,
+/// `owner` is the type or variant they belong to.
+/// Returns the content of the template, the compiled template and the code that tracks it and warns about unused fields.
+#[cfg(feature = "template")]
+#[allow(clippy::too_many_arguments)]
+fn check_template(
+    template: &template::TemplateSource,
+    fields: &[template::TemplateField],
+    helpers: &[template::HelperSpec],
+    partials: &[template::Partial],
+    layout: Option<&template::TemplateFile>,
+    owner: &str,
+    check: template::CheckMode,
+    lints: &lint::Lints,
+) -> syn::Result<(String, handlebars::Template, TokenStream2, BTreeSet<String>)> {
+    let (template::TemplateFile { path: template_path, content }, track) = template.load()?;
+
+    let content = match layout {
+        Some(layout) => template::apply_layout(layout, &content)?,
+        None => content,
+    };
+
+    let compile = |content: &str, what: &str, span: Span| {
+        handlebars::Template::compile(content).map_err(|e| syn::Error::new(span, format!("error compiling {}: {}", what, e)))
+    };
+
+    let compiled = compile(&content, "template", template_path.span)?;
+
+    let referenced = {
+        let mut refs = template::references(&compiled);
+
+        // partials are rendered in the same context, so their variables count as well
+        let mut visited = BTreeSet::new();
+        while let Some(name) = refs.partials.iter().find(|name| !visited.contains(*name)).cloned() {
+            let partial = partials.iter().find(|p| p.name == name).ok_or_else(|| {
+                syn::Error::new(
+                    template_path.span,
+                    format!("partial `{0}` is not registered, add `#[include_js(partial(name = \"{0}\", path = \"...\"))]`", name),
+                )
+            })?;
+
+            let nested = template::references(&compile(&partial.file.content, "partial", partial.file.path.span)?);
+            refs.variables.extend(nested.variables);
+            refs.partials.extend(nested.partials);
+            refs.iterated.extend(nested.iterated);
+            visited.insert(name);
+        }
+
+        refs
     };
+    let iterated = &referenced.iterated;
+    let paths = &referenced.paths;
+    let referenced = &referenced.variables;
+
+    let unknown: Vec<_> = referenced
+        .iter()
+        .filter(|var| !fields.iter().any(|f| !f.flatten && &f.name() == *var))
+        // `{{helper}}` without parameters looks like a variable
+        .filter(|var| !helpers.iter().any(|h| &h.name.value() == *var))
+        .collect();
+
+    // the variables a flattened field provides are only known at runtime
+    let flattened = fields.iter().find(|f| f.flatten);
+
+    if !unknown.is_empty() && flattened.is_none() {
+        let unknown: Vec<_> = unknown.iter().map(|var| format!("`{}`", var)).collect();
+
+        return Err(syn::Error::new(
+            template_path.span,
+            format!("template variables without matching field in `{}`: {}", owner, unknown.join(", ")),
+        ));
+    }
+
+    let unused = fields.iter().map(|f| f.name()).filter(|name| !referenced.contains(name)).collect();
+
+    if check == template::CheckMode::Placeholder {
+        let mut data: HashMap<String, handlebars::JsonValue> = fields
+            .iter()
+            .filter(|f| !f.flatten)
+            .map(|f| {
+                let placeholder = handlebars::JsonValue::String(f.placeholder());
+
+                // a sequence that is iterated contains a single placeholder, so the body of the loop is checked as well
+                let value = match f.sequence_depth() {
+                    Some(depth) if iterated.contains(&f.name()) => {
+                        (0..depth).fold(placeholder, |item, _| handlebars::JsonValue::Array(vec![item]))
+                    },
+                    _ => nested_placeholder(&template::paths_into(paths, &f.name()), f.placeholder()),
+                };
+
+                (f.name(), value)
+            })
+            .collect();
+
+        if let Some(flattened) = flattened {
+            data.extend(unknown.iter().map(|var| {
+                (var.to_string(), nested_placeholder(&template::paths_into(paths, var), flattened.placeholder()))
+            }));
+        }
 
-    let expanded = {
         let mut h = Handlebars::new();
         h.set_strict_mode(true);
-        h.render_template(&content, &data)
-            .expect("error rendering template")
-    };
-    let _ = boa::parse(&expanded, false).expect("syntax error");
-
-    TokenStream::from(quote! {
-        impl JSTemplate for #struct_name {
-            fn render_template(&self) -> ::include_js::JSString {                
-                let mut h = ::include_js::TemplateEngine::new();
-                h.set_strict_mode(true);
-                let s = h.render_template(#content, self).unwrap();
-                
-                // safety: in the macro invocation it was made sure that the resulting string is js
-                unsafe {
-                    ::include_js::JSString::new_unchecked(s)
+        // placeholders are Javascript and need to end up in the output verbatim
+        h.register_escape_fn(handlebars::no_escape);
+        h.register_helper("js_string", Box::new(template::js_string_helper));
+        for helper in helpers {
+            h.register_helper(&helper.name.value(), Box::new(template::PlaceholderHelper(helper.placeholder())));
+        }
+        for partial in partials {
+            h.register_partial(&partial.name, &partial.file.content)
+                .map_err(|e| syn::Error::new(partial.file.path.span, format!("error compiling partial: {}", e)))?;
+        }
+
+        for case in template::condition_cases(fields, referenced) {
+            data.extend(case.iter().map(|(name, value)| (name.clone(), (*value).into())));
+
+            let expanded = h.render_template(&content, &data).map_err(|e| {
+                syn::Error::new(template_path.span, template::with_case(format!("error rendering template: {}", e), &case))
+            })?;
+            check_syntax(&expanded, &template_path.value, template_path.span)
+                .and_then(|_| lints.check(&expanded, false, &template_path.value, template_path.span))
+                .map_err(|e| syn::Error::new(template_path.span, template::with_case(e, &case)))?;
+        }
+    }
+
+    Ok((content, compiled, track, unused))
+}
+
+/// The placeholder of a variable with `paths` into it for the compiletime check, see `template::nested_placeholder`
+#[cfg(feature = "template")]
+fn nested_placeholder(paths: &[&[String]], placeholder: String) -> handlebars::JsonValue {
+    template::nested_placeholder(paths, &|| placeholder.clone().into(), &|fields| {
+        handlebars::JsonValue::Object(fields.into_iter().collect())
+    })
+}
+
+#[cfg(feature = "template")]
+fn expand_js_template(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let template::TemplateArgs {
+        template,
+        named,
+        check,
+        escape,
+        engine,
+        helpers,
+        partials,
+        layout,
+        lints,
+        krate,
+        function,
+        builder,
+    } = template::get_args(input)?;
+    let name = &input.ident;
+
+    let (partials, partial_tracks): (Vec<_>, Vec<_>) = partials
+        .iter()
+        .map(template::PartialSpec::load)
+        .collect::<syn::Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    // the engine registers the partials when it is first used and can't report errors then
+    for partial in &partials {
+        handlebars::Template::compile(&partial.file.content)
+            .map_err(|e| syn::Error::new(partial.file.path.span, format!("error compiling partial: {}", e)))?;
+    }
+
+    let (layout, layout_track) = match layout {
+        Some(layout) => {
+            let (layout, track) = template::TemplateFile::load(&layout)?;
+            (Some(layout), Some(track))
+        },
+        None => (None, None),
+    };
+    let mut bounds = Vec::new();
+    let mut sources = Vec::new();
+
+    let registrations = helpers.iter().map(template::HelperSpec::register);
+    let partial_registrations = partials.iter().map(|template::Partial { name, file }| {
+        let content = &file.content;
+        quote! {
+            h.register_partial(#name, #content).expect("the partial is compiled at compiletime");
+        }
+    });
+
+    let escape_fn = match escape {
+        template::EscapeMode::None => quote! { h.register_escape_fn(#krate::helpers::no_escape); },
+        template::EscapeMode::Html => quote! {},
+    };
+
+    let renderer = template::Renderer {
+        setup: quote! {
+            let mut h = #krate::TemplateEngine::new();
+            h.set_strict_mode(true);
+            #escape_fn
+            h.register_helper("js_string", Box::new(#krate::helpers::js_string));
+            #(#registrations)*
+            #(#partial_registrations)*
+        },
+        helpers: &helpers,
+        escape,
+        krate: &krate,
+    };
+
+    let check_template = |template: &template::TemplateSource, fields: &[template::TemplateField], owner: &str| match engine {
+        template::Engine::Handlebars => {
+            check_template(template, fields, &helpers, &partials, layout.as_ref(), owner, check, &lints)
+                .map(|(content, compiled, track, unused)| (content, template::Compiled::Handlebars(compiled), track, unused))
+        },
+        #[cfg(feature = "minijinja")]
+        template::Engine::Minijinja => template::jinja::check_template(template, fields, owner, check, &lints)
+            .map(|(content, track, unused)| (content, template::Compiled::Minijinja, track, unused)),
+    };
+
+    let validate = (check == template::CheckMode::Runtime).then(|| {
+        quote! {
+            if let Some(e) = #krate::JSStr::new(&buf[start..]).err() {
+                buf.truncate(start);
+                return Err(#krate::RenderError::Syntax(e));
+            }
+        }
+    });
+    let mut named_renders = Vec::new();
+
+    let (checks, render) = match &input.data {
+        Data::Struct(ds) => {
+            if template.is_none() && named.is_empty() {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "missing template specification, add `#[include_js(template = \"...\")]` or `#[include_js(inline = \"...\")]`",
+                ));
+            }
+
+            let fields = template::fields(&ds.fields, &input.attrs)?;
+            bounds.extend(template::serialize_bounds(&fields, &krate));
+
+            let mut tracks = Vec::new();
+            // a field only has to be used by one of the templates
+            let mut unused: Option<BTreeSet<String>> = None;
+            let mut check_template = |source: &template::TemplateSource| {
+                let (content, compiled, track, template_unused) = check_template(source, &fields, &name.to_string())?;
+                sources.push(registry::register(&krate, registry::Kind::Template, &source.name(), &content, None));
+                tracks.push(track);
+                unused = Some(match unused.take() {
+                    Some(unused) => unused.intersection(&template_unused).cloned().collect(),
+                    None => template_unused,
+                });
+
+                syn::Result::Ok(renderer.render_struct(&ds.fields, &fields, &content, &compiled))
+            };
+
+            let render = template.as_ref().map(&mut check_template).transpose()?;
+            for template in &named {
+                named_renders.push((template, check_template(&template.source)?));
+            }
+
+            let place = match (&template, named.as_slice()) {
+                (Some(source), []) => format!("template '{}'", source.name()),
+                (None, [template]) => format!("template '{}'", template.source.name()),
+                _ => format!("any template of `{}`", name),
+            };
+            let unused = unused.unwrap_or_default();
+            let warnings = fields
+                .iter()
+                .filter(|f| !f.flatten && unused.contains(&f.name()))
+                .map(|f| template::unused_field_warning(f, &place));
+
+            (quote! { #(#tracks)* #(#warnings)* }, render)
+        },
+        Data::Enum(de) => {
+            if let Some(template) = template {
+                return Err(syn::Error::new(
+                    template.span(),
+                    "the templates of an enum are specified on its variants",
+                ));
+            }
+
+            let mut checks = Vec::new();
+            let mut arms = Vec::new();
+
+            for variant in &de.variants {
+                let template = template::variant_template(variant)?;
+                let fields = template::fields(&variant.fields, &variant.attrs)?;
+                let owner = format!("{}::{}", name, variant.ident);
+                let (content, compiled, track, unused) = check_template(&template, &fields, &owner)?;
+                arms.push(renderer.render_variant(variant, &fields, &content, &compiled));
+                bounds.extend(template::serialize_bounds(&fields, &krate));
+                sources.push(registry::register(&krate, registry::Kind::Template, &template.name(), &content, None));
+
+                let place = format!("template '{}'", template.name());
+                let warnings = fields
+                    .iter()
+                    .filter(|f| !f.flatten && unused.contains(&f.name()))
+                    .map(|f| template::unused_field_warning(f, &place));
+                checks.push(quote! { #track #(#warnings)* });
+            }
+
+            if arms.is_empty() {
+                return Err(syn::Error::new(name.span(), "enums without variants are not supported"));
+            }
+
+            (quote! { #(#checks)* }, Some(quote! { match self { #(#arms,)* } }))
+        },
+        Data::Union(du) => return Err(syn::Error::new(du.union_token.span, "unions are not supported")),
+    };
+
+    // the template is rendered from the fields, which are only `Serialize` if the type parameters are
+    let mut generics = input.generics.clone();
+    if generics.type_params().next().is_some() {
+        generics.make_where_clause().predicates.extend(bounds);
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let render_fn = match (&function, &input.data) {
+        (Some(function), _) if render.is_none() => {
+            return Err(syn::Error::new(function.span(), "`fn` renders the template given with `template = \"...\"`"));
+        },
+        (Some(function), Data::Struct(ds)) => Some(template::render_fn(input, &ds.fields, function, &generics, &krate)),
+        _ => None,
+    };
+    let builder = match (builder, &input.data) {
+        (true, Data::Struct(ds)) => Some(template::builder(input, &ds.fields, &generics, render.is_some(), &krate)?),
+        _ => None,
+    };
+    let named_renders = (!named_renders.is_empty()).then(|| template::named_renders(input, &named_renders, &generics, validate.as_ref(), &krate));
+
+    let template_impl = render.map(|render| {
+        quote! {
+            impl #impl_generics #krate::JSTemplate for #name #ty_generics #where_clause {
+                fn try_render_template(&self) -> ::core::result::Result<#krate::JSString, #krate::RenderError> {
+                    let mut s = ::std::string::String::new();
+                    self.try_render_into(&mut s)?;
+
+                    // safety: `try_render_into` made sure that the rendered template is js
+                    ::core::result::Result::Ok(unsafe { #krate::JSString::new_unchecked(s) })
+                }
+
+                fn try_render_into<'b>(
+                    &self,
+                    buf: &'b mut ::std::string::String,
+                ) -> ::core::result::Result<&'b #krate::JSStr, #krate::RenderError> {
+                    let start = buf.len();
+                    let out = &mut *buf;
+
+                    if let ::core::result::Result::Err(e) = #render {
+                        buf.truncate(start);
+                        return ::core::result::Result::Err(e);
+                    }
+
+                    #validate
+
+                    // safety: in the macro invocation it was made sure that the resulting string is js,
+                    // it was checked above or the user explicitly opted out of the check
+                    ::core::result::Result::Ok(unsafe { #krate::JSStr::new_unchecked(&buf[start..]) })
                 }
             }
         }
+    });
+    let default_impl = match &input.data {
+        Data::Struct(ds) => template::default_impl(input, &ds.fields)?,
+        _ => None,
+    };
+
+    Ok(quote! {
+        #checks
+        #(#partial_tracks)*
+        #layout_track
+        #(#sources)*
+
+        #template_impl
+        #named_renders
+        #render_fn
+        #builder
+        #default_impl
     })
 }
@@ -0,0 +1,347 @@
+//! Constructs that can be forbidden in the included Javascript, e.g. with `include_js!("path", deny(eval, with))`
+//! or `include_js!("path", forbid_globals = ["document"])`, and functions it has to define,
+//! e.g. with `include_js!("path", require_fn = ["init"])`.
+//!
+//! The checks walk the syntax tree of swc, so they need the `parser-swc` feature regardless of the parser
+//! that validates the code.
+
+use proc_macro2::Span;
+use syn::{
+    bracketed, parenthesized,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Ident, LitStr, Token,
+};
+
+/// A construct that can be denied with `deny(...)`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Lint {
+    /// references to `eval`, including indirect calls like `(0, eval)(code)` and properties like `window.eval`,
+    /// and calls of the `Function` constructor
+    Eval,
+    /// `with` statements
+    With,
+    /// `debugger` statements
+    Debugger,
+    /// statements that rely on automatic semicolon insertion
+    Asi,
+}
+
+impl Parse for Lint {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident: Ident = input.parse()?;
+
+        match ident.to_string().as_str() {
+            "eval" => Ok(Lint::Eval),
+            "with" => Ok(Lint::With),
+            "debugger" => Ok(Lint::Debugger),
+            "asi" => Ok(Lint::Asi),
+            _ => Err(syn::Error::new(ident.span(), "expected one of `eval`, `with`, `debugger` or `asi`")),
+        }
+    }
+}
+
+/// The lints of a macro invocation, i.e. what the included code must not or has to contain
+#[derive(Clone, Default)]
+pub(super) struct Lints {
+    pub(super) deny: Vec<Lint>,
+    /// the globals the code must not reference
+    pub(super) forbid_globals: Vec<String>,
+    /// the functions the code has to declare at the top level
+    pub(super) require_fn: Vec<String>,
+}
+
+impl Lints {
+    pub(super) fn is_empty(&self) -> bool {
+        self.deny.is_empty() && self.forbid_globals.is_empty() && self.require_fn.is_empty()
+    }
+
+    /// Parses the parenthesized list after `deny`, `span` is the span of the keyword.
+    pub(super) fn parse_deny(input: ParseStream, span: Span) -> syn::Result<Vec<Lint>> {
+        require_swc(span)?;
+
+        let content;
+        parenthesized!(content in input);
+
+        Ok(Punctuated::<Lint, Token![,]>::parse_terminated(&content)?.into_iter().collect())
+    }
+
+    /// Parses a list of names like the one after `forbid_globals =`, `span` is the span of the keyword.
+    pub(super) fn parse_names(input: ParseStream, span: Span) -> syn::Result<Vec<String>> {
+        require_swc(span)?;
+
+        let content;
+        bracketed!(content in input);
+
+        Ok(Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?.iter().map(LitStr::value).collect())
+    }
+
+    /// Checks `content` as a script or module and converts the first violation into a compile error at `span`.
+    #[cfg(feature = "parser-swc")]
+    pub(super) fn check(&self, content: &str, module: bool, origin: &str, span: Span) -> syn::Result<()> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => swc::check(self, content, module, origin, span),
+        }
+    }
+
+    /// Without the `parser-swc` feature lints are rejected when parsing the arguments, so there is nothing to check.
+    #[cfg(not(feature = "parser-swc"))]
+    pub(super) fn check(&self, _content: &str, _module: bool, _origin: &str, _span: Span) -> syn::Result<()> {
+        debug_assert!(self.is_empty(), "lints are rejected without the `parser-swc` feature");
+        Ok(())
+    }
+}
+
+fn require_swc(span: Span) -> syn::Result<()> {
+    match cfg!(feature = "parser-swc") {
+        true => Ok(()),
+        false => Err(syn::Error::new(span, "lints require the `parser-swc` feature of include_js")),
+    }
+}
+
+#[cfg(feature = "parser-swc")]
+mod swc {
+    use proc_macro2::Span;
+    use swc_common::Spanned;
+    use swc_ecma_ast::*;
+    use swc_ecma_visit::{Visit, VisitWith};
+
+    use super::{Lint, Lints};
+    use crate::parser;
+
+    impl Lint {
+        /// What the lint denies, for error messages
+        fn description(self) -> &'static str {
+            match self {
+                Lint::Eval => "`eval`",
+                Lint::With => "`with`",
+                Lint::Debugger => "`debugger`",
+                Lint::Asi => "automatic semicolon insertion",
+            }
+        }
+    }
+
+    pub(super) fn check(lints: &Lints, content: &str, module: bool, origin: &str, span: Span) -> syn::Result<()> {
+        let parsed = parser::swc::parse(content, module, false, origin, span)?;
+        let mut checker = Checker { lints, parsed: &parsed, violation: None };
+        parsed.program.visit_with(&mut checker);
+
+        if let Some((pos, lint)) = checker.violation {
+            return Err(syn::Error::new(
+                span,
+                format!("{} is denied but used in '{}' at {}", lint.description(), origin, parsed.location(pos)),
+            ));
+        }
+
+        check_globals(lints, content, module, origin, span)?;
+        check_required(lints, &parsed.program, origin, span)
+    }
+
+    fn check_globals(lints: &Lints, content: &str, module: bool, origin: &str, span: Span) -> syn::Result<()> {
+        if lints.forbid_globals.is_empty() {
+            return Ok(());
+        }
+
+        // SAFETY: the code is validated before the lints are checked, and as swc just parsed it
+        // the analysis, which is done by swc as well, can't fail on it
+        let free = if module {
+            unsafe { include_js_core::JSModule::new_unchecked(content) }.free_variables()
+        } else {
+            unsafe { include_js_core::JSStr::new_unchecked(content) }.free_variables()
+        };
+
+        let forbidden: Vec<_> = lints
+            .forbid_globals
+            .iter()
+            .filter(|global| free.contains(*global))
+            .map(|global| format!("`{}`", global))
+            .collect();
+
+        match forbidden.is_empty() {
+            true => Ok(()),
+            false => Err(syn::Error::new(
+                span,
+                format!("'{}' references forbidden globals: {}", origin, forbidden.join(", ")),
+            )),
+        }
+    }
+
+    fn check_required(lints: &Lints, program: &Program, origin: &str, span: Span) -> syn::Result<()> {
+        let mut defined = Vec::new();
+
+        match program {
+            Program::Script(script) => script.body.iter().for_each(|stmt| stmt_functions(stmt, &mut defined)),
+            Program::Module(module) => {
+                for item in &module.body {
+                    match item {
+                        ModuleItem::Stmt(stmt) => stmt_functions(stmt, &mut defined),
+                        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)) => decl_functions(&export.decl, &mut defined),
+                        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+                            decl: DefaultDecl::Fn(FnExpr { ident: Some(ident), .. }),
+                            ..
+                        })) => defined.push(&ident.sym),
+                        ModuleItem::ModuleDecl(_) => {},
+                    }
+                }
+            },
+        }
+
+        let missing: Vec<_> = lints
+            .require_fn
+            .iter()
+            .filter(|name| !defined.contains(&name.as_str()))
+            .map(|name| format!("`{}`", name))
+            .collect();
+
+        match missing.is_empty() {
+            true => Ok(()),
+            false => Err(syn::Error::new(
+                span,
+                format!("'{}' does not define the required functions: {}", origin, missing.join(", ")),
+            )),
+        }
+    }
+
+    /// Adds the names of the functions `stmt` declares at the top level to `defined`
+    fn stmt_functions<'a>(stmt: &'a Stmt, defined: &mut Vec<&'a str>) {
+        if let Stmt::Decl(decl) = stmt {
+            decl_functions(decl, defined);
+        }
+    }
+
+    /// Adds the names of the functions `decl` declares to `defined`, i.e. function declarations
+    /// and variables that are initialized with a function or arrow function
+    fn decl_functions<'a>(decl: &'a Decl, defined: &mut Vec<&'a str>) {
+        match decl {
+            Decl::Fn(function) => defined.push(&function.ident.sym),
+            Decl::Var(var) => {
+                for declarator in &var.decls {
+                    if let (Pat::Ident(ident), Some(init)) = (&declarator.name, &declarator.init) {
+                        if matches!(&**init, Expr::Fn(_) | Expr::Arrow(_)) {
+                            defined.push(&ident.id.sym);
+                        }
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Whether `prop` is the property `name`, i.e. `.name`, `["name"]` or `[`name`]`
+    fn is_property(prop: &MemberProp, name: &str) -> bool {
+        match prop {
+            MemberProp::Ident(ident) => &*ident.sym == name,
+            MemberProp::Computed(computed) => match &*computed.expr {
+                Expr::Lit(Lit::Str(lit)) => lit.value == name,
+                Expr::Tpl(tpl) => {
+                    tpl.exprs.is_empty() && tpl.quasis.iter().all(|quasi| quasi.cooked.as_ref().is_some_and(|c| *c == name))
+                },
+                _ => false,
+            },
+            MemberProp::PrivateName(_) => false,
+        }
+    }
+
+    /// Whether `callee` is the `Function` constructor, which evaluates its last argument like `eval`
+    fn is_function_constructor(callee: &Expr) -> bool {
+        match callee {
+            Expr::Ident(ident) => &*ident.sym == "Function",
+            Expr::Member(member) => is_property(&member.prop, "Function"),
+            Expr::Paren(paren) => is_function_constructor(&paren.expr),
+            _ => false,
+        }
+    }
+
+    struct Checker<'a> {
+        lints: &'a Lints,
+        parsed: &'a parser::swc::Parsed,
+        violation: Option<(swc_common::BytePos, Lint)>,
+    }
+
+    impl Checker<'_> {
+        fn found(&mut self, span: swc_common::Span, lint: Lint) {
+            if self.lints.deny.contains(&lint) && self.violation.is_none() {
+                self.violation = Some((span.lo, lint));
+            }
+        }
+
+        /// Checks that the statement at `span`, which has to end with a semicolon, is terminated explicitly.
+        /// The span of a statement includes its semicolon if there is one.
+        fn terminated(&mut self, span: swc_common::Span) {
+            let source = self.parsed.source(span).unwrap_or_default();
+
+            if !source.trim_end().ends_with(';') {
+                self.found(span, Lint::Asi);
+            }
+        }
+    }
+
+    impl Visit for Checker<'_> {
+        fn visit_stmt(&mut self, node: &Stmt) {
+            match node {
+                Stmt::Expr(_)
+                | Stmt::Decl(Decl::Var(_))
+                | Stmt::Return(_)
+                | Stmt::Throw(_)
+                | Stmt::Break(_)
+                | Stmt::Continue(_)
+                | Stmt::Debugger(_)
+                | Stmt::DoWhile(_) => self.terminated(node.span()),
+                _ => {},
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_module_decl(&mut self, node: &ModuleDecl) {
+            match node {
+                ModuleDecl::Import(_)
+                | ModuleDecl::ExportNamed(_)
+                | ModuleDecl::ExportAll(_)
+                | ModuleDecl::ExportDefaultExpr(_)
+                | ModuleDecl::ExportDecl(ExportDecl { decl: Decl::Var(_), .. }) => self.terminated(node.span()),
+                _ => {},
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_expr(&mut self, node: &Expr) {
+            if let Expr::Ident(ident) = node {
+                if &*ident.sym == "eval" {
+                    self.found(ident.span, Lint::Eval);
+                }
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_member_expr(&mut self, node: &MemberExpr) {
+            if is_property(&node.prop, "eval") {
+                self.found(node.span, Lint::Eval);
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_call_expr(&mut self, node: &CallExpr) {
+            if matches!(&node.callee, Callee::Expr(callee) if is_function_constructor(callee)) {
+                self.found(node.span, Lint::Eval);
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_new_expr(&mut self, node: &NewExpr) {
+            if is_function_constructor(&node.callee) {
+                self.found(node.span, Lint::Eval);
+            }
+            node.visit_children_with(self);
+        }
+
+        fn visit_with_stmt(&mut self, node: &WithStmt) {
+            self.found(node.span, Lint::With);
+            node.visit_children_with(self);
+        }
+
+        fn visit_debugger_stmt(&mut self, node: &DebuggerStmt) {
+            self.found(node.span, Lint::Debugger);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{ext::IdentExt, spanned::Spanned, Data, DeriveInput, Fields};
+
+pub(super) fn expand_to_js_literal(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(ds) => match &ds.fields {
+            Fields::Named(fields) => &fields.named,
+            other => return Err(syn::Error::new(other.span(), "only structs with named fields are supported")),
+        },
+        _ => return Err(syn::Error::new(input.ident.span(), "only structs supported")),
+    };
+
+    let struct_name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let properties = fields.iter().enumerate().map(|(i, f)| {
+        let ident = f.ident.as_ref().unwrap();
+        let key = ident.unraw().to_string();
+        let separator = if i == 0 { quote!() } else { quote!(out.push_str(", ");) };
+
+        quote! {
+            #separator
+            ::include_js::ToJsLiteral::write_js_literal(#key, out);
+            out.push_str(": ");
+            ::include_js::ToJsLiteral::write_js_literal(&self.#ident, out);
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::include_js::ToJsLiteral for #struct_name #ty_generics #where_clause {
+            fn write_js_literal(&self, out: &mut ::include_js::__private::String) {
+                out.push('{');
+                #(#properties)*
+                out.push('}');
+            }
+        }
+    })
+}
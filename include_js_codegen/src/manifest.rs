@@ -0,0 +1,85 @@
+//! The manifest of the embedded code, `$OUT_DIR/include_js_manifest.json`, with the `manifest` feature.
+//!
+//! The manifest is a JSON array with an object per embedded script, sorted by path, with its `path`, `kind`, `size`
+//! and `sha256` like `include_js::registry()` reports them and, for code that is only embedded in some builds,
+//! the `cfg` it is embedded under. The entries are collected in memory for each manifest, i.e. each `OUT_DIR`,
+//! and the manifest is written again after every entry. `cargo build` loads the macros anew for every crate, so the
+//! manifest lists the scripts of the last build of the crate. A process that keeps the macros loaded while the code
+//! changes, like the proc macro server of rust-analyzer, can leave scripts in it that are no longer embedded.
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+};
+
+use proc_macro2::{Span, TokenStream};
+
+use super::{registry::Kind, source_map::json_string};
+
+/// An embedded script, the fields are in the order they are written in
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Entry {
+    path: String,
+    kind: &'static str,
+    size: usize,
+    sha256: String,
+    cfg: Option<String>,
+}
+
+/// The entries of each manifest, the macros can be loaded once for several crates
+static ENTRIES: Mutex<BTreeMap<PathBuf, Vec<Entry>>> = Mutex::new(BTreeMap::new());
+
+/// Adds the embedded `content` of `path` to the manifest and writes it. Returns a compile error if it can't be
+/// written, as tooling would read an outdated manifest otherwise, and nothing without the `manifest` feature
+/// or if the crate has no build script, i.e. no `OUT_DIR`.
+pub(super) fn record(kind: Kind, path: &str, content: &str, sha256: &str, cfg: Option<&str>) -> TokenStream {
+    let manifest = match std::env::var_os("OUT_DIR") {
+        Some(out_dir) if cfg!(feature = "manifest") => PathBuf::from(out_dir).join("include_js_manifest.json"),
+        _ => return TokenStream::new(),
+    };
+
+    let entry = Entry {
+        path: path.to_owned(),
+        kind: kind.name(),
+        size: content.len(),
+        sha256: sha256.to_owned(),
+        cfg: cfg.map(str::to_owned),
+    };
+
+    let mut manifests = ENTRIES.lock().unwrap_or_else(PoisonError::into_inner);
+    let entries = manifests.entry(manifest.clone()).or_default();
+
+    // a script that is included several times the same way is only listed once
+    if let Err(i) = entries.binary_search(&entry) {
+        entries.insert(i, entry);
+    }
+
+    let lines: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            let cfg = match &entry.cfg {
+                Some(cfg) => format!(r#","cfg":{}"#, json_string(cfg)),
+                None => String::new(),
+            };
+
+            format!(
+                r#"  {{"path":{},"kind":"{}","size":{},"sha256":"{}"{}}}"#,
+                json_string(&entry.path),
+                entry.kind,
+                entry.size,
+                entry.sha256,
+                cfg
+            )
+        })
+        .collect();
+
+    match std::fs::write(&manifest, format!("[\n{}\n]\n", lines.join(",\n"))) {
+        Ok(()) => TokenStream::new(),
+        Err(e) => syn::Error::new(
+            Span::call_site(),
+            format!("could not write the manifest '{}': {}", manifest.display(), e),
+        )
+        .into_compile_error(),
+    }
+}
@@ -0,0 +1,3 @@
+import { square } from './math.js';
+
+console.log(square(4));
@@ -0,0 +1,203 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use proc_macro2::Span;
+use syn::{
+    parse::{Parse, ParseStream},
+    LitStr,
+};
+
+/// The parser used to validate the Javascript in a macro invocation
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Parser {
+    Boa,
+    /// swc, optionally rejecting everything newer than the given ECMAScript version
+    Swc(Option<u16>),
+}
+
+impl Parse for Parser {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lit: LitStr = input.parse()?;
+
+        match lit.value().as_str() {
+            "boa" => Ok(Parser::Boa),
+            "swc" if cfg!(feature = "parser-swc") => Ok(Parser::Swc(None)),
+            "swc" => Err(syn::Error::new(
+                lit.span(),
+                "the swc parser requires the `parser-swc` feature of include_js",
+            )),
+            _ => Err(syn::Error::new(lit.span(), "expected one of \"boa\" or \"swc\"")),
+        }
+    }
+}
+
+/// The stack size of the threads `prepare` parses on, parsing deeply nested code recurses deeply,
+/// so they get as much as the main thread of rustc
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Parses `content` as a script with `parser`, in strict mode if `strict` is set,
+/// and converts a syntax error into a compile error at `span`.
+pub(super) fn check_script(parser: Parser, content: &str, strict: bool, origin: &str, span: Span) -> syn::Result<()> {
+    validate(parser, content, false, strict, origin).map_err(|msg| error(&msg, origin, span))
+}
+
+/// Parses `content` as a module with `parser` and converts a syntax error into a compile error at `span`.
+pub(super) fn check_module(parser: Parser, content: &str, origin: &str, span: Span) -> syn::Result<()> {
+    validate(parser, content, true, true, origin).map_err(|msg| error(&msg, origin, span))
+}
+
+/// Validates all of `contents` in parallel, so checking them one after another afterwards only has to parse the
+/// invalid ones again. Spans can't be sent to other threads, so the errors are only reported by the checks.
+pub(super) fn prepare(parser: Parser, contents: &[&str], module: bool, strict: bool) {
+    let threads = thread::available_parallelism().map_or(1, usize::from).min(contents.len());
+    let next = AtomicUsize::new(0);
+
+    if threads < 2 {
+        return;
+    }
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let worker = || {
+                while let Some(content) = contents.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let _ = validate(parser, content, module, strict, "");
+                }
+            };
+
+            // without threads the code is simply parsed when it is checked
+            if thread::Builder::new().stack_size(STACK_SIZE).spawn_scoped(scope, worker).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Validates `content` like `check_script` and `check_module` do, unless it is cached, and returns the message
+/// of the syntax error if it is invalid.
+#[cfg_attr(not(feature = "parser-swc"), allow(unused_variables))]
+fn validate(parser: Parser, content: &str, module: bool, strict: bool, origin: &str) -> Result<(), String> {
+    let mode = match parser {
+        Parser::Boa => "boa".to_owned(),
+        Parser::Swc(None) => "swc".to_owned(),
+        Parser::Swc(Some(es)) => format!("swc es{}", es),
+    };
+    let mode = format!("{} module={} strict={}", mode, module, strict);
+
+    super::cache::validated(&mode, content, || match parser {
+        Parser::Boa if module => include_js_core::JSModule::new(content)
+            .map(|_| ())
+            .map_err(|e| e.into_parse_error().to_string()),
+        Parser::Boa => {
+            debug_assert!(!strict, "`strict` implies the swc parser");
+            include_js_core::JSStr::new(content)
+                .map(|_| ())
+                .map_err(|e| e.into_parse_error().to_string())
+        },
+        #[cfg(feature = "parser-swc")]
+        Parser::Swc(es) => swc::validate(content, module, strict, es, origin),
+        #[cfg(not(feature = "parser-swc"))]
+        Parser::Swc(_) => unreachable!("the swc parser is rejected when parsing the arguments"),
+    })
+}
+
+/// The compile error at `span` for the syntax error `msg` in `origin`
+fn error(msg: &str, origin: &str, span: Span) -> syn::Error {
+    syn::Error::new(span, format!("syntax error in '{}': {}", origin, msg))
+}
+
+#[cfg(feature = "parser-swc")]
+pub(super) mod swc {
+    use proc_macro2::Span;
+    use swc_common::{errors::SourceMapper, sync::Lrc, BytePos, FileName, SourceMap, Spanned};
+    use swc_ecma_ast::Program;
+    use swc_ecma_parser::{Parser, StringInput, Syntax};
+
+    use crate::es_version;
+
+    /// `content` parsed by swc, together with the source map to locate its nodes
+    pub(crate) struct Parsed {
+        cm: Lrc<SourceMap>,
+        pub(crate) program: Program,
+    }
+
+    impl Parsed {
+        /// The position `pos` as it is written in error messages, i.e. `line 1, col 1`
+        pub(crate) fn location(&self, pos: BytePos) -> String {
+            location(&self.cm, pos)
+        }
+
+        /// The code of the node at `span`
+        pub(crate) fn source(&self, span: swc_common::Span) -> Option<String> {
+            self.cm.span_to_snippet(span).ok()
+        }
+    }
+
+    /// Parses `content` as a script or module and converts a syntax error into a compile error at `span`.
+    /// Scripts are parsed in strict mode if `strict` is set, modules always are.
+    pub(crate) fn parse(content: &str, module: bool, strict: bool, origin: &str, span: Span) -> syn::Result<Parsed> {
+        try_parse(content, module, strict, origin).map_err(|msg| super::error(&msg, origin, span))
+    }
+
+    /// Like `parse`, but a syntax error is returned as its message
+    fn try_parse(content: &str, module: bool, strict: bool, origin: &str) -> Result<Parsed, String> {
+        let cm: Lrc<SourceMap> = Default::default();
+        let file = cm.new_source_file(Lrc::new(FileName::Custom(origin.to_owned())), content.to_owned());
+        let mut parser = Parser::new(Syntax::Es(Default::default()), StringInput::from(&*file), None);
+
+        let program = if module {
+            parser.parse_module().map(Program::Module)
+        } else if strict {
+            parser.strict_mode(|parser| parser.parse_script()).map(Program::Script)
+        } else {
+            parser.parse_script().map(Program::Script)
+        };
+
+        // swc recovers from some errors and only reports them afterwards
+        let recovered = parser.take_errors();
+
+        let message = |pos: BytePos, msg: &str| message(&location(&cm, pos), msg);
+
+        match (program, recovered.first()) {
+            (Err(e), _) => Err(message(e.span().lo, &e.kind().msg())),
+            (Ok(_), Some(e)) => Err(message(e.span().lo, &e.kind().msg())),
+            (Ok(program), None) => Ok(Parsed { cm, program }),
+        }
+    }
+
+    /// Parses `content` and checks that it only uses features up to `es`, returns the message of the error if not.
+    pub(super) fn validate(content: &str, module: bool, strict: bool, es: Option<u16>, origin: &str) -> Result<(), String> {
+        let parsed = try_parse(content, module, strict, origin)?;
+
+        match es.and_then(|target| es_version::check(&parsed.program, target)) {
+            Some(v) => Err(message(
+                &parsed.location(v.span.lo),
+                &format!(
+                    "{} introduced in {} but the target is {}",
+                    v.feature,
+                    es_version::display(v.version),
+                    es_version::display(es.unwrap_or_default())
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The position `pos` in the file of `cm` as it is written in error messages
+    pub(crate) fn location(cm: &SourceMap, pos: BytePos) -> String {
+        let loc = cm.lookup_char_pos(pos);
+        format!("line {}, col {}", loc.line, loc.col.0 + 1)
+    }
+
+    /// The syntax error `msg` at `location` in code that was transformed
+    #[cfg(any(feature = "jsx", feature = "transpile"))]
+    pub(crate) fn syntax_error(location: &str, msg: &str, origin: &str, span: Span) -> syn::Error {
+        super::error(&message(location, msg), origin, span)
+    }
+
+    /// The message of the syntax error `msg` at `location`
+    fn message(location: &str, msg: &str) -> String {
+        format!("{} at {}", msg, location)
+    }
+}
@@ -0,0 +1,82 @@
+//! Checks of the paths written in the macro invocations and hints for paths that don't exist.
+
+use std::path::{Path, PathBuf};
+
+/// Whether `path` is absolute on any platform, i.e. starts with a separator or a drive letter like `C:`
+pub(super) fn is_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with(['/', '\\']) || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+/// The number of chars that have to be inserted, removed or replaced to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, &cb) in b.iter().enumerate() {
+            let replaced = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Whether the file `name` was probably meant when `written` was written, e.g. because the case or the extension differs
+fn is_near_miss(written: &str, name: &str) -> bool {
+    let stem = |name: &str| Path::new(name).file_stem().map(|stem| stem.to_owned());
+    let distance = edit_distance(&written.to_lowercase(), &name.to_lowercase());
+
+    written != name && (distance <= (written.chars().count() / 4).max(1) || stem(written) == stem(name))
+}
+
+/// The existing paths relative to `base` that were probably meant with `rel_path`, which does not exist.
+/// The components of `rel_path` that don't exist are replaced with the entries next to them that are similar to them.
+pub(super) fn near_misses(base: &Path, rel_path: &Path) -> Vec<PathBuf> {
+    let mut dir = base.to_owned();
+    let mut written = PathBuf::new();
+    let mut components = rel_path.components();
+
+    while let Some(component) = components.next() {
+        let next = dir.join(component);
+
+        if next.exists() {
+            dir = next;
+            written.push(component);
+            continue;
+        }
+
+        let missing = component.as_os_str().to_string_lossy();
+        let rest: PathBuf = components.collect();
+
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut candidates: Vec<_> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| is_near_miss(&missing, name))
+            .flat_map(|name| match rest.as_os_str().is_empty() {
+                true => vec![written.join(name)],
+                // the rest of the path can be misspelled as well
+                false => match written.join(&name).join(&rest) {
+                    candidate if base.join(&candidate).exists() => vec![candidate],
+                    candidate => near_misses(base, &candidate),
+                },
+            })
+            .collect();
+
+        candidates.sort();
+        candidates.truncate(3);
+        return candidates;
+    }
+
+    Vec::new()
+}
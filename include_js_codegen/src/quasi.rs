@@ -0,0 +1,297 @@
+use proc_macro2::{Delimiter, Ident, Spacing, Span, TokenStream, TokenTree};
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Expr, LitStr, Token,
+};
+
+/// A piece of Javascript given to `js!` or `format_js!`, either literal code or a Rust expression to splice in at runtime
+enum Fragment {
+    Code(String),
+    Interpolation(Box<Expr>),
+}
+
+struct Printer {
+    fragments: Vec<Fragment>,
+    code: String,
+    /// whether the previous token was a punct that is joined with the next one, e.g. the first `=` of `==`
+    joint: bool,
+    /// whether the code so far ends with an interpolated value, e.g. a number that would merge with a following `.`
+    after_interpolation: bool,
+}
+
+impl Printer {
+    /// Whether tokens have to be separated by a space, this is only for readability,
+    /// the spaces are left out where they are never needed.
+    fn needs_space(&self, next: &str) -> bool {
+        let after_opening = self.code.ends_with(['.', '(', '[']);
+        let before_closing = matches!(next, "," | ";" | ")" | "]");
+        let call_or_index = matches!(next, "(" | "[")
+            && self.code.ends_with(|c: char| c.is_alphanumeric() || matches!(c, '_' | '$' | ')' | ']'));
+
+        if self.after_interpolation {
+            return !before_closing;
+        }
+
+        !self.joint && !self.code.is_empty() && !after_opening && !before_closing && !call_or_index && next != "."
+    }
+
+    fn push_code(&mut self, code: &str) {
+        if self.needs_space(code) {
+            self.code.push(' ');
+        }
+        self.code.push_str(code);
+        self.joint = false;
+        self.after_interpolation = false;
+    }
+
+    fn push_interpolation(&mut self, expr: Expr) {
+        // the interpolated value is always separated by spaces from surrounding puncts so they can't merge,
+        // e.g. `x-#y` with `y = -1`
+        if self.needs_space("") {
+            self.code.push(' ');
+        }
+        self.fragments.push(Fragment::Code(std::mem::take(&mut self.code)));
+        self.fragments.push(Fragment::Interpolation(Box::new(expr)));
+        self.joint = false;
+        self.after_interpolation = true;
+    }
+
+    fn print(&mut self, tokens: TokenStream) -> syn::Result<()> {
+        let mut iter = tokens.into_iter().peekable();
+
+        while let Some(tt) = iter.next() {
+            match tt {
+                TokenTree::Punct(p) if p.as_char() == '#' => match iter.next() {
+                    Some(TokenTree::Ident(ident)) => self.push_interpolation(syn::parse2(TokenTree::Ident(ident).into())?),
+                    Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => {
+                        self.push_interpolation(syn::parse2(g.stream())?)
+                    },
+                    _ => {
+                        return Err(syn::Error::new(
+                            p.span(),
+                            "expected an identifier or a parenthesized expression after `#`",
+                        ))
+                    },
+                },
+                TokenTree::Punct(p) => {
+                    self.push_code(&p.as_char().to_string());
+                    let next_is_punct = matches!(iter.peek(), Some(TokenTree::Punct(n)) if n.as_char() != '#');
+                    self.joint = p.spacing() == Spacing::Joint && next_is_punct;
+                },
+                TokenTree::Group(g) => {
+                    let (open, close) = match g.delimiter() {
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::None => ("", ""),
+                    };
+
+                    self.push_code(open);
+                    self.print(g.stream())?;
+                    self.push_code(close);
+                },
+                TokenTree::Ident(ident) => self.push_code(&ident.to_string()),
+                TokenTree::Literal(lit) => self.push_code(&lit.to_string()),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(super) fn expand_js(tokens: TokenStream) -> syn::Result<TokenStream> {
+    let mut printer = Printer {
+        fragments: Vec::new(),
+        code: String::new(),
+        joint: true,
+        after_interpolation: false,
+    };
+
+    printer.print(tokens)?;
+    let mut fragments = printer.fragments;
+    fragments.push(Fragment::Code(printer.code));
+
+    expand_fragments(&fragments, "js! invocation", Span::call_site())
+}
+
+/// A single argument of `format_js!`, i.e. `expr` or `name = expr`
+struct FormatArg {
+    name: Option<Ident>,
+    expr: Expr,
+}
+
+impl Parse for FormatArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = if input.peek(syn::Ident) && input.peek2(Token![=]) && !input.peek2(Token![==]) {
+            let name = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Some(name)
+        } else {
+            None
+        };
+
+        Ok(FormatArg { name, expr: input.parse()? })
+    }
+}
+
+struct FormatInput {
+    template: LitStr,
+    args: Vec<FormatArg>,
+}
+
+impl Parse for FormatInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let template = input.parse()?;
+
+        let args = if input.is_empty() {
+            Vec::new()
+        } else {
+            input.parse::<Token![,]>()?;
+            Punctuated::<FormatArg, Token![,]>::parse_terminated(input)?.into_iter().collect()
+        };
+
+        Ok(FormatInput { template, args })
+    }
+}
+
+/// A piece of a `format_js!` template, placeholders are always surrounded by code pieces
+enum TemplatePiece {
+    Code(String),
+    /// the name or index of the placeholder, empty for `{}`
+    Placeholder(String),
+}
+
+fn parse_format_template(template: &LitStr) -> syn::Result<Vec<TemplatePiece>> {
+    let source = template.value();
+    let mut pieces = Vec::new();
+    let mut code = String::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                code.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                code.push('}');
+            },
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(syn::Error::new(template.span(), "unterminated placeholder, use `{{` for a literal `{`")),
+                    }
+                }
+
+                pieces.push(TemplatePiece::Code(std::mem::take(&mut code)));
+                pieces.push(TemplatePiece::Placeholder(name.trim().to_owned()));
+            },
+            '}' => return Err(syn::Error::new(template.span(), "unmatched `}`, use `}}` for a literal `}`")),
+            c => code.push(c),
+        }
+    }
+
+    pieces.push(TemplatePiece::Code(code));
+    Ok(pieces)
+}
+
+pub(super) fn expand_format_js(tokens: TokenStream) -> syn::Result<TokenStream> {
+    let FormatInput { template, args } = syn::parse2(tokens)?;
+    let pieces = parse_format_template(&template)?;
+
+    let mut used = vec![false; args.len()];
+    let mut next_positional = 0;
+    let mut fragments = Vec::with_capacity(pieces.len());
+
+    for (i, piece) in pieces.iter().enumerate() {
+        let name = match piece {
+            TemplatePiece::Code(code) => {
+                fragments.push(Fragment::Code(code.clone()));
+                continue;
+            },
+            TemplatePiece::Placeholder(name) => name,
+        };
+
+        let index = if name.is_empty() {
+            next_positional += 1;
+            Some(next_positional - 1)
+        } else if let Ok(index) = name.parse::<usize>() {
+            Some(index)
+        } else {
+            args.iter().position(|a| matches!(&a.name, Some(n) if n == name))
+        };
+
+        let expr = match index {
+            Some(index) if index < args.len() => {
+                used[index] = true;
+                args[index].expr.clone()
+            },
+            Some(index) => {
+                return Err(syn::Error::new(template.span(), format!("placeholder {{{}}} has no argument", index)));
+            },
+            // like `format!`, placeholders without a matching argument capture a variable of the same name
+            None => syn::parse_str::<Ident>(name)
+                .map(|ident| syn::parse_quote_spanned!(template.span()=> #ident))
+                .map_err(|_| syn::Error::new(template.span(), format!("invalid placeholder {{{}}}", name)))?,
+        };
+
+        // keep the value from merging with the code around it, e.g. `x-{y}` with `y = -1` or `{n}.toFixed()`
+        let before = matches!(&pieces[i - 1], TemplatePiece::Code(code) if code.ends_with(['+', '-']));
+        let after = matches!(&pieces[i + 1], TemplatePiece::Code(code) if code.starts_with(|c: char| c == '.' || c.is_alphanumeric()));
+
+        if before {
+            fragments.push(Fragment::Code(" ".to_owned()));
+        }
+        fragments.push(Fragment::Interpolation(Box::new(expr)));
+        if after {
+            fragments.push(Fragment::Code(" ".to_owned()));
+        }
+    }
+
+    if let Some(unused) = used.iter().position(|u| !u) {
+        return Err(syn::Error::new_spanned(&args[unused].expr, "argument never used in template"));
+    }
+
+    expand_fragments(&fragments, "format_js! template", template.span())
+}
+
+fn expand_fragments(fragments: &[Fragment], origin: &str, span: Span) -> syn::Result<TokenStream> {
+    let check_source: String = fragments
+        .iter()
+        .map(|f| match f {
+            Fragment::Code(code) => code.as_str(),
+            Fragment::Interpolation(_) => "[]",
+        })
+        .collect();
+
+    super::check_syntax(&check_source, origin, span)?;
+
+    // mixed site hygiene keeps the buffer from shadowing variables used in the interpolated expressions
+    let buf = Ident::new("code", Span::mixed_site());
+
+    let pushes = fragments.iter().map(|f| match f {
+        Fragment::Code(code) => quote! {
+            #buf.push_str(#code);
+        },
+        Fragment::Interpolation(expr) => quote! {
+            #buf.push_str(::include_js::ToJsLiteral::to_js_literal(&(#expr)).as_str());
+        },
+    });
+
+    Ok(quote! {
+        {
+            let mut #buf = ::include_js::__private::String::new();
+            #(#pushes)*
+
+            // safety: the code was checked in the macro invocation with `[]` in place of the interpolated
+            // values and `ToJsLiteral` always produces a single expression
+            unsafe { ::include_js::JSString::new_unchecked(#buf) }
+        }
+    })
+}
@@ -0,0 +1,55 @@
+//! Registration of the embedded code with `include_js::registry()`, with the `registry` feature,
+//! and in the manifest of the `manifest` feature.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use sha2::Sha256;
+
+/// The kinds of code that are registered, see `include_js::ScriptKind`
+#[derive(Clone, Copy)]
+pub(super) enum Kind {
+    Script,
+    Module,
+    Template,
+}
+
+impl Kind {
+    /// The name of the kind in the manifest
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Kind::Script => "script",
+            Kind::Module => "module",
+            Kind::Template => "template",
+        }
+    }
+}
+
+/// The code that registers `content`, which is embedded from `path`, or nothing without the `registry` feature.
+/// The code is an item, so it has to be put into a block with the code that embeds `content`. Code that is only
+/// embedded in some builds is registered under the same `cfg`, e.g. `debug_assertions`.
+pub(super) fn register(krate: &syn::Path, kind: Kind, path: &str, content: &str, cfg: Option<&str>) -> TokenStream {
+    let sha256 = super::integrity::<Sha256>("sha256", content);
+    let mut register = super::manifest::record(kind, path, content, &sha256, cfg);
+
+    if !cfg!(feature = "registry") {
+        return register;
+    }
+
+    let cfg = cfg.map(|cfg| {
+        let cfg: TokenStream = cfg.parse().expect("the cfg is valid");
+        quote! { #[cfg(#cfg)] }
+    });
+    let variant = match kind {
+        Kind::Script => quote! { Script },
+        Kind::Module => quote! { Module },
+        Kind::Template => quote! { Template },
+    };
+    let size = content.len();
+
+    // the macro of include_js expands to nothing without its `registry` feature
+    register.extend(quote! {
+        #cfg
+        #krate::__private::register! { #variant, #path, #size, #sha256 }
+    });
+    register
+}
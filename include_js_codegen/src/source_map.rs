@@ -0,0 +1,98 @@
+//! Source maps for the included code, created by `include_js_with_map!` or with `source_map = inline`.
+//!
+//! Files that are included as they are get a map from every line to the same line of the file, files that are
+//! compiled by swc, e.g. TypeScript, get the map swc creates. If the code consists of several files or doesn't
+//! start with the file, e.g. because of a banner, the maps are combined into an index map with a section per file.
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use syn::{
+    parse::{Parse, ParseStream},
+    Ident,
+};
+
+/// How the source map is embedded, given with `source_map = ...`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum SourceMapMode {
+    /// as a `//# sourceMappingURL=data:...` comment at the end of the code
+    Inline,
+}
+
+impl Parse for SourceMapMode {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident: Ident = input.parse()?;
+
+        match ident.to_string().as_str() {
+            "inline" => Ok(SourceMapMode::Inline),
+            _ => Err(syn::Error::new(ident.span(), "expected `inline`")),
+        }
+    }
+}
+
+/// The source map of a single file as JSON
+pub(super) struct FileMap(String);
+
+impl FileMap {
+    /// The map from every line of `content` to the same line of the file `source`, whose content it is
+    pub(super) fn identity(source: &str, content: &str) -> Self {
+        // every line starts with a segment that maps its first column to the first column of the next line
+        // of the source, the fields are relative to the previous segment
+        let mappings = match content.lines().count() {
+            0 => String::new(),
+            lines => format!("AAAA{}", ";AACA".repeat(lines - 1)),
+        };
+
+        FileMap(format!(
+            r#"{{"version":3,"sources":[{}],"sourcesContent":[{}],"names":[],"mappings":"{}"}}"#,
+            json_string(source),
+            json_string(content),
+            mappings
+        ))
+    }
+
+    /// A map that was created by swc
+    #[cfg(any(feature = "jsx", feature = "transpile"))]
+    pub(super) fn from_json(json: String) -> Self {
+        FileMap(json)
+    }
+}
+
+/// The source map of code that consists of the files of `maps`, each starting at the given line
+pub(super) fn combine(maps: Vec<(usize, FileMap)>) -> String {
+    match maps.as_slice() {
+        [(0, FileMap(map))] => map.clone(),
+        _ => {
+            let sections: Vec<_> = maps
+                .into_iter()
+                .map(|(line, FileMap(map))| format!(r#"{{"offset":{{"line":{},"column":0}},"map":{}}}"#, line, map))
+                .collect();
+
+            format!(r#"{{"version":3,"sections":[{}]}}"#, sections.join(","))
+        },
+    }
+}
+
+/// The comment that embeds `map` at the end of the code
+pub(super) fn inline_comment(map: &str) -> String {
+    format!("//# sourceMappingURL=data:application/json;charset=utf-8;base64,{}\n", BASE64_STANDARD.encode(map))
+}
+
+/// `value` as a JSON string literal
+pub(super) fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+    json
+}
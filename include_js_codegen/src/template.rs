@@ -1,48 +1,1661 @@
-use syn::{AttrStyle, Attribute, DataStruct, DeriveInput, Fields, Ident, LitStr, Token, parse::Parse};
+use std::collections::{BTreeMap, BTreeSet};
+
+use handlebars::{template::{DecoratorTemplate, HelperTemplate, Parameter, Template, TemplateElement}, HelperDef, JsonRender};
+use include_js_core::JSString;
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
+use syn::{token, AttrStyle, Attribute, Data, DeriveInput, Field, Fields, GenericArgument, Generics, Ident, Index, LitStr, Member, PathArguments, Token, Type, Variant, WherePredicate, ext::IdentExt, parse::Parse, punctuated::Punctuated, spanned::Spanned};
+
+pub(super) mod checked;
+pub(super) mod generate;
+#[cfg(feature = "minijinja")]
+pub(super) mod jinja;
+mod precompile;
+mod serde_attrs;
+
+use super::args::PathArg;
+
+use precompile::Precompiler;
+use serde_attrs::RenameRule;
 
 mod kw {
     syn::custom_keyword!(template);
+    syn::custom_keyword!(check);
+    syn::custom_keyword!(escape);
+    syn::custom_keyword!(engine);
+    syn::custom_keyword!(helper);
+    syn::custom_keyword!(name);
+    syn::custom_keyword!(partial);
+    syn::custom_keyword!(layout);
+    syn::custom_keyword!(path);
+    syn::custom_keyword!(placeholder);
+    syn::custom_keyword!(rename);
+    syn::custom_keyword!(skip);
+    syn::custom_keyword!(flatten);
+    syn::custom_keyword!(json);
+    syn::custom_keyword!(deny);
+    syn::custom_keyword!(forbid_globals);
+    syn::custom_keyword!(require_fn);
+    syn::custom_keyword!(builder);
+    syn::custom_keyword!(default);
+    syn::custom_keyword!(inline);
+}
+
+/// How the derive makes sure that a rendered template is valid Javascript,
+/// set with `#[include_js(check = "...")]`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum CheckMode {
+    /// Render the template at compile time with `[]` for every variable and parse the result
+    Placeholder,
+    /// Parse the rendered template at runtime in `render_template`
+    Runtime,
+    /// Do not check the template at all
+    None,
+}
+
+impl Parse for CheckMode {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mode: LitStr = input.parse()?;
+
+        match mode.value().as_str() {
+            "placeholder" => Ok(CheckMode::Placeholder),
+            "runtime" => Ok(CheckMode::Runtime),
+            "none" => Ok(CheckMode::None),
+            _ => Err(syn::Error::new(mode.span(), "expected one of \"placeholder\", \"runtime\" or \"none\"")),
+        }
+    }
+}
+
+/// How values are escaped when the template is rendered at runtime, set with `#[include_js(escape = "...")]`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum EscapeMode {
+    /// Values are written as they are, which is what Javascript needs
+    None,
+    /// Values are escaped as HTML, the handlebars default, for templates that end up embedded in HTML
+    Html,
+}
+
+impl Parse for EscapeMode {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mode: LitStr = input.parse()?;
+
+        match mode.value().as_str() {
+            "none" => Ok(EscapeMode::None),
+            "html" => Ok(EscapeMode::Html),
+            _ => Err(syn::Error::new(mode.span(), "expected one of \"none\" or \"html\"")),
+        }
+    }
+}
+
+/// The engine that renders the templates, set with `#[include_js(engine = "...")]`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Engine {
+    Handlebars,
+    /// Jinja syntax, needs the `minijinja` feature
+    #[cfg(feature = "minijinja")]
+    Minijinja,
+}
+
+impl Parse for Engine {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let engine: LitStr = input.parse()?;
+
+        match engine.value().as_str() {
+            "handlebars" => Ok(Engine::Handlebars),
+            #[cfg(feature = "minijinja")]
+            "minijinja" => Ok(Engine::Minijinja),
+            #[cfg(not(feature = "minijinja"))]
+            "minijinja" => Err(syn::Error::new(engine.span(), "the minijinja engine needs the `minijinja` feature of include_js")),
+            _ => Err(syn::Error::new(engine.span(), "expected one of \"handlebars\" or \"minijinja\"")),
+        }
+    }
+}
+
+/// A custom handlebars helper, registered with `#[include_js(helper(name = "...", fn = "..."))]`
+pub(super) struct HelperSpec {
+    pub(super) name: LitStr,
+    /// the path of the helper, anything that implements `HelperDef` like a function with the signature of a helper
+    function: syn::Path,
+    /// Javascript the helper outputs when checking the template at compiletime instead of `[]`
+    placeholder: Option<LitStr>,
+}
+
+impl Parse for HelperSpec {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let content;
+        syn::parenthesized!(content in input);
+
+        let mut name = None;
+        let mut function = None;
+        let mut placeholder = None;
+
+        while !content.is_empty() {
+            let lookahead = content.lookahead1();
+
+            if lookahead.peek(kw::name) {
+                let kw: kw::name = content.parse()?;
+                content.parse::<Token![=]>()?;
+                if name.replace(content.parse::<LitStr>()?).is_some() {
+                    return Err(syn::Error::new(kw.span, "duplicate helper name specification"));
+                }
+            } else if lookahead.peek(Token![fn]) {
+                let kw: Token![fn] = content.parse()?;
+                content.parse::<Token![=]>()?;
+                let path: syn::Path = content.parse::<LitStr>()?.parse()?;
+                if function.replace(path).is_some() {
+                    return Err(syn::Error::new(kw.span, "duplicate helper function specification"));
+                }
+            } else if lookahead.peek(kw::placeholder) {
+                let kw: kw::placeholder = content.parse()?;
+                content.parse::<Token![=]>()?;
+                if placeholder.replace(content.parse::<LitStr>()?).is_some() {
+                    return Err(syn::Error::new(kw.span, "duplicate placeholder specification"));
+                }
+            } else {
+                return Err(lookahead.error());
+            }
+
+            if !content.is_empty() {
+                content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(HelperSpec {
+            name: name.ok_or_else(|| content.error("missing helper name, add `name = \"...\"`"))?,
+            function: function.ok_or_else(|| content.error("missing helper function, add `fn = \"...\"`"))?,
+            placeholder,
+        })
+    }
+}
+
+impl HelperSpec {
+    /// Generates the registration of the helper with the engine `h`
+    pub(super) fn register(&self) -> TokenStream {
+        let HelperSpec { name, function, .. } = self;
+        quote! { h.register_helper(#name, Box::new(#function)); }
+    }
+
+    /// The Javascript that stands in for the output of the helper when checking the template at compiletime
+    pub(super) fn placeholder(&self) -> String {
+        self.placeholder
+            .as_ref()
+            .map(LitStr::value)
+            .unwrap_or_else(|| "[]".to_owned())
+    }
+}
+
+/// Compiletime stand-in for a custom helper, outputs its placeholder.
+pub(super) struct PlaceholderHelper(pub(super) String);
+
+impl HelperDef for PlaceholderHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        _: &handlebars::Helper<'reg, 'rc>,
+        _: &'reg handlebars::Handlebars<'reg>,
+        _: &'rc handlebars::Context,
+        _: &mut handlebars::RenderContext<'reg, 'rc>,
+        out: &mut dyn handlebars::Output,
+    ) -> handlebars::HelperResult {
+        out.write(&self.0)?;
+        Ok(())
+    }
 }
 
-pub(super) struct TemplatePathInput {
-    pub(super) attr_name: kw::template,
-    pub(super) eq: Token![=],
+/// A partial, registered with `#[include_js(partial(name = "...", path = "..."))]`
+pub(super) struct PartialSpec {
+    pub(super) name: LitStr,
+    /// the path of the partial, resolved like the path of the template
     pub(super) path: LitStr,
 }
 
-impl Parse for TemplatePathInput {
+impl Parse for PartialSpec {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let (name, path) = parse_name_and_path(input, "partial")?;
+        Ok(PartialSpec { name, path })
+    }
+}
+
+/// Parses `(name = "...", path = "...")` for a partial or named template, `what` is used in the errors.
+fn parse_name_and_path(input: syn::parse::ParseStream, what: &str) -> syn::Result<(LitStr, LitStr)> {
+    let content;
+    syn::parenthesized!(content in input);
+
+    let mut name = None;
+    let mut path = None;
+
+    while !content.is_empty() {
+        let lookahead = content.lookahead1();
+
+        if lookahead.peek(kw::name) {
+            let kw: kw::name = content.parse()?;
+            content.parse::<Token![=]>()?;
+            if name.replace(content.parse::<LitStr>()?).is_some() {
+                return Err(syn::Error::new(kw.span, format!("duplicate {} name specification", what)));
+            }
+        } else if lookahead.peek(kw::path) {
+            let kw: kw::path = content.parse()?;
+            content.parse::<Token![=]>()?;
+            if path.replace(content.parse::<LitStr>()?).is_some() {
+                return Err(syn::Error::new(kw.span, format!("duplicate {} path specification", what)));
+            }
+        } else {
+            return Err(lookahead.error());
+        }
+
+        if !content.is_empty() {
+            content.parse::<Token![,]>()?;
+        }
+    }
+
+    Ok((
+        name.ok_or_else(|| content.error(format!("missing {} name, add `name = \"...\"`", what)))?,
+        path.ok_or_else(|| content.error(format!("missing {} path, add `path = \"...\"`", what)))?,
+    ))
+}
+
+/// A template besides the main one, given with `#[include_js(template(name = "...", path = "..."))]`,
+/// which is rendered with `render_<name>`
+pub(super) struct NamedTemplate {
+    pub(super) name: Ident,
+    pub(super) source: TemplateSource,
+}
+
+impl Parse for NamedTemplate {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let (name, path) = parse_name_and_path(input, "template")?;
+        let name = name
+            .parse()
+            .map_err(|_| syn::Error::new(name.span(), "the name of a template has to be an identifier"))?;
+
+        Ok(NamedTemplate { name, source: TemplateSource::Path(path) })
+    }
+}
+
+impl PartialSpec {
+    /// Reads the partial, returning it together with the code that tracks its file
+    pub(super) fn load(&self) -> syn::Result<(Partial, TokenStream)> {
+        let (file, track) = TemplateFile::load(&self.path)?;
+        Ok((Partial { name: self.name.value(), file }, track))
+    }
+}
+
+/// A template, partial or layout that was read at compiletime
+pub(super) struct TemplateFile {
+    pub(super) path: PathArg,
+    pub(super) content: String,
+}
+
+impl TemplateFile {
+    /// Reads the file at `path`, returning it together with the code that tracks it
+    pub(super) fn load(path: &LitStr) -> syn::Result<(Self, TokenStream)> {
+        let path = PathArg::from_lit(path)?;
+        path.reject_absolute(false)?;
+        let content = super::read_to_string_relative(std::path::Path::new(&path.value), path.span)?;
+        let track = super::track_relative(std::path::Path::new(&path.value), path.span)?;
+
+        Ok((TemplateFile { path, content }, track))
+    }
+}
+
+/// Where the template of a struct or variant comes from
+pub(super) enum TemplateSource {
+    /// `template = "..."`, the path of the template
+    Path(LitStr),
+    /// `inline = "..."`, the template itself
+    Inline(LitStr),
+}
+
+impl TemplateSource {
+    /// Reads the template, returning it together with the code that tracks its file.
+    /// The path of an inline template is `<inline>`.
+    pub(super) fn load(&self) -> syn::Result<(TemplateFile, TokenStream)> {
+        match self {
+            TemplateSource::Path(path) => TemplateFile::load(path),
+            TemplateSource::Inline(template) => {
+                let path = PathArg { value: "<inline>".to_owned(), span: template.span(), absolute: false };
+                Ok((TemplateFile { path, content: template.value() }, TokenStream::new()))
+            },
+        }
+    }
+
+    /// The name of the template in messages and the registry, i.e. its path or `<inline>`
+    pub(super) fn name(&self) -> String {
+        match self {
+            TemplateSource::Path(path) => path.value(),
+            TemplateSource::Inline(_) => "<inline>".to_owned(),
+        }
+    }
+
+    pub(super) fn span(&self) -> Span {
+        match self {
+            TemplateSource::Path(lit) | TemplateSource::Inline(lit) => lit.span(),
+        }
+    }
+}
+
+/// A partial that was read at compiletime
+pub(super) struct Partial {
+    pub(super) name: String,
+    pub(super) file: TemplateFile,
+}
+
+/// Composes `layout` and `body` into a single template by substituting `body` for the `{{{body}}}` in `layout`.
+pub(super) fn apply_layout(layout: &TemplateFile, body: &str) -> syn::Result<String> {
+    let content = &layout.content;
+
+    // `{{{body}}}` may contain whitespace, like every other expression
+    let markers: Vec<_> = content
+        .match_indices("{{{")
+        .filter_map(|(start, _)| {
+            let inner = &content[start + 3..];
+            let rest = inner.trim_start().strip_prefix("body")?.trim_start();
+            rest.starts_with("}}}").then(|| (start, content.len() - rest.len() + 3))
+        })
+        .collect();
+
+    match markers.as_slice() {
+        [(start, end)] => Ok(format!("{}{}{}", &content[..*start], body, &content[*end..])),
+        _ => Err(syn::Error::new(layout.path.span, "a layout has to contain `{{{body}}}` exactly once")),
+    }
+}
+
+/// A single `key = value` argument of `#[include_js(...)]` on the struct
+enum TemplateArg {
+    Template(TemplateSource),
+    Named(NamedTemplate),
+    Check(kw::check, CheckMode),
+    Escape(kw::escape, EscapeMode),
+    Engine(kw::engine, Engine),
+    Helper(HelperSpec),
+    Partial(PartialSpec),
+    Layout(kw::layout, LitStr),
+    Deny(kw::deny, Vec<crate::lint::Lint>),
+    ForbidGlobals(kw::forbid_globals, Vec<String>),
+    RequireFn(kw::require_fn, Vec<String>),
+    Crate(Token![crate], LitStr),
+    /// `fn` or `fn = "name"`
+    Function(Token![fn], Option<LitStr>),
+    Builder(kw::builder),
+}
+
+impl Parse for TemplateArg {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let attr_name: kw::template = input.parse()?;
-        let eq: Token![=] = input.parse()?;
-        let path: LitStr = input.parse()?;
-        Ok(TemplatePathInput { attr_name, eq, path })
+        let lookahead = input.lookahead1();
+
+        if lookahead.peek(kw::template) {
+            input.parse::<kw::template>()?;
+            if input.peek(token::Paren) {
+                return Ok(TemplateArg::Named(input.parse()?));
+            }
+            input.parse::<Token![=]>()?;
+            Ok(TemplateArg::Template(TemplateSource::Path(input.parse()?)))
+        } else if lookahead.peek(kw::inline) {
+            input.parse::<kw::inline>()?;
+            input.parse::<Token![=]>()?;
+            Ok(TemplateArg::Template(TemplateSource::Inline(input.parse()?)))
+        } else if lookahead.peek(kw::check) {
+            let kw: kw::check = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(TemplateArg::Check(kw, input.parse()?))
+        } else if lookahead.peek(kw::escape) {
+            let kw: kw::escape = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(TemplateArg::Escape(kw, input.parse()?))
+        } else if lookahead.peek(kw::engine) {
+            let kw: kw::engine = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(TemplateArg::Engine(kw, input.parse()?))
+        } else if lookahead.peek(kw::helper) {
+            input.parse::<kw::helper>()?;
+            Ok(TemplateArg::Helper(input.parse()?))
+        } else if lookahead.peek(kw::partial) {
+            input.parse::<kw::partial>()?;
+            Ok(TemplateArg::Partial(input.parse()?))
+        } else if lookahead.peek(kw::layout) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(TemplateArg::Layout(kw, input.parse()?))
+        } else if lookahead.peek(kw::deny) {
+            let kw: kw::deny = input.parse()?;
+            Ok(TemplateArg::Deny(kw, crate::lint::Lints::parse_deny(input, kw.span)?))
+        } else if lookahead.peek(kw::forbid_globals) {
+            let kw: kw::forbid_globals = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(TemplateArg::ForbidGlobals(kw, crate::lint::Lints::parse_names(input, kw.span)?))
+        } else if lookahead.peek(kw::require_fn) {
+            let kw: kw::require_fn = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(TemplateArg::RequireFn(kw, crate::lint::Lints::parse_names(input, kw.span)?))
+        } else if lookahead.peek(Token![crate]) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(TemplateArg::Crate(kw, input.parse()?))
+        } else if lookahead.peek(Token![fn]) {
+            let kw = input.parse()?;
+            let name = match input.parse::<Option<Token![=]>>()? {
+                Some(_) => Some(input.parse()?),
+                None => None,
+            };
+            Ok(TemplateArg::Function(kw, name))
+        } else if lookahead.peek(kw::builder) {
+            Ok(TemplateArg::Builder(input.parse()?))
+        } else {
+            Err(lookahead.error())
+        }
     }
 }
 
-pub(super) fn struct_field_names(st: &DataStruct) -> Vec<String> {
-    let fields = match &st.fields {
-        Fields::Named(fields) => fields,
-        _ => panic!("only normal struct supported"),
+/// All arguments given via `#[include_js(...)]` attributes on the struct
+pub(super) struct TemplateArgs {
+    /// only set for structs, the templates of enums are given on their variants
+    pub(super) template: Option<TemplateSource>,
+    /// the templates of a struct besides the main one
+    pub(super) named: Vec<NamedTemplate>,
+    pub(super) check: CheckMode,
+    pub(super) escape: EscapeMode,
+    pub(super) engine: Engine,
+    pub(super) helpers: Vec<HelperSpec>,
+    pub(super) partials: Vec<PartialSpec>,
+    /// the template every template of the type is embedded into at `{{{body}}}`
+    pub(super) layout: Option<LitStr>,
+    /// the constructs the rendered templates must not contain, checked on the placeholder rendering
+    pub(super) lints: crate::lint::Lints,
+    /// the path of the include_js crate in the generated code, like `#[serde(crate = "...")]`
+    pub(super) krate: syn::Path,
+    /// the name of the function generated with `#[include_js(fn)]`, only for structs
+    pub(super) function: Option<Ident>,
+    /// whether a builder is generated with `#[include_js(builder)]`, only for structs
+    pub(super) builder: bool,
+}
+
+fn is_include_js_attr(attr: &Attribute) -> bool {
+    matches!(attr.style, AttrStyle::Outer)
+        && attr
+            .path
+            .get_ident()
+            .map(|id| id == &Ident::new("include_js", id.span()))
+            .unwrap_or(false)
+}
+
+pub(super) fn get_args(input: &DeriveInput) -> syn::Result<TemplateArgs> {
+    let mut template = None;
+    let mut named: Vec<NamedTemplate> = Vec::new();
+    let mut check = None;
+    let mut escape = None;
+    let mut engine = None;
+    let mut helpers: Vec<HelperSpec> = Vec::new();
+    let mut partials: Vec<PartialSpec> = Vec::new();
+    let mut layout = None;
+    let mut deny = None;
+    let mut forbid_globals = None;
+    let mut require_fn = None;
+    let mut krate = None;
+    let mut function = None;
+    let mut builder = None;
+
+    for attr in input.attrs.iter().filter(|a| is_include_js_attr(a)) {
+        let args = attr.parse_args_with(Punctuated::<TemplateArg, Token![,]>::parse_terminated)?;
+
+        for arg in args {
+            match arg {
+                TemplateArg::Template(source) if template.is_some() => {
+                    return Err(syn::Error::new(source.span(), "duplicate template specification"));
+                },
+                TemplateArg::Template(source) => template = Some(source),
+                TemplateArg::Named(template) if named.iter().any(|t| t.name == template.name) => {
+                    return Err(syn::Error::new(template.name.span(), "duplicate template name"));
+                },
+                TemplateArg::Named(template) if !matches!(input.data, Data::Struct(_)) => {
+                    return Err(syn::Error::new(template.name.span(), "named templates are only supported on structs"));
+                },
+                TemplateArg::Named(template) => named.push(template),
+                TemplateArg::Check(kw, _) if check.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate check specification"));
+                },
+                TemplateArg::Check(_, mode) => check = Some(mode),
+                TemplateArg::Escape(kw, _) if escape.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate escape specification"));
+                },
+                TemplateArg::Escape(_, mode) => escape = Some(mode),
+                TemplateArg::Engine(kw, _) if engine.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate engine specification"));
+                },
+                TemplateArg::Engine(_, e) => engine = Some(e),
+                TemplateArg::Helper(helper) if helpers.iter().any(|h| h.name.value() == helper.name.value()) => {
+                    return Err(syn::Error::new(helper.name.span(), "duplicate helper name"));
+                },
+                TemplateArg::Helper(helper) => helpers.push(helper),
+                TemplateArg::Partial(partial) if partials.iter().any(|p| p.name.value() == partial.name.value()) => {
+                    return Err(syn::Error::new(partial.name.span(), "duplicate partial name"));
+                },
+                TemplateArg::Partial(partial) => partials.push(partial),
+                TemplateArg::Layout(kw, _) if layout.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate layout specification"));
+                },
+                TemplateArg::Layout(_, path) => layout = Some(path),
+                TemplateArg::Deny(kw, _) if deny.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate deny specification"));
+                },
+                TemplateArg::Deny(kw, lints) => deny = Some((kw, lints)),
+                TemplateArg::ForbidGlobals(kw, _) if forbid_globals.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate forbid_globals specification"));
+                },
+                TemplateArg::ForbidGlobals(kw, globals) => forbid_globals = Some((kw, globals)),
+                TemplateArg::RequireFn(kw, _) if require_fn.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate require_fn specification"));
+                },
+                TemplateArg::RequireFn(kw, names) => require_fn = Some((kw, names)),
+                TemplateArg::Crate(kw, _) if krate.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate crate specification"));
+                },
+                TemplateArg::Crate(_, path) => krate = Some(path.parse()?),
+                TemplateArg::Function(kw, _) if function.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate fn specification"));
+                },
+                TemplateArg::Function(kw, _) if !matches!(input.data, Data::Struct(_)) => {
+                    return Err(syn::Error::new(kw.span, "`fn` is only supported on structs"));
+                },
+                TemplateArg::Function(_, Some(name)) => function = Some(name.parse()?),
+                TemplateArg::Function(kw, None) => {
+                    function = Some(Ident::new(&snake_case(&input.ident.unraw().to_string()), kw.span));
+                },
+                TemplateArg::Builder(kw) if builder.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate builder specification"));
+                },
+                TemplateArg::Builder(kw) => match &input.data {
+                    Data::Struct(ds) if matches!(ds.fields, Fields::Named(_)) => builder = Some(kw),
+                    _ => return Err(syn::Error::new(kw.span, "`builder` is only supported on structs with named fields")),
+                },
+            }
+        }
+    }
+
+    let engine = engine.unwrap_or(Engine::Handlebars);
+
+    // helpers, partials and layouts are written in handlebars syntax
+    #[cfg(feature = "minijinja")]
+    if engine == Engine::Minijinja {
+        let span = helpers
+            .first()
+            .map(|h| h.name.span())
+            .or_else(|| partials.first().map(|p| p.name.span()))
+            .or_else(|| layout.as_ref().map(LitStr::span));
+
+        if let Some(span) = span {
+            return Err(syn::Error::new(
+                span,
+                "helpers, partials and layouts are only supported by the handlebars engine",
+            ));
+        }
+    }
+
+    let check = check.unwrap_or(CheckMode::Placeholder);
+
+    // the lints can only look at the code of the template after rendering it with placeholders
+    if check != CheckMode::Placeholder {
+        let lint = deny
+            .as_ref()
+            .map(|(kw, _)| (kw.span, "deny"))
+            .or_else(|| forbid_globals.as_ref().map(|(kw, _)| (kw.span, "forbid_globals")))
+            .or_else(|| require_fn.as_ref().map(|(kw, _)| (kw.span, "require_fn")));
+
+        if let Some((span, lint)) = lint {
+            return Err(syn::Error::new(span, format!("`{}` only has an effect with `check = \"placeholder\"`", lint)));
+        }
+    }
+
+    let lints = crate::lint::Lints {
+        deny: deny.map(|(_, lints)| lints).unwrap_or_default(),
+        forbid_globals: forbid_globals.map(|(_, globals)| globals).unwrap_or_default(),
+        require_fn: require_fn.map(|(_, names)| names).unwrap_or_default(),
     };
 
-    fields
-        .named
+    Ok(TemplateArgs {
+        template,
+        named,
+        check,
+        escape: escape.unwrap_or(EscapeMode::None),
+        engine,
+        helpers,
+        partials,
+        layout,
+        lints,
+        krate: krate.unwrap_or_else(|| syn::parse_quote!(::include_js)),
+        function,
+        builder: builder.is_some(),
+    })
+}
+
+/// `name` in snake_case, e.g. `move_window` for `MoveWindow`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+
+    for (i, c) in name.char_indices() {
+        if c.is_uppercase() {
+            let previous = name[..i].chars().next_back();
+            let after_lower = previous.is_some_and(|p| p.is_lowercase() || p.is_numeric());
+            let in_acronym = previous.is_some_and(char::is_uppercase);
+            let before_lower = name[i..].chars().nth(1).is_some_and(char::is_lowercase);
+
+            if i > 0 && !snake.ends_with('_') && (after_lower || (in_acronym && before_lower)) {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+
+    snake
+}
+
+/// Returns the template given via `#[include_js(template = "...")]` or `#[include_js(inline = "...")]`
+/// on an enum variant.
+pub(super) fn variant_template(variant: &Variant) -> syn::Result<TemplateSource> {
+    let mut template = None;
+
+    for attr in variant.attrs.iter().filter(|a| is_include_js_attr(a)) {
+        let args = attr.parse_args_with(Punctuated::<TemplateArg, Token![,]>::parse_terminated)?;
+
+        for arg in args {
+            match arg {
+                TemplateArg::Template(source) if template.is_some() => {
+                    return Err(syn::Error::new(source.span(), "duplicate template specification"));
+                },
+                TemplateArg::Template(source) => template = Some(source),
+                TemplateArg::Named(template) => {
+                    return Err(syn::Error::new(template.name.span(), "named templates are only supported on structs"));
+                },
+                TemplateArg::Check(kw, _) => {
+                    return Err(syn::Error::new(kw.span, "the check can only be specified on the enum"));
+                },
+                TemplateArg::Escape(kw, _) => {
+                    return Err(syn::Error::new(kw.span, "the escaping can only be specified on the enum"));
+                },
+                TemplateArg::Engine(kw, _) => {
+                    return Err(syn::Error::new(kw.span, "the engine can only be specified on the enum"));
+                },
+                TemplateArg::Helper(helper) => {
+                    return Err(syn::Error::new(helper.name.span(), "helpers can only be registered on the enum"));
+                },
+                TemplateArg::Partial(partial) => {
+                    return Err(syn::Error::new(partial.name.span(), "partials can only be registered on the enum"));
+                },
+                TemplateArg::Layout(kw, _) => {
+                    return Err(syn::Error::new(kw.span, "the layout can only be specified on the enum"));
+                },
+                TemplateArg::Deny(kw, _) => {
+                    return Err(syn::Error::new(kw.span, "lints can only be specified on the enum"));
+                },
+                TemplateArg::ForbidGlobals(kw, _) => {
+                    return Err(syn::Error::new(kw.span, "lints can only be specified on the enum"));
+                },
+                TemplateArg::RequireFn(kw, _) => {
+                    return Err(syn::Error::new(kw.span, "lints can only be specified on the enum"));
+                },
+                TemplateArg::Crate(kw, _) => {
+                    return Err(syn::Error::new(kw.span, "the crate can only be specified on the enum"));
+                },
+                TemplateArg::Function(kw, _) => {
+                    return Err(syn::Error::new(kw.span, "`fn` is only supported on structs"));
+                },
+                TemplateArg::Builder(kw) => {
+                    return Err(syn::Error::new(kw.span, "`builder` is only supported on structs with named fields"));
+                },
+            }
+        }
+    }
+
+    template.ok_or_else(|| {
+        syn::Error::new(
+            variant.ident.span(),
+            "missing template specification, add `#[include_js(template = \"...\")]` or `#[include_js(inline = \"...\")]` to the variant",
+        )
+    })
+}
+
+/// A single argument of `#[include_js(...)]` on a field
+enum FieldArg {
+    Placeholder(LitStr),
+    Rename(LitStr),
+    Skip(kw::skip),
+    Flatten(kw::flatten),
+    Json(kw::json),
+    Default(kw::default, Option<LitStr>),
+}
+
+impl Parse for FieldArg {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let lookahead = input.lookahead1();
+
+        if lookahead.peek(kw::placeholder) {
+            input.parse::<kw::placeholder>()?;
+            input.parse::<Token![=]>()?;
+            Ok(FieldArg::Placeholder(input.parse()?))
+        } else if lookahead.peek(kw::rename) {
+            input.parse::<kw::rename>()?;
+            input.parse::<Token![=]>()?;
+            Ok(FieldArg::Rename(input.parse()?))
+        } else if lookahead.peek(kw::skip) {
+            Ok(FieldArg::Skip(input.parse()?))
+        } else if lookahead.peek(kw::flatten) {
+            Ok(FieldArg::Flatten(input.parse()?))
+        } else if lookahead.peek(kw::json) {
+            Ok(FieldArg::Json(input.parse()?))
+        } else if lookahead.peek(kw::default) {
+            let kw = input.parse()?;
+            let expr = match input.parse::<Option<Token![=]>>()? {
+                Some(_) => Some(input.parse()?),
+                None => None,
+            };
+            Ok(FieldArg::Default(kw, expr))
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+/// A struct field together with the arguments given via `#[include_js(...)]` attributes on it
+pub(super) struct TemplateField {
+    /// the field name, or its index for tuple structs
+    pub(super) member: Member,
+    ty: Type,
+    /// the name of the template variable if it differs from the field name
+    rename: Option<String>,
+    /// whether the fields of the value are merged into the template data instead of the value itself
+    pub(super) flatten: bool,
+    /// whether `{{field}}` outputs the value as JSON instead of rendering it
+    json: bool,
+    /// Javascript substituted for the field in the compiletime check instead of `[]`,
+    /// for flattened fields it is substituted for every variable the field could provide
+    placeholder: Option<LitStr>,
+}
+
+impl TemplateField {
+    /// Returns `None` if the field is skipped with `#[include_js(skip)]` or `#[serde(skip)]`.
+    fn from_field(index: usize, field: &Field, rename_all: Option<RenameRule>) -> syn::Result<Option<Self>> {
+        let mut placeholder = None;
+        let mut rename = None;
+        let mut skip = None;
+        let mut flatten = None;
+        let mut json = None;
+
+        for attr in field.attrs.iter().filter(|a| is_include_js_attr(a)) {
+            let args = attr.parse_args_with(Punctuated::<FieldArg, Token![,]>::parse_terminated)?;
+
+            for arg in args {
+                match arg {
+                    FieldArg::Placeholder(lit) if placeholder.is_some() => {
+                        return Err(syn::Error::new(lit.span(), "duplicate placeholder specification"));
+                    },
+                    FieldArg::Placeholder(lit) => placeholder = Some(lit),
+                    FieldArg::Rename(lit) if rename.is_some() => {
+                        return Err(syn::Error::new(lit.span(), "duplicate rename specification"));
+                    },
+                    FieldArg::Rename(lit) => rename = Some(lit),
+                    FieldArg::Skip(kw) if skip.is_some() => {
+                        return Err(syn::Error::new(kw.span, "duplicate skip specification"));
+                    },
+                    FieldArg::Skip(kw) => skip = Some(kw),
+                    FieldArg::Flatten(kw) if flatten.is_some() => {
+                        return Err(syn::Error::new(kw.span, "duplicate flatten specification"));
+                    },
+                    FieldArg::Flatten(kw) => flatten = Some(kw),
+                    FieldArg::Json(kw) if json.is_some() => {
+                        return Err(syn::Error::new(kw.span, "duplicate json specification"));
+                    },
+                    FieldArg::Json(kw) => json = Some(kw),
+                    // the default is not part of the template data, see `field_default`
+                    FieldArg::Default(..) => (),
+                }
+            }
+        }
+
+        serde_attrs::reject_unsupported(&field.attrs)?;
+
+        // positions of tuple fields can't be renamed and must not shift
+        if field.ident.is_none() {
+            let span = rename
+                .as_ref()
+                .map(LitStr::span)
+                .or_else(|| skip.map(|kw| kw.span))
+                .or_else(|| flatten.map(|kw| kw.span))
+                .or_else(|| json.map(|kw| kw.span));
+
+            if let Some(span) = span {
+                return Err(syn::Error::new(
+                    span,
+                    "`rename`, `skip`, `flatten` and `json` are only supported on named fields",
+                ));
+            }
+        }
+
+        if let (Some(rename), Some(_)) = (&rename, flatten) {
+            return Err(syn::Error::new(rename.span(), "a flattened field can't be renamed"));
+        }
+
+        if let (Some(json), Some(_)) = (json, flatten) {
+            return Err(syn::Error::new(json.span, "a flattened field can't be embedded as JSON"));
+        }
+
+        let (member, rename, skip, flatten) = match &field.ident {
+            // serde attributes are honored unless overridden, so the data matches the serialized field
+            Some(ident) => {
+                let serde = serde_attrs::field(&field.attrs);
+                let rename = rename
+                    .or(serde.rename)
+                    .map(|lit| lit.value())
+                    .or_else(|| rename_all.map(|rule| rule.apply(&ident.unraw().to_string())));
+                let flatten = flatten.is_some() || (serde.flatten && rename.is_none());
+
+                (Member::Named(ident.clone()), rename, skip.is_some() || serde.skip, flatten)
+            },
+            None => (Member::Unnamed(Index { index: index as u32, span: field.span() }), None, false, false),
+        };
+
+        if skip {
+            return Ok(None);
+        }
+
+        Ok(Some(TemplateField {
+            member,
+            ty: field.ty.clone(),
+            rename,
+            flatten,
+            json: json.is_some() && !flatten,
+            placeholder,
+        }))
+    }
+
+    /// The name of the template variable for this field, i.e. `0`, `1`, ... for tuple structs
+    pub(super) fn name(&self) -> String {
+        match (&self.rename, &self.member) {
+            (Some(rename), _) => rename.clone(),
+            (None, Member::Named(ident)) => ident.unraw().to_string(),
+            (None, Member::Unnamed(index)) => index.index.to_string(),
+        }
+    }
+
+    pub(super) fn span(&self) -> Span {
+        self.member.span()
+    }
+
+    /// The Javascript that stands in for this field when checking the template at compiletime
+    pub(super) fn placeholder(&self) -> String {
+        self.placeholder
+            .as_ref()
+            .map(LitStr::value)
+            .unwrap_or_else(|| "[]".to_owned())
+    }
+
+    /// How many levels of sequences of primitive values the field consists of, e.g. `1` for `Vec<String>`,
+    /// or `None` if its values are not known to render as a single value like `Vec<SomeStruct>`
+    pub(super) fn sequence_depth(&self) -> Option<usize> {
+        sequence_depth(&self.ty)
+    }
+
+    /// Whether the field is a `bool`, which is checked with both values
+    fn is_bool(&self) -> bool {
+        let mut ty = &self.ty;
+        while let Type::Reference(r) = ty {
+            ty = &r.elem;
+        }
+
+        matches!(ty, Type::Path(p) if p.qself.is_none() && p.path.is_ident("bool"))
+    }
+
+    /// The identifier the field is bound to when matching an enum variant
+    fn binding(&self) -> Ident {
+        match &self.member {
+            Member::Named(ident) => ident.clone(),
+            Member::Unnamed(index) => Ident::new(&format!("__field{}", index.index), Span::call_site()),
+        }
+    }
+}
+
+/// The fields of a struct or variant that end up in the template data, tuple fields are rendered
+/// with positional variables (`{{0}}`, `{{1}}`, ...) and unit structs have none.
+/// `attrs` are the attributes of the struct or variant.
+pub(super) fn fields(fields: &Fields, attrs: &[Attribute]) -> syn::Result<Vec<TemplateField>> {
+    let rename_all = serde_attrs::rename_all(attrs);
+
+    let fields = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| TemplateField::from_field(index, field, rename_all))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(fields.into_iter().flatten().collect())
+}
+
+/// The default of a field, used by the builder and the `Default` implementation even if the field is skipped
+/// in the template data.
+enum FieldDefault {
+    /// `#[include_js(default)]`, i.e. the `Default` implementation of the field type
+    Trait,
+    /// `#[include_js(default = "...")]`
+    Expr(Box<syn::Expr>),
+}
+
+impl FieldDefault {
+    fn get(field: &Field) -> syn::Result<Option<Self>> {
+        let mut default = None;
+
+        for attr in field.attrs.iter().filter(|a| is_include_js_attr(a)) {
+            let args = attr.parse_args_with(Punctuated::<FieldArg, Token![,]>::parse_terminated)?;
+
+            for arg in args {
+                match arg {
+                    FieldArg::Default(kw, _) if default.is_some() => {
+                        return Err(syn::Error::new(kw.span, "duplicate default specification"));
+                    },
+                    FieldArg::Default(_, None) => default = Some(FieldDefault::Trait),
+                    FieldArg::Default(_, Some(expr)) => default = Some(FieldDefault::Expr(Box::new(expr.parse()?))),
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(default)
+    }
+
+    /// The expression that evaluates to the default
+    fn value(&self) -> TokenStream {
+        match self {
+            FieldDefault::Trait => quote! { ::core::default::Default::default() },
+            FieldDefault::Expr(expr) => quote! { #expr },
+        }
+    }
+}
+
+/// The defaults of `fields`, with `generics` extended by the bounds they need, i.e. that the types of the fields
+/// with `#[include_js(default)]` implement `Default`.
+fn field_defaults(fields: &Fields, generics: &Generics) -> syn::Result<(Vec<Option<FieldDefault>>, Generics)> {
+    let defaults = fields.iter().map(FieldDefault::get).collect::<syn::Result<Vec<_>>>()?;
+    let mut generics = generics.clone();
+
+    if generics.type_params().next().is_some() {
+        let bounds = fields
+            .iter()
+            .zip(&defaults)
+            .filter(|(_, default)| matches!(default, Some(FieldDefault::Trait)))
+            .map(|(field, _)| -> WherePredicate {
+                let ty = &field.ty;
+                syn::parse_quote!(#ty: ::core::default::Default)
+            });
+
+        generics.make_where_clause().predicates.extend(bounds);
+    }
+
+    Ok((defaults, generics))
+}
+
+/// The `Default` implementation of the struct `input` if all of its `fields` have a default, and it has any.
+pub(super) fn default_impl(input: &DeriveInput, fields: &Fields) -> syn::Result<Option<TokenStream>> {
+    let name = &input.ident;
+    let (defaults, generics) = field_defaults(fields, &input.generics)?;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let values = match defaults.iter().map(|default| default.as_ref().map(FieldDefault::value)).collect::<Option<Vec<_>>>() {
+        Some(values) if !values.is_empty() => values,
+        _ => return Ok(None),
+    };
+
+    let value = match fields {
+        Fields::Named(_) => {
+            let idents = fields.iter().map(|field| &field.ident);
+            quote! { #name { #(#idents: #values,)* } }
+        },
+        _ => quote! { #name(#(#values),*) },
+    };
+
+    Ok(Some(quote! {
+        impl #impl_generics ::core::default::Default for #name #ty_generics #where_clause {
+            fn default() -> Self {
+                #value
+            }
+        }
+    }))
+}
+
+/// The builder generated with `#[include_js(builder)]` for the struct `input` with the named `fields`, with a setter
+/// per field and `build` and `render` to finish it. Fields that are not set use their default or make `build` fail.
+/// `generics` are the generics of the `JSTemplate` impl.
+pub(super) fn builder(
+    input: &DeriveInput,
+    fields: &Fields,
+    generics: &Generics,
+    render: bool,
+    krate: &syn::Path,
+) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let builder = format_ident!("{}Builder", name.unraw());
+    let (struct_impl_generics, struct_ty_generics, struct_where_clause) = input.generics.split_for_impl();
+    let (defaults, build_generics) = field_defaults(fields, &input.generics)?;
+    let (build_impl_generics, _, build_where_clause) = build_generics.split_for_impl();
+    let (_, render_generics) = field_defaults(fields, generics)?;
+    let (impl_generics, ty_generics, where_clause) = render_generics.split_for_impl();
+
+    let idents: Vec<_> = fields.iter().map(|field| field.ident.as_ref().expect("the fields are named")).collect();
+    let types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+
+    let values = defaults.iter().zip(&idents).map(|(default, ident)| match default {
+        Some(default) => {
+            let default = default.value();
+            quote! { self.#ident.unwrap_or_else(|| #default) }
+        },
+        None => quote! {
+            self.#ident.ok_or(#krate::MissingField { template: stringify!(#name), field: stringify!(#ident) })?
+        },
+    });
+
+    // without a main template the struct is not a `JSTemplate`
+    let render = render.then(|| {
+        quote! {
+            impl #impl_generics #builder #ty_generics #where_clause {
+                /// Builds and renders the template.
+                ///
+                /// # Panics
+                /// Panics if a field without a default was not set or rendering fails.
+                #vis fn render(self) -> #krate::JSString {
+                    match self.build() {
+                        ::core::result::Result::Ok(template) => #krate::JSTemplate::render_template(&template),
+                        ::core::result::Result::Err(e) => panic!("{}", e),
+                    }
+                }
+            }
+        }
+    });
+
+    let struct_doc = format!("Builder for `{}`, see `{}::builder`.", name, name);
+    let builder_doc = format!("Starts building a `{}` whose fields are set one by one.", name);
+    let setter_docs = idents.iter().map(|ident| format!("Sets the field `{}`.", ident.unraw()));
+
+    Ok(quote! {
+        #[doc = #struct_doc]
+        #vis struct #builder #struct_impl_generics #struct_where_clause {
+            #(#idents: ::core::option::Option<#types>,)*
+        }
+
+        impl #struct_impl_generics #name #struct_ty_generics #struct_where_clause {
+            #[doc = #builder_doc]
+            #vis fn builder() -> #builder #struct_ty_generics {
+                #builder { #(#idents: ::core::option::Option::None,)* }
+            }
+        }
+
+        impl #struct_impl_generics #builder #struct_ty_generics #struct_where_clause {
+            #(
+                #[doc = #setter_docs]
+                #vis fn #idents(mut self, #idents: #types) -> Self {
+                    self.#idents = ::core::option::Option::Some(#idents);
+                    self
+                }
+            )*
+        }
+
+        impl #build_impl_generics #builder #struct_ty_generics #build_where_clause {
+            /// Builds the template, fails if a field without a default was not set.
+            #vis fn build(self) -> ::core::result::Result<#name #struct_ty_generics, #krate::MissingField> {
+                ::core::result::Result::Ok(#name { #(#idents: #values,)* })
+            }
+        }
+
+        #render
+    })
+}
+
+/// The methods rendering the named templates of the struct `input`, `try_render_<name>` and `render_<name>` for each
+/// of them and `try_render_template_named` and `render_template_named` that select one by its name. `renders` are the
+/// templates with the code that renders them into `out`. `generics` are the generics of the `JSTemplate` impl.
+pub(super) fn named_renders(
+    input: &DeriveInput,
+    renders: &[(&NamedTemplate, TokenStream)],
+    generics: &Generics,
+    validate: Option<&TokenStream>,
+    krate: &syn::Path,
+) -> TokenStream {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let start = validate.map(|_| quote! { let start = 0; });
+
+    let methods = renders.iter().map(|(template, render)| {
+        let try_render = format_ident!("try_render_{}", template.name);
+        let render_fn = format_ident!("render_{}", template.name);
+        let try_doc = format!("Renders the template `{}`, returning an error instead of panicking if that fails.", template.name);
+        let doc = format!("Renders the template `{}`.", template.name);
+
+        quote! {
+            #[doc = #try_doc]
+            #vis fn #try_render(&self) -> ::core::result::Result<#krate::JSString, #krate::RenderError> {
+                let mut buf = ::std::string::String::new();
+                #start
+                let out = &mut buf;
+
+                #render?;
+
+                #validate
+
+                // safety: in the macro invocation it was made sure that the resulting string is js,
+                // it was checked above or the user explicitly opted out of the check
+                ::core::result::Result::Ok(unsafe { #krate::JSString::new_unchecked(buf) })
+            }
+
+            #[doc = #doc]
+            ///
+            /// # Panics
+            /// Panics if rendering fails.
+            #vis fn #render_fn(&self) -> #krate::JSString {
+                match self.#try_render() {
+                    ::core::result::Result::Ok(js) => js,
+                    ::core::result::Result::Err(e) => ::core::panic!("{}", e),
+                }
+            }
+        }
+    });
+
+    let names = renders.iter().map(|(template, _)| template.name.to_string());
+    let try_renders = renders.iter().map(|(template, _)| format_ident!("try_render_{}", template.name));
+
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#methods)*
+
+            /// Renders the template with the given name, fails if there is none.
+            #vis fn try_render_template_named(&self, name: &str) -> ::core::result::Result<#krate::JSString, #krate::RenderError> {
+                match name {
+                    #(#names => self.#try_renders(),)*
+                    _ => {
+                        let message = ::std::format!("no template named `{}`", name);
+                        ::core::result::Result::Err(#krate::RenderError::Template(message.into()))
+                    },
+                }
+            }
+
+            /// Renders the template with the given name.
+            ///
+            /// # Panics
+            /// Panics if there is no template with the name or rendering fails.
+            #vis fn render_template_named(&self, name: &str) -> #krate::JSString {
+                match self.try_render_template_named(name) {
+                    ::core::result::Result::Ok(js) => js,
+                    ::core::result::Result::Err(e) => ::core::panic!("{}", e),
+                }
+            }
+        }
+    }
+}
+
+/// The function generated with `#[include_js(fn = "...")]` for the struct `input`, which takes the fields as
+/// arguments and renders the struct built from them. `generics` are the generics of the `JSTemplate` impl.
+pub(super) fn render_fn(input: &DeriveInput, fields: &Fields, function: &Ident, generics: &Generics, krate: &syn::Path) -> TokenStream {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let doc = format!("Renders the template of `{}` with the given fields.", name);
+
+    let args: Vec<_> = fields
         .iter()
-        .map(|f| format!("{}", f.ident.as_ref().unwrap()))
+        .enumerate()
+        .map(|(index, field)| field.ident.clone().unwrap_or_else(|| format_ident!("arg{}", index)))
+        .collect();
+    let types = fields.iter().map(|field| &field.ty);
+
+    let value = match fields {
+        Fields::Named(_) => quote! { #name { #(#args),* } },
+        Fields::Unnamed(_) => quote! { #name(#(#args),*) },
+        Fields::Unit => quote! { #name },
+    };
+
+    quote! {
+        #[doc = #doc]
+        #vis fn #function #impl_generics(#(#args: #types),*) -> #krate::JSString #where_clause {
+            #krate::JSTemplate::render_template(&#value)
+        }
+    }
+}
+
+/// A template that passed `check_template`, together with what the engine needs to render it
+pub(super) enum Compiled {
+    Handlebars(Template),
+    #[cfg(feature = "minijinja")]
+    Minijinja,
+}
+
+/// Generates the rendering of the templates of a type
+pub(super) struct Renderer<'a> {
+    /// code that creates the engine `h` and registers everything the templates need, without the templates themselves
+    pub(super) setup: TokenStream,
+    pub(super) helpers: &'a [HelperSpec],
+    pub(super) escape: EscapeMode,
+    pub(super) krate: &'a syn::Path,
+}
+
+impl Renderer<'_> {
+    /// Generates the rendering of `template` into `out: &mut String`, which evaluates to a `Result<(), RenderError>`.
+    /// `content` is its source, `values` are the expressions referencing the fields.
+    ///
+    /// Templates that only use what `Precompiler` supports are built directly, the others are rendered by the engine.
+    fn render(
+        &self,
+        kind: &Fields,
+        fields: &[TemplateField],
+        values: &[TokenStream],
+        content: &str,
+        compiled: &Compiled,
+    ) -> TokenStream {
+        let krate = self.krate;
+
+        // without minijinja the match only has one arm
+        #[cfg_attr(not(feature = "minijinja"), allow(clippy::infallible_destructuring_match))]
+        let template = match compiled {
+            Compiled::Handlebars(template) => template,
+            #[cfg(feature = "minijinja")]
+            Compiled::Minijinja => return jinja::render(engine_data(kind, fields, values, krate), content, self.escape, krate),
+        };
+
+        let precompiler = Precompiler { fields, values, helpers: self.helpers, escape: self.escape, krate };
+        if let Some(code) = precompiler.template(template) {
+            // the text is a lower bound of the size of the result
+            let capacity: usize = template
+                .elements
+                .iter()
+                .map(|e| match e {
+                    TemplateElement::RawString(text) => text.len(),
+                    _ => 0,
+                })
+                .sum();
+
+            return quote! {
+                {
+                    out.reserve(#capacity);
+                    #code
+                    Ok::<_, #krate::RenderError>(())
+                }
+            };
+        }
+
+        let setup = &self.setup;
+        let data = engine_data(kind, fields, values, krate);
+
+        // json fields are additionally registered as helpers under their name,
+        // which take precedence over the data and write their JSON unescaped for `{{field}}`
+        let json_helpers = fields.iter().filter(|field| field.json).map(|field| {
+            let name = field.name();
+            quote! { h.register_helper(#name, Box::new(#krate::helpers::json_field)); }
+        });
+
+        // the engine is set up once and shared by all renders of the template,
+        // a static in a generic function is shared by all instances which is fine as the engine does not depend on them
+        quote! {
+            {
+                static ENGINE: ::std::sync::OnceLock<#krate::TemplateEngine<'static>> = ::std::sync::OnceLock::new();
+
+                let h = ENGINE.get_or_init(|| {
+                    #setup
+                    #(#json_helpers)*
+                    h.register_template_string("template", #content)
+                        .expect("the template is compiled at compiletime");
+                    h
+                });
+
+                h.render_to_write("template", #data, #krate::helpers::StringWriter(out))
+                    .map_err(|e| #krate::RenderError::Template(Box::new(e)))
+            }
+        }
+    }
+
+    /// Generates the rendering of the template of a struct, `fields` are its fields as returned by `fields`.
+    pub(super) fn render_struct(&self, kind: &Fields, fields: &[TemplateField], content: &str, compiled: &Compiled) -> TokenStream {
+        let values: Vec<_> = fields
+            .iter()
+            .map(|f| {
+                let member = &f.member;
+                quote! { &self.#member }
+            })
+            .collect();
+
+        self.render(kind, fields, &values, content, compiled)
+    }
+
+    /// Generates the match arm that renders the template of `variant`, `fields` are its fields as returned by `fields`.
+    pub(super) fn render_variant(
+        &self,
+        variant: &Variant,
+        fields: &[TemplateField],
+        content: &str,
+        compiled: &Compiled,
+    ) -> TokenStream {
+        let ident = &variant.ident;
+        let bindings: Vec<_> = fields.iter().map(TemplateField::binding).collect();
+
+        let pattern = match &variant.fields {
+            Fields::Named(_) => quote! { Self::#ident { #(#bindings,)* .. } },
+            Fields::Unnamed(_) => quote! { Self::#ident(#(#bindings),*) },
+            Fields::Unit => quote! { Self::#ident },
+        };
+
+        let values: Vec<_> = bindings.iter().map(|b| quote! { #b }).collect();
+        let render = self.render(&variant.fields, fields, &values, content, compiled);
+
+        quote! { #pattern => #render }
+    }
+}
+
+/// With more `bool` fields than this not every combination of their values is checked, as there would be too many
+const MAX_CONDITIONS: usize = 8;
+
+/// The values of the `bool` fields in `referenced` for each time the compiletime check renders the template,
+/// so both branches of conditionals over them are checked. These are all combinations of the values,
+/// or with more than `MAX_CONDITIONS` fields all of them `true` and all of them `false`.
+/// Without `bool` fields there is a single case without any values.
+pub(super) fn condition_cases(fields: &[TemplateField], referenced: &BTreeSet<String>) -> Vec<Vec<(String, bool)>> {
+    let names: Vec<_> = fields
+        .iter()
+        .filter(|f| !f.flatten && f.is_bool() && referenced.contains(&f.name()))
+        .map(TemplateField::name)
+        .collect();
+
+    let combinations = names.len() <= MAX_CONDITIONS;
+    let count = if combinations { 1 << names.len() } else { 2 };
+
+    (0..count)
+        .map(|case: usize| {
+            names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| (name.clone(), if combinations { case >> i & 1 == 0 } else { case == 0 }))
+                .collect()
+        })
         .collect()
 }
 
-pub(super) fn get_attr(input: &DeriveInput) -> Attribute {
-    input
-        .attrs
+/// Adds the values of the `bool` fields in `case` to an error `message` of the compiletime check
+pub(super) fn with_case(message: impl std::fmt::Display, case: &[(String, bool)]) -> String {
+    if case.is_empty() {
+        return message.to_string();
+    }
+
+    let values: Vec<_> = case.iter().map(|(name, value)| format!("`{}` = {}", name, value)).collect();
+    format!("{} (with {})", message, values.join(", "))
+}
+
+/// Types that serialize to a single value
+const PRIMITIVES: &[&str] = &[
+    "bool", "char", "str", "String", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128",
+    "usize", "f32", "f64",
+];
+
+/// Types that serialize like the type they wrap
+const WRAPPERS: &[&str] = &["Box", "Rc", "Arc", "Cow"];
+
+/// Types that serialize to a sequence of the type they are generic over
+const SEQUENCES: &[&str] = &["Vec", "VecDeque", "LinkedList", "HashSet", "BTreeSet", "BinaryHeap"];
+
+/// See `TemplateField::sequence_depth`, the types are only known by name so this is a best effort.
+fn sequence_depth(ty: &Type) -> Option<usize> {
+    match ty {
+        Type::Reference(r) => sequence_depth(&r.elem),
+        Type::Paren(p) => sequence_depth(&p.elem),
+        Type::Group(g) => sequence_depth(&g.elem),
+        Type::Array(a) => sequence_depth(&a.elem).map(|depth| depth + 1),
+        Type::Slice(s) => sequence_depth(&s.elem).map(|depth| depth + 1),
+        Type::Path(p) if p.qself.is_none() => {
+            let segment = p.path.segments.last()?;
+            let name = segment.ident.to_string();
+
+            if PRIMITIVES.contains(&name.as_str()) {
+                return Some(0);
+            }
+
+            let inner = match &segment.arguments {
+                PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+                    GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                })?,
+                _ => return None,
+            };
+
+            if WRAPPERS.contains(&name.as_str()) {
+                sequence_depth(inner)
+            } else if SEQUENCES.contains(&name.as_str()) {
+                sequence_depth(inner).map(|depth| depth + 1)
+            } else {
+                None
+            }
+        },
+        _ => None,
+    }
+}
+
+/// The value the engine renders a template from, `values` are the expressions referencing the fields.
+///
+/// Named fields are collected into an object so the field attributes apply. Tuple fields are collected into
+/// a tuple of references, which always serializes as an array; a newtype struct on its own would
+/// serialize as its only field.
+fn engine_data(kind: &Fields, fields: &[TemplateField], values: &[TokenStream], krate: &syn::Path) -> TokenStream {
+    match kind {
+        Fields::Named(_) => {
+            let inserts = fields.iter().zip(values).map(|(field, value)| {
+                if field.flatten {
+                    quote! { #krate::helpers::flatten_into(&mut data, #value); }
+                } else {
+                    let name = field.name();
+                    quote! { data.insert(#name.to_owned(), #krate::helpers::to_json(#value)); }
+                }
+            });
+
+            quote! {
+                &{
+                    let mut data = #krate::helpers::TemplateData::new();
+                    #(#inserts)*
+                    data
+                }
+            }
+        },
+        Fields::Unnamed(_) => quote! { &(#(#values,)*) },
+        Fields::Unit => quote! { &() },
+    }
+}
+
+/// The bounds that make `fields` `Serialize`, needed if the type has type parameters.
+pub(super) fn serialize_bounds(fields: &[TemplateField], krate: &syn::Path) -> Vec<WherePredicate> {
+    fields
         .iter()
-        .filter(|a| matches!(a.style, AttrStyle::Outer))
-        .find(|a| {
-            a.path
-                .get_ident()
-                .map(|id| id == &Ident::new("include_js", id.span()))
-                .unwrap_or(false)
+        .map(|f| {
+            let ty = &f.ty;
+            syn::parse_quote!(#ty: #krate::helpers::Serialize)
         })
-        .expect("missing template path specification")
-        .clone()
+        .collect()
+}
+
+/// Splits a template path into its segments starting with the top level variable, e.g. `["config", "width"]`
+/// for `config.width`, or returns `None` if it refers to something other than the rendered struct (e.g. `@index` or `../x`).
+fn variable_path(raw_path: &str) -> Option<Vec<String>> {
+    let mut rest = raw_path
+        .strip_prefix("@root.")
+        .or_else(|| raw_path.strip_prefix("this."))
+        .or_else(|| raw_path.strip_prefix("./"))
+        .unwrap_or(raw_path);
+
+    if rest.starts_with('@') || rest.starts_with("..") || rest == "this" {
+        return None;
+    }
+
+    let mut segments = Vec::new();
+
+    while !rest.is_empty() {
+        // segments that are not identifiers, like the index in `[0]`, are written in brackets
+        let (segment, after) = match rest.strip_prefix('[') {
+            Some(bracketed) => bracketed.split_once(']')?,
+            None => rest.split_at(rest.find(['.', '/', '[']).unwrap_or(rest.len())),
+        };
+
+        if segment.is_empty() {
+            return None;
+        }
+
+        segments.push(segment.to_owned());
+        rest = after.strip_prefix(['.', '/']).unwrap_or(after);
+    }
+
+    Some(segments).filter(|segments| !segments.is_empty())
+}
+
+/// Of `paths` starting with a top level variable, the ones into the variable `name` without the variable itself
+pub(super) fn paths_into<'a>(paths: &'a BTreeSet<Vec<String>>, name: &str) -> Vec<&'a [String]> {
+    paths.iter().filter(|path| path[0] == name).map(|path| &path[1..]).collect()
+}
+
+/// The value substituted for a variable in the compiletime check, where `paths` are the paths into it
+/// the template uses, e.g. `["width"]` for `{{config.width}}`. Without paths it is the placeholder `leaf()`,
+/// otherwise an `object()` containing a placeholder at each of the paths, as the type of the field is not known.
+pub(super) fn nested_placeholder<V>(
+    paths: &[&[String]],
+    leaf: &impl Fn() -> V,
+    object: &impl Fn(Vec<(String, V)>) -> V,
+) -> V {
+    let mut children: BTreeMap<&String, Vec<&[String]>> = BTreeMap::new();
+
+    for path in paths {
+        if let Some((first, rest)) = path.split_first() {
+            children.entry(first).or_default().push(rest);
+        }
+    }
+
+    if children.is_empty() {
+        return leaf();
+    }
+
+    object(
+        children
+            .into_iter()
+            .map(|(name, paths)| (name.clone(), nested_placeholder(&paths, leaf, object)))
+            .collect(),
+    )
+}
+
+/// What a template refers to by name
+#[derive(Default)]
+pub(super) struct References {
+    /// the top level variables
+    pub(super) variables: BTreeSet<String>,
+    /// the partials that are rendered in the same context
+    pub(super) partials: BTreeSet<String>,
+    /// the top level variables that are iterated with `{{#each variable}}`
+    pub(super) iterated: BTreeSet<String>,
+    /// the paths into top level variables, e.g. `["config", "width"]` for `{{config.width}}`
+    pub(super) paths: BTreeSet<Vec<String>>,
+}
+
+
+fn raw_path(path: &handlebars::Path) -> &str {
+    match path {
+        handlebars::Path::Relative((_, raw)) => raw,
+        handlebars::Path::Local((_, _, raw)) => raw,
+    }
+}
+
+fn collect_parameter(param: &Parameter, refs: &mut References) {
+    match param {
+        Parameter::Path(path) => {
+            if let Some(path) = variable_path(raw_path(path)) {
+                refs.variables.insert(path[0].clone());
+                if path.len() > 1 {
+                    refs.paths.insert(path);
+                }
+            }
+        },
+        Parameter::Subexpression(sub) => {
+            if let TemplateElement::Expression(helper) = sub.as_element() {
+                collect_helper(helper, refs);
+            }
+        },
+        Parameter::Name(_) | Parameter::Literal(_) => (),
+    }
+}
+
+fn collect_helper(helper: &HelperTemplate, refs: &mut References) {
+    collect_parameter(&helper.name, refs);
+    helper.params.iter().for_each(|p| collect_parameter(p, refs));
+    helper.hash.values().for_each(|p| collect_parameter(p, refs));
+
+    if let (true, Parameter::Name(name), Some(Parameter::Path(path))) = (helper.block, &helper.name, helper.params.first()) {
+        if let (true, Some([variable])) = (name == "each", variable_path(raw_path(path)).as_deref()) {
+            refs.iterated.insert(variable.clone());
+        }
+    }
+
+    // only these builtin blocks render their contents in the same context,
+    // the variables inside of all other blocks are relative to something else
+    if helper.block && matches!(&helper.name, Parameter::Name(name) if name == "if" || name == "unless") {
+        helper.template.iter().chain(&helper.inverse).for_each(|t| collect_template(t, refs));
+    }
+}
+
+fn collect_partial(partial: &DecoratorTemplate, refs: &mut References) {
+    collect_parameter(&partial.name, refs);
+    partial.params.iter().for_each(|p| collect_parameter(p, refs));
+    partial.hash.values().for_each(|p| collect_parameter(p, refs));
+
+    // a partial with a context parameter or hash is rendered in a different context
+    if let (Parameter::Name(name), true, true) = (&partial.name, partial.params.is_empty(), partial.hash.is_empty()) {
+        refs.partials.insert(name.clone());
+    }
+
+    partial.template.iter().for_each(|t| collect_template(t, refs));
+}
+
+fn collect_template(template: &Template, refs: &mut References) {
+    for element in &template.elements {
+        match element {
+            TemplateElement::HTMLExpression(param) => collect_parameter(param, refs),
+            TemplateElement::Expression(helper) | TemplateElement::HelperBlock(helper) => collect_helper(helper, refs),
+            TemplateElement::PartialExpression(partial) | TemplateElement::PartialBlock(partial) => {
+                collect_partial(partial, refs)
+            },
+            _ => (),
+        }
+    }
+}
+
+/// Collects the names of all top level variables and partials referenced by `template`.
+pub(super) fn references(template: &Template) -> References {
+    let mut refs = References::default();
+    collect_template(template, &mut refs);
+    refs
+}
+
+/// Compiletime stand-in for `include_js::helpers::js_string`, renders the placeholder as a string literal.
+pub(super) fn js_string_helper(
+    h: &handlebars::Helper,
+    _: &handlebars::Handlebars,
+    _: &handlebars::Context,
+    _: &mut handlebars::RenderContext,
+    out: &mut dyn handlebars::Output,
+) -> handlebars::HelperResult {
+    let value = h
+        .param(0)
+        .ok_or_else(|| handlebars::RenderError::new("js_string expects exactly one parameter"))?
+        .value()
+        .render();
+
+    out.write(JSString::quote(&value).as_str())?;
+    Ok(())
+}
+
+/// Generates code that makes the compiler warn about `field` not being used in the template at `template_path`.
+/// There is no stable API for proc macro warnings, so this goes through a deprecated item.
+pub(super) fn unused_field_warning(field: &TemplateField, place: &str) -> TokenStream {
+    let note = format!("field `{}` is never used in {}", field.name(), place);
+
+    quote_spanned! {field.span()=>
+        const _: () = {
+            #[deprecated(note = #note)]
+            #[allow(non_camel_case_types)]
+            struct unused_template_field;
+
+            let _ = unused_template_field;
+        };
+    }
 }
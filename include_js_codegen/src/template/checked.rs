@@ -0,0 +1,49 @@
+//! `include_js_template!`, which checks a template on its own so it can be rendered from any data.
+
+use std::collections::HashMap;
+
+use handlebars::{template::Template, Handlebars, JsonValue};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::LitStr;
+
+use super::TemplateFile;
+use crate::registry::Kind;
+
+pub(crate) fn expand_include_js_template(template: &LitStr, krate: &syn::Path) -> syn::Result<TokenStream> {
+    let (TemplateFile { path, content }, track) = TemplateFile::load(template)?;
+
+    let compiled = Template::compile(&content)
+        .map_err(|e| syn::Error::new(path.span, format!("error compiling template: {}", e)))?;
+    let refs = super::references(&compiled);
+
+    // nothing is known about the data, so every variable the template uses is substituted like a field of unknown type
+    let data: HashMap<&String, JsonValue> = refs
+        .variables
+        .iter()
+        .map(|var| (var, crate::nested_placeholder(&super::paths_into(&refs.paths, var), "[]".to_owned())))
+        .collect();
+
+    let mut h = Handlebars::new();
+    h.set_strict_mode(true);
+    // placeholders are Javascript and need to end up in the output verbatim
+    h.register_escape_fn(handlebars::no_escape);
+    h.register_helper("js_string", Box::new(super::js_string_helper));
+
+    let expanded = h
+        .render_template(&content, &data)
+        .map_err(|e| syn::Error::new(path.span, format!("error rendering template: {}", e)))?;
+    crate::check_syntax(&expanded, &path.value, path.span)?;
+
+    let register = crate::registry::register(krate, Kind::Template, &path.value, &content, None);
+
+    // a static so the engine of the template is shared by every render, wherever the macro is used
+    Ok(quote! {
+        {
+            #track
+            #register
+            static TEMPLATE: #krate::CheckedTemplate = unsafe { #krate::CheckedTemplate::new_unchecked(#content) };
+            &TEMPLATE
+        }
+    })
+}
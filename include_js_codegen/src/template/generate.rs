@@ -0,0 +1,114 @@
+//! `js_template!`, which generates a struct that derives `JSTemplate` from the variables of a template.
+
+use handlebars::template::Template;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    token, Attribute, Fields, FieldsNamed, Ident, LitStr, Token, Visibility,
+};
+
+use super::TemplateFile;
+
+/// The input of `js_template!`, i.e. `#[attrs] vis "path" as Name { declared: Type, ... }`
+pub(crate) struct TemplateStruct {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    template: LitStr,
+    ident: Ident,
+    /// fields whose type or attributes differ from the generated ones
+    declared: Option<FieldsNamed>,
+}
+
+impl Parse for TemplateStruct {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        let template = input.parse()?;
+        input.parse::<Token![as]>()?;
+        let ident = input.parse()?;
+        let declared = if input.peek(token::Brace) { Some(input.parse()?) } else { None };
+
+        Ok(TemplateStruct { attrs, vis, template, ident, declared })
+    }
+}
+
+/// The field for the template variable `name`, keywords are turned into raw identifiers like `r#type`.
+fn field_ident(name: &str, span: Span) -> syn::Result<Ident> {
+    syn::parse_str::<Ident>(name)
+        .or_else(|_| syn::parse_str::<Ident>(&format!("r#{}", name)))
+        .map(|mut ident| {
+            ident.set_span(span);
+            ident
+        })
+        .map_err(|_| {
+            syn::Error::new(
+                span,
+                format!(
+                    "template variable `{0}` is not a valid field name, declare a field with `#[include_js(rename = \"{0}\")]` for it",
+                    name
+                ),
+            )
+        })
+}
+
+pub(crate) fn expand_js_template_struct(input: &TemplateStruct) -> syn::Result<TokenStream> {
+    let TemplateStruct { attrs, vis, template, ident, declared } = input;
+
+    // the derive tracks the file and checks the template, it is only read here to find its variables
+    let (file, _) = TemplateFile::load(template)?;
+    let compiled = Template::compile(&file.content)
+        .map_err(|e| syn::Error::new(template.span(), format!("error compiling template: {}", e)))?;
+    let refs = super::references(&compiled);
+
+    let declared: Vec<_> = declared.iter().flat_map(|fields| fields.named.iter().cloned()).collect();
+    let declared_names: Vec<_> = super::fields(&Fields::Named(syn::parse_quote!({ #(#declared),* })), attrs)?
+        .iter()
+        .map(super::TemplateField::name)
+        .collect();
+
+    let generated = refs
+        .variables
+        .iter()
+        .filter(|var| !declared_names.contains(var))
+        .map(|var| {
+            let ident = field_ident(var, template.span())?;
+
+            // `{{#each var}}` iterates it, paths into it need a type that has these fields
+            let ty = if refs.iterated.contains(var) {
+                quote! { Vec<String> }
+            } else if let Some(path) = refs.paths.iter().find(|path| &path[0] == var) {
+                return Err(syn::Error::new(
+                    template.span(),
+                    format!(
+                        "template variable `{}` is used as `{}`, declare a field with a type that has these fields for it",
+                        var,
+                        path.join(".")
+                    ),
+                ));
+            } else {
+                quote! { String }
+            };
+
+            Ok(quote! { #vis #ident: #ty })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    // declared fields are as visible as the generated ones unless they say otherwise
+    let declared = declared.into_iter().map(|mut field| {
+        if let Visibility::Inherited = field.vis {
+            field.vis = vis.clone();
+        }
+        field
+    });
+
+    Ok(quote! {
+        #(#attrs)*
+        #[derive(::include_js::JSTemplate)]
+        #[include_js(template = #template)]
+        #vis struct #ident {
+            #(#generated,)*
+            #(#declared,)*
+        }
+    })
+}
@@ -0,0 +1,211 @@
+//! Templates rendered by minijinja instead of handlebars, selected with `#[include_js(engine = "minijinja")]`.
+//!
+//! They are checked the same way, but none of the handlebars specific features like helpers, partials,
+//! layouts or the precompilation are available.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+use include_js_core::JSString;
+use minijinja::{
+    value::{Object, ObjectKind, SeqObject},
+    AutoEscape, Environment, UndefinedBehavior, Value,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Member;
+
+use super::{CheckMode, EscapeMode, TemplateField, TemplateFile, TemplateSource};
+
+/// The functions every environment provides, `{{ range(3) }}` references them like variables
+const GLOBALS: &[&str] = &["range", "dict", "debug", "namespace"];
+
+/// The value of a variable when checking the template, renders as its placeholder.
+/// Otherwise it is a sequence, so loops over it don't iterate the characters of the placeholder.
+/// Sequences of primitive values contain a single placeholder so the body of a loop over them is checked as well,
+/// everything else is empty like the default placeholder `[]`.
+#[derive(Debug)]
+struct Placeholder {
+    js: String,
+    item: Option<Value>,
+}
+
+impl Placeholder {
+    /// The placeholder `js` for a sequence that is nested `depth` levels deep
+    fn value(js: String, depth: Option<usize>) -> Value {
+        let item = match depth {
+            Some(depth) if depth > 0 => Some(Placeholder::value(js.clone(), Some(depth - 1))),
+            _ => None,
+        };
+
+        Value::from_object(Placeholder { js, item })
+    }
+}
+
+impl fmt::Display for Placeholder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.js)
+    }
+}
+
+impl Object for Placeholder {
+    fn kind(&self) -> ObjectKind<'_> {
+        ObjectKind::Seq(self)
+    }
+}
+
+impl SeqObject for Placeholder {
+    fn get_item(&self, idx: usize) -> Option<Value> {
+        self.item.clone().filter(|_| idx == 0)
+    }
+
+    fn item_count(&self) -> usize {
+        self.item.iter().count()
+    }
+}
+
+/// The placeholder of a variable with `paths` into it, see `super::nested_placeholder`
+fn nested_placeholder(paths: &[&[String]], placeholder: String) -> Value {
+    super::nested_placeholder(paths, &|| Placeholder::value(placeholder.clone(), None), &|fields| {
+        fields.into_iter().collect()
+    })
+}
+
+/// Compiletime stand-in for `include_js::helpers::js_string_filter`
+fn js_string(value: Value) -> Value {
+    let quoted = match value.as_str() {
+        Some(s) => JSString::quote(s),
+        None => JSString::quote(&value.to_string()),
+    };
+
+    Value::from_safe_string(quoted.into_string())
+}
+
+/// Reads the template at `template` and checks it against `fields`, `owner` is the type or variant they belong to.
+/// Returns the content of the template, the code that tracks it and the names of the fields it doesn't use.
+pub(crate) fn check_template(
+    template: &TemplateSource,
+    fields: &[TemplateField],
+    owner: &str,
+    check: CheckMode,
+    lints: &crate::lint::Lints,
+) -> syn::Result<(String, TokenStream, BTreeSet<String>)> {
+    // the root of the data is an object, so there is no way to refer to positional fields
+    if let Some(field) = fields.iter().find(|f| matches!(f.member, Member::Unnamed(_))) {
+        return Err(syn::Error::new(field.span(), "the minijinja engine only supports named fields"));
+    }
+
+    if let Some(field) = fields.iter().find(|f| f.json) {
+        return Err(syn::Error::new(
+            field.span(),
+            "`json` is not supported by the minijinja engine, use the `tojson` filter instead",
+        ));
+    }
+
+    let (TemplateFile { path: template_path, content }, track) = template.load()?;
+
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    // placeholders are Javascript and need to end up in the output verbatim
+    env.set_auto_escape_callback(|_| AutoEscape::None);
+    env.add_filter("js_string", js_string);
+    env.add_template("template", &content)
+        .map_err(|e| syn::Error::new(template_path.span, format!("error compiling template: {}", e)))?;
+
+    let compiled = env.get_template("template").expect("the template was just added");
+
+    // with the paths into variables like `config.width`, as far as they are known
+    let paths: BTreeSet<Vec<String>> = compiled
+        .undeclared_variables(true)
+        .iter()
+        .map(|path| path.split('.').map(str::to_owned).collect())
+        .filter(|path: &Vec<String>| !GLOBALS.contains(&path[0].as_str()))
+        .collect();
+
+    let referenced: BTreeSet<_> = paths.iter().map(|path| path[0].clone()).collect();
+
+    let unknown: Vec<_> = referenced
+        .iter()
+        .filter(|var| !fields.iter().any(|f| !f.flatten && &f.name() == *var))
+        .collect();
+
+    // the variables a flattened field provides are only known at runtime
+    let flattened = fields.iter().find(|f| f.flatten);
+
+    if !unknown.is_empty() && flattened.is_none() {
+        let unknown: Vec<_> = unknown.iter().map(|var| format!("`{}`", var)).collect();
+
+        return Err(syn::Error::new(
+            template_path.span,
+            format!("template variables without matching field in `{}`: {}", owner, unknown.join(", ")),
+        ));
+    }
+
+    let unused = fields.iter().map(|f| f.name()).filter(|name| !referenced.contains(name)).collect();
+
+    if check == CheckMode::Placeholder {
+        let mut data: HashMap<String, Value> = fields
+            .iter()
+            .filter(|f| !f.flatten)
+            .map(|f| {
+                let value = match f.sequence_depth() {
+                    Some(depth) => Placeholder::value(f.placeholder(), Some(depth)),
+                    None => nested_placeholder(&super::paths_into(&paths, &f.name()), f.placeholder()),
+                };
+
+                (f.name(), value)
+            })
+            .collect();
+
+        if let Some(flattened) = flattened {
+            data.extend(unknown.iter().map(|var| {
+                (var.to_string(), nested_placeholder(&super::paths_into(&paths, var), flattened.placeholder()))
+            }));
+        }
+
+        for case in super::condition_cases(fields, &referenced) {
+            data.extend(case.iter().map(|(name, value)| (name.clone(), Value::from(*value))));
+
+            let expanded = compiled.render(&data).map_err(|e| {
+                syn::Error::new(template_path.span, super::with_case(format!("error rendering template: {}", e), &case))
+            })?;
+            crate::check_syntax(&expanded, &template_path.value, template_path.span)
+                .and_then(|_| lints.check(&expanded, false, &template_path.value, template_path.span))
+                .map_err(|e| syn::Error::new(template_path.span, super::with_case(e, &case)))?;
+        }
+    }
+
+    Ok((content, track, unused))
+}
+
+/// Generates the rendering of a template with the source `content` from `data` into `out: &mut String`,
+/// which evaluates to a `Result<(), RenderError>`.
+pub(super) fn render(data: TokenStream, content: &str, escape: EscapeMode, krate: &syn::Path) -> TokenStream {
+    let auto_escape = match escape {
+        EscapeMode::None => quote! { #krate::minijinja::AutoEscape::None },
+        EscapeMode::Html => quote! { #krate::minijinja::AutoEscape::Html },
+    };
+
+    // like the handlebars engine, the environment is set up once and shared by all renders of the template
+    quote! {
+        {
+            static ENVIRONMENT: ::std::sync::OnceLock<#krate::minijinja::Environment<'static>> = ::std::sync::OnceLock::new();
+
+            let env = ENVIRONMENT.get_or_init(|| {
+                let mut env = #krate::minijinja::Environment::new();
+                env.set_undefined_behavior(#krate::minijinja::UndefinedBehavior::Strict);
+                env.set_auto_escape_callback(|_| #auto_escape);
+                env.add_filter("js_string", #krate::helpers::js_string_filter);
+                env.add_template("template", #content)
+                    .expect("the template is compiled at compiletime");
+                env
+            });
+
+            env.get_template("template")
+                .and_then(|template| template.render_to_write(#data, #krate::helpers::StringWriter(out)).map(|_| ()))
+                .map_err(|e| #krate::RenderError::Template(Box::new(e)))
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Translates templates into code that builds the rendered string directly, so rendering does not need to
+//! parse the template with the engine every time.
+//!
+//! Only the constructs most templates consist of are supported: text, comments, top level variables,
+//! `js_string` and `if`/`unless` on top level variables. Everything else is left to the engine.
+
+use handlebars::template::{HelperTemplate, Parameter, Template, TemplateElement};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use super::{EscapeMode, HelperSpec, TemplateField};
+
+pub(super) struct Precompiler<'a> {
+    pub(super) fields: &'a [TemplateField],
+    /// the expressions referencing the fields
+    pub(super) values: &'a [TokenStream],
+    pub(super) helpers: &'a [HelperSpec],
+    pub(super) escape: EscapeMode,
+    pub(super) krate: &'a syn::Path,
+}
+
+/// The helpers every engine has, `{{name}}` calls these instead of looking up a variable
+const BUILTIN_HELPERS: &[&str] = &[
+    "if", "unless", "each", "with", "lookup", "raw", "log", "eq", "ne", "gt", "gte", "lt", "lte", "and", "or", "not",
+    "js_string",
+];
+
+fn is_helper(helper: &HelperTemplate, name: &str) -> bool {
+    matches!(&helper.name, Parameter::Name(n) if n == name)
+}
+
+impl Precompiler<'_> {
+    /// Translates `template` into code that appends to `out: &mut String`,
+    /// or returns `None` if it uses anything that has to be rendered by the engine.
+    pub(super) fn template(&self, template: &Template) -> Option<TokenStream> {
+        let elements = template
+            .elements
+            .iter()
+            .map(|element| self.element(element))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(quote! { #(#elements)* })
+    }
+
+    /// The field a parameter refers to, if it is a top level variable like `{{field}}` or `{{[0]}}`
+    fn field(&self, param: &Parameter) -> Option<(&TemplateField, &TokenStream)> {
+        let raw = match param {
+            Parameter::Path(handlebars::Path::Relative((_, raw))) => raw,
+            _ => return None,
+        };
+
+        // a helper with the name of a field takes precedence over it
+        if BUILTIN_HELPERS.contains(&raw.as_str()) || self.helpers.iter().any(|h| &h.name.value() == raw) {
+            return None;
+        }
+
+        self.fields.iter().zip(self.values).find(|(field, _)| {
+            let name = field.name();
+            !field.flatten && (*raw == name || *raw == format!("[{}]", name))
+        })
+    }
+
+    fn element(&self, element: &TemplateElement) -> Option<TokenStream> {
+        let krate = self.krate;
+
+        match element {
+            TemplateElement::RawString(text) => Some(quote! { out.push_str(#text); }),
+            TemplateElement::Comment(_) => Some(TokenStream::new()),
+            // `{{{field}}}`, json fields are only written as JSON by `{{field}}`
+            TemplateElement::HTMLExpression(param) => {
+                let (field, value) = self.field(param)?;
+                (!field.json).then(|| quote! { #krate::helpers::push_value(out, #value, false); })
+            },
+            // `{{field}}`
+            TemplateElement::Expression(helper) if helper.params.is_empty() && helper.hash.is_empty() => {
+                let (field, value) = self.field(&helper.name)?;
+
+                if field.json {
+                    Some(quote! { #krate::helpers::push_json(out, #value); })
+                } else {
+                    let escape_html = self.escape == EscapeMode::Html;
+                    Some(quote! { #krate::helpers::push_value(out, #value, #escape_html); })
+                }
+            },
+            // `{{js_string field}}`
+            TemplateElement::Expression(helper)
+                if is_helper(helper, "js_string") && helper.params.len() == 1 && helper.hash.is_empty() =>
+            {
+                let (_, value) = self.field(&helper.params[0])?;
+                Some(quote! { #krate::helpers::push_js_string(out, #value); })
+            },
+            // `{{#if field}}...{{else}}...{{/if}}` and the same with `unless`
+            TemplateElement::HelperBlock(helper)
+                if (is_helper(helper, "if") || is_helper(helper, "unless"))
+                    && helper.params.len() == 1
+                    && helper.hash.is_empty()
+                    && helper.block_param.is_none() =>
+            {
+                let (_, value) = self.field(&helper.params[0])?;
+                let then = helper.template.as_ref().map_or(Some(TokenStream::new()), |t| self.template(t))?;
+                let otherwise = helper.inverse.as_ref().map_or(Some(TokenStream::new()), |t| self.template(t))?;
+
+                let condition = if is_helper(helper, "if") {
+                    quote! { #krate::helpers::is_truthy(#value) }
+                } else {
+                    quote! { !#krate::helpers::is_truthy(#value) }
+                };
+
+                Some(quote! {
+                    if #condition {
+                        #then
+                    } else {
+                        #otherwise
+                    }
+                })
+            },
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Reads the `#[serde(...)]` attributes that change the name or presence of fields,
+//! so the template data matches what serde would serialize, and rejects the ones it can't honor.
+
+use syn::{Attribute, Lit, LitStr, Meta, NestedMeta};
+
+/// The serde attributes of a field that matter for the template data
+#[derive(Default)]
+pub(super) struct SerdeField {
+    pub(super) rename: Option<LitStr>,
+    pub(super) skip: bool,
+    pub(super) flatten: bool,
+}
+
+/// The items of all `#[serde(...)]` attributes, attributes that don't parse are left to serde to report
+fn serde_items(attrs: &[Attribute]) -> impl Iterator<Item = Meta> + '_ {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("serde"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested| match nested {
+            NestedMeta::Meta(meta) => Some(meta),
+            NestedMeta::Lit(_) => None,
+        })
+}
+
+fn lit_str(lit: &Lit) -> Option<LitStr> {
+    match lit {
+        Lit::Str(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// The name used for serialization of `rename = "..."` or `rename(serialize = "...")`
+fn serialize_name(meta: &Meta) -> Option<LitStr> {
+    match meta {
+        Meta::NameValue(nv) => lit_str(&nv.lit),
+        Meta::List(list) => list.nested.iter().find_map(|nested| match nested {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("serialize") => lit_str(&nv.lit),
+            _ => None,
+        }),
+        Meta::Path(_) => None,
+    }
+}
+
+pub(super) fn field(attrs: &[Attribute]) -> SerdeField {
+    let mut field = SerdeField::default();
+
+    for meta in serde_items(attrs) {
+        let path = meta.path();
+
+        if path.is_ident("rename") {
+            field.rename = serialize_name(&meta).or(field.rename);
+        } else if path.is_ident("skip") || path.is_ident("skip_serializing") {
+            field.skip = true;
+        } else if path.is_ident("flatten") {
+            field.flatten = true;
+        }
+    }
+
+    field
+}
+
+/// The items of `#[serde(...)]` on a field that change how it serializes in ways the template data does not model,
+/// as it is built from the fields one by one
+const UNSUPPORTED: &[&str] = &["serialize_with", "with", "skip_serializing_if", "getter"];
+
+/// Fails for serde attributes of a field that would be ignored by the template data
+pub(super) fn reject_unsupported(attrs: &[Attribute]) -> syn::Result<()> {
+    match serde_items(attrs).find(|meta| UNSUPPORTED.iter().any(|name| meta.path().is_ident(name))) {
+        Some(meta) => {
+            let name = meta.path().get_ident().map(ToString::to_string).unwrap_or_default();
+            Err(syn::Error::new_spanned(
+                meta.path(),
+                format!(
+                    "`#[serde({})]` is not supported by the JSTemplate derive, the template data is built from the \
+                     fields one by one and would not use it; implement `Serialize` for the type of the field instead",
+                    name,
+                ),
+            ))
+        },
+        None => Ok(()),
+    }
+}
+
+/// A `#[serde(rename_all = "...")]` rule
+#[derive(Clone, Copy)]
+pub(super) enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lowercase" => Some(RenameRule::Lower),
+            "UPPERCASE" => Some(RenameRule::Upper),
+            "PascalCase" => Some(RenameRule::Pascal),
+            "camelCase" => Some(RenameRule::Camel),
+            "snake_case" => Some(RenameRule::Snake),
+            "SCREAMING_SNAKE_CASE" => Some(RenameRule::ScreamingSnake),
+            "kebab-case" => Some(RenameRule::Kebab),
+            "SCREAMING-KEBAB-CASE" => Some(RenameRule::ScreamingKebab),
+            _ => None,
+        }
+    }
+
+    /// Applies the rule to a field name, which like in serde is assumed to be snake_case
+    pub(super) fn apply(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_owned(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => field
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                        .unwrap_or_default()
+                })
+                .collect(),
+            RenameRule::Camel => {
+                let pascal = RenameRule::Pascal.apply(field);
+                let mut chars = pascal.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_lowercase().to_string() + chars.as_str())
+                    .unwrap_or_default()
+            },
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
+
+/// The `rename_all` rule for serialization given on a struct or enum variant, unknown rules are left to serde to report
+pub(super) fn rename_all(attrs: &[Attribute]) -> Option<RenameRule> {
+    serde_items(attrs)
+        .filter(|meta| meta.path().is_ident("rename_all"))
+        .filter_map(|meta| serialize_name(&meta))
+        .last()
+        .and_then(|rule| RenameRule::from_name(&rule.value()))
+}
@@ -0,0 +1,359 @@
+//! Transformations that compile the included files to plain Javascript at compiletime, i.e. TypeScript for
+//! `include_ts!`, JSX with `include_js!("path", jsx(pragma = "h"))` and newer Javascript that is transpiled down
+//! to an older version with `include_js!("path", target = es5)`.
+//!
+//! The code is parsed, transformed and printed again by swc, comments are kept. The result is validated like
+//! any other included code afterwards.
+
+use proc_macro2::Span;
+use syn::{
+    parenthesized,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Ident, LitStr, Token,
+};
+
+use crate::source_map::FileMap;
+
+mod kw {
+    syn::custom_keyword!(pragma);
+    syn::custom_keyword!(pragma_frag);
+}
+
+/// A single `key = value` argument of `jsx(...)`
+enum JsxArg {
+    Pragma(kw::pragma, LitStr),
+    PragmaFrag(kw::pragma_frag, LitStr),
+}
+
+impl Parse for JsxArg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lookahead = input.lookahead1();
+
+        if lookahead.peek(kw::pragma) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(JsxArg::Pragma(kw, input.parse()?))
+        } else if lookahead.peek(kw::pragma_frag) {
+            let kw = input.parse()?;
+            input.parse::<Token![=]>()?;
+            Ok(JsxArg::PragmaFrag(kw, input.parse()?))
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+/// How JSX is compiled, i.e. the functions elements and fragments are created with.
+/// They default to `React.createElement` and `React.Fragment`.
+#[derive(Clone, Default)]
+#[cfg_attr(not(feature = "jsx"), allow(dead_code))]
+pub(super) struct Jsx {
+    pragma: Option<String>,
+    pragma_frag: Option<String>,
+}
+
+impl Jsx {
+    /// Parses the optional parenthesized arguments after `jsx`, `span` is the span of the keyword.
+    pub(super) fn parse_args(input: ParseStream, span: Span) -> syn::Result<Self> {
+        if !cfg!(feature = "jsx") {
+            return Err(syn::Error::new(span, "compiling JSX requires the `jsx` feature of include_js"));
+        }
+
+        let mut jsx = Jsx::default();
+
+        if !input.peek(syn::token::Paren) {
+            return Ok(jsx);
+        }
+
+        let content;
+        parenthesized!(content in input);
+
+        for arg in Punctuated::<JsxArg, Token![,]>::parse_terminated(&content)? {
+            match arg {
+                JsxArg::Pragma(kw, _) if jsx.pragma.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate pragma specification"));
+                },
+                JsxArg::Pragma(_, pragma) => jsx.pragma = Some(pragma.value()),
+                JsxArg::PragmaFrag(kw, _) if jsx.pragma_frag.is_some() => {
+                    return Err(syn::Error::new(kw.span, "duplicate pragma_frag specification"));
+                },
+                JsxArg::PragmaFrag(_, pragma_frag) => jsx.pragma_frag = Some(pragma_frag.value()),
+            }
+        }
+
+        Ok(jsx)
+    }
+}
+
+/// Parses the version after `target =`, i.e. `es5` or `es2015` to `es2022`, `span` is the span of the keyword.
+/// Everything newer than the version is transpiled.
+pub(super) fn parse_target(input: ParseStream, span: Span) -> syn::Result<u16> {
+    if !cfg!(feature = "transpile") {
+        return Err(syn::Error::new(span, "transpiling requires the `transpile` feature of include_js"));
+    }
+
+    let ident: Ident = input.parse()?;
+
+    match ident.to_string().as_str() {
+        "es5" => Ok(5),
+        version => match version.strip_prefix("es").and_then(|year| year.parse().ok()) {
+            Some(year @ 2015..=2022) => Ok(year),
+            _ => Err(syn::Error::new(ident.span(), "expected `es5` or one of `es2015` to `es2022`")),
+        },
+    }
+}
+
+/// How the included files are compiled to plain Javascript before they are validated
+#[derive(Clone, Default)]
+pub(super) struct Transform {
+    /// whether the files are TypeScript, only set by `include_ts!`
+    pub(super) typescript: bool,
+    pub(super) jsx: Option<Jsx>,
+    /// the ECMAScript version everything newer is transpiled to
+    pub(super) target: Option<u16>,
+}
+
+impl Transform {
+    /// Whether the files are included as they are
+    pub(super) fn is_empty(&self) -> bool {
+        !self.typescript && self.jsx.is_none() && self.target.is_none()
+    }
+
+    /// Compiles `content` to Javascript and converts an error into a compile error at `span`.
+    /// With `source_map` the map from the result to `content` is returned as well.
+    #[cfg(any(feature = "jsx", feature = "transpile"))]
+    pub(super) fn apply(
+        &self,
+        content: &str,
+        origin: &str,
+        span: Span,
+        source_map: bool,
+    ) -> syn::Result<(String, Option<FileMap>)> {
+        swc::compile(self, content, origin, span, source_map)
+    }
+
+    /// Without the features that enable them the transformations are rejected when parsing the arguments,
+    /// so there is nothing to compile.
+    #[cfg(not(any(feature = "jsx", feature = "transpile")))]
+    pub(super) fn apply(
+        &self,
+        content: &str,
+        origin: &str,
+        _span: Span,
+        source_map: bool,
+    ) -> syn::Result<(String, Option<FileMap>)> {
+        debug_assert!(self.is_empty(), "transformations are rejected without their features");
+        Ok((content.to_owned(), source_map.then(|| FileMap::identity(origin, content))))
+    }
+}
+
+#[cfg(any(feature = "jsx", feature = "transpile"))]
+mod swc {
+    use proc_macro2::Span;
+    use swc_common::{
+        comments::SingleThreadedComments, source_map::SourceMapGenConfig, sync::Lrc, FileName, Globals, Mark, SourceMap,
+        Spanned, GLOBALS,
+    };
+    use swc_ecma_ast::Program;
+    use swc_ecma_codegen::{text_writer::JsWriter, Emitter};
+    use swc_ecma_parser::{EsSyntax, Parser, StringInput, Syntax, TsSyntax};
+    use swc_ecma_transforms_base::{fixer::fixer, hygiene::hygiene, resolver};
+
+    use super::Transform;
+    use crate::{
+        parser::swc::{location, syntax_error},
+        source_map::FileMap,
+    };
+
+    /// Names the sources of a map like the included files and puts their content into it
+    struct MapConfig;
+
+    impl SourceMapGenConfig for MapConfig {
+        fn file_name_to_source(&self, f: &FileName) -> String {
+            match f {
+                FileName::Custom(origin) => origin.clone(),
+                f => f.to_string(),
+            }
+        }
+
+        fn inline_sources_content(&self, _f: &FileName) -> bool {
+            true
+        }
+    }
+
+    /// Parses `content`, applies `transform` and prints the result, with `source_map` together with its source map.
+    pub(super) fn compile(
+        transform: &Transform,
+        content: &str,
+        origin: &str,
+        span: Span,
+        source_map: bool,
+    ) -> syn::Result<(String, Option<FileMap>)> {
+        let cm: Lrc<SourceMap> = Default::default();
+        let comments = SingleThreadedComments::default();
+        let file = cm.new_source_file(Lrc::new(FileName::Custom(origin.to_owned())), content.to_owned());
+
+        let syntax = match transform.typescript {
+            true => Syntax::Typescript(TsSyntax { tsx: transform.jsx.is_some(), ..Default::default() }),
+            false => Syntax::Es(EsSyntax { jsx: transform.jsx.is_some(), ..Default::default() }),
+        };
+
+        let mut parser = Parser::new(syntax, StringInput::from(&*file), Some(&comments));
+        let program = parser.parse_program();
+
+        // swc recovers from some errors and only reports them afterwards
+        let recovered = parser.take_errors();
+
+        let syntax_error =
+            |e: &swc_ecma_parser::error::Error| syntax_error(&location(&cm, e.span().lo), &e.kind().msg(), origin, span);
+
+        let program = match (program, recovered.first()) {
+            (Err(e), _) => return Err(syntax_error(&e)),
+            (Ok(_), Some(e)) => return Err(syntax_error(e)),
+            (Ok(program), None) => program,
+        };
+
+        let program: Program = GLOBALS.set(&Globals::new(), || {
+            let unresolved_mark = Mark::new();
+            let top_level_mark = Mark::new();
+
+            let program = program.apply(resolver(unresolved_mark, top_level_mark, transform.typescript));
+
+            #[cfg(feature = "typescript")]
+            let program = match transform.typescript {
+                true => program.apply(strip(unresolved_mark, top_level_mark)),
+                false => program,
+            };
+
+            #[cfg(feature = "jsx")]
+            let program = match &transform.jsx {
+                Some(jsx) => program.apply(react(&cm, &comments, jsx, unresolved_mark, top_level_mark)),
+                None => program,
+            };
+
+            #[cfg(feature = "transpile")]
+            let program = match transform.target {
+                Some(target) => transpile(program, target, &comments, unresolved_mark),
+                None => program,
+            };
+
+            program.apply(hygiene()).apply(fixer(Some(&comments)))
+        });
+
+        // not everything can be transpiled, e.g. regular expressions with newer flags
+        #[cfg(feature = "transpile")]
+        if let Some(target) = transform.target {
+            if let Some(v) = crate::es_version::check(&program, target) {
+                let position = match v.span.is_dummy() {
+                    true => String::new(),
+                    false => format!(" at {}", location(&cm, v.span.lo)),
+                };
+
+                return Err(syn::Error::new(
+                    span,
+                    format!(
+                        "'{}' can't be transpiled to {}, {} introduced in {} remains{}",
+                        origin,
+                        crate::es_version::display(target),
+                        v.feature,
+                        crate::es_version::display(v.version),
+                        position
+                    ),
+                ));
+            }
+        }
+
+        let mut code = Vec::new();
+        let mut mappings = Vec::new();
+
+        Emitter {
+            cfg: Default::default(),
+            cm: cm.clone(),
+            comments: Some(&comments),
+            wr: JsWriter::new(cm.clone(), "\n", &mut code, source_map.then_some(&mut mappings)),
+        }
+        .emit_program(&program)
+        .expect("writing to a Vec can't fail");
+
+        let map = source_map.then(|| {
+            let mut json = Vec::new();
+            cm.build_source_map(&mappings, None, MapConfig)
+                .to_writer(&mut json)
+                .expect("writing to a Vec can't fail");
+            FileMap::from_json(String::from_utf8(json).expect("source maps are JSON"))
+        });
+
+        Ok((String::from_utf8(code).expect("swc emits UTF-8"), map))
+    }
+
+    /// The pass that removes the types from TypeScript
+    #[cfg(feature = "typescript")]
+    fn strip(unresolved_mark: Mark, top_level_mark: Mark) -> impl swc_ecma_ast::Pass {
+        // by default swc adds `export {}` to files whose imports were all removed, which would turn scripts into modules
+        let config = swc_ecma_transforms_typescript::Config { no_empty_export: true, ..Default::default() };
+        swc_ecma_transforms_typescript::typescript(config, unresolved_mark, top_level_mark)
+    }
+
+    /// The pass that compiles JSX to calls of the pragmas in `jsx`
+    #[cfg(feature = "jsx")]
+    fn react<'a>(
+        cm: &Lrc<SourceMap>,
+        comments: &'a SingleThreadedComments,
+        jsx: &super::Jsx,
+        unresolved_mark: Mark,
+        top_level_mark: Mark,
+    ) -> impl 'a + swc_ecma_ast::Pass {
+        use swc_ecma_transforms_react::{Options, Runtime};
+
+        let options = Options {
+            runtime: Some(Runtime::Classic),
+            pragma: jsx.pragma.clone().map(Into::into),
+            pragma_frag: jsx.pragma_frag.clone().map(Into::into),
+            ..Default::default()
+        };
+
+        swc_ecma_transforms_react::jsx(cm.clone(), Some(comments), options, top_level_mark, unresolved_mark)
+    }
+
+    /// Transpiles everything in `program` that is newer than the ECMAScript version `target`.
+    /// The helpers the transformations need are put into the code.
+    #[cfg(feature = "transpile")]
+    fn transpile(program: Program, target: u16, comments: &SingleThreadedComments, unresolved_mark: Mark) -> Program {
+        use swc_ecma_transforms_base::helpers::{inject_helpers, Helpers, HELPERS};
+        use swc_ecma_transforms_compat::{es2015, es2016, es2017, es2018, es2019, es2020, es2021, es2022};
+
+        HELPERS.set(&Helpers::new(false), || {
+            // like babel, from the newest version to the oldest, as e.g. the ES2015 transformations
+            // can't handle the syntax of later versions
+            let mut program = program;
+
+            if target < 2022 {
+                program = program.apply(es2022(Default::default(), unresolved_mark));
+            }
+            if target < 2021 {
+                program = program.apply(es2021());
+            }
+            if target < 2020 {
+                program = program.apply(es2020(Default::default(), unresolved_mark));
+            }
+            if target < 2019 {
+                program = program.apply(es2019());
+            }
+            if target < 2018 {
+                program = program.apply(es2018(Default::default()));
+            }
+            if target < 2017 {
+                program = program.apply(es2017(Default::default(), unresolved_mark));
+            }
+            if target < 2016 {
+                program = program.apply(es2016());
+            }
+            if target < 2015 {
+                program = program.apply(es2015(unresolved_mark, Some(comments), Default::default()));
+            }
+
+            program.apply(inject_helpers(unresolved_mark))
+        })
+    }
+}
@@ -0,0 +1,240 @@
+//! A sanity check of Javascript that can be evaluated in `const` contexts.
+//!
+//! None of the parsers can run at compiletime without the proc macros, so this only checks that brackets are
+//! balanced and that strings, template literals, regular expressions and comments are terminated. Code that
+//! passes the check is not necessarily valid, but most mistakes in hand-written constants are caught by it.
+
+use core::fmt;
+
+/// How deeply brackets and template literals can be nested in code that is checked with `check_balanced`
+const MAX_DEPTH: usize = 256;
+
+/// Error returned by `check_balanced` for code whose brackets or literals are not balanced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unbalanced {
+    /// What is wrong with the code
+    pub message: &'static str,
+    /// The byte offset in the code at which the problem was found
+    pub offset: usize,
+}
+
+impl fmt::Display for Unbalanced {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl core::error::Error for Unbalanced {}
+
+const fn unbalanced(message: &'static str, offset: usize) -> Result<(), Unbalanced> {
+    Err(Unbalanced { message, offset })
+}
+
+pub(crate) const fn is_ident(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80
+}
+
+/// Whether the word that ends at `end` is a keyword after which a `/` starts a regular expression
+pub(crate) const fn is_keyword_before_regex(code: &[u8], end: usize) -> bool {
+    const KEYWORDS: [&str; 13] =
+        ["return", "typeof", "instanceof", "in", "of", "new", "delete", "void", "throw", "case", "do", "else", "yield"];
+
+    let mut start = end;
+    while start > 0 && is_ident(code[start - 1]) {
+        start -= 1;
+    }
+
+    let mut k = 0;
+    while k < KEYWORDS.len() {
+        let keyword = KEYWORDS[k].as_bytes();
+
+        if keyword.len() == end - start {
+            let mut i = 0;
+            while i < keyword.len() && keyword[i] == code[start + i] {
+                i += 1;
+            }
+
+            if i == keyword.len() {
+                return true;
+            }
+        }
+        k += 1;
+    }
+
+    false
+}
+
+/// Checks that the brackets in `js` are balanced and that its strings, template literals, regular expressions and
+/// comments are terminated. This can be evaluated at compiletime, so constants can be checked without the proc
+/// macros, but it is only a sanity check and not a substitute for parsing, e.g. `f(;)` passes it.
+/// Whether a `/` starts a regular expression is guessed from the code in front of it.
+///
+/// # Examples
+///
+/// ```rust
+/// use include_js_core::{check_balanced, JSStr};
+///
+/// const CODE: &str = "function f(a) { return `${a[0]}}` + /[)]/.source; }";
+/// const _: () = assert!(check_balanced(CODE).is_ok());
+///
+/// // SAFETY: the code is valid, the check above only catches mistakes when it is changed
+/// const JS: &JSStr = unsafe { JSStr::new_unchecked(CODE) };
+///
+/// let e = check_balanced("if (x) { f(x]; }").unwrap_err();
+/// assert_eq!(e.message, "mismatched closing bracket");
+/// assert_eq!(e.offset, 12);
+/// assert_eq!(check_balanced("'unterminated").unwrap_err().message, "unterminated string literal");
+/// ```
+///
+/// ```compile_fail
+/// use include_js_core::check_balanced;
+///
+/// const _: () = assert!(check_balanced("function f() { return 1;").is_ok());
+/// ```
+pub const fn check_balanced(js: &str) -> Result<(), Unbalanced> {
+    let code = js.as_bytes();
+
+    // the open brackets, with `$` for the `${` of a template literal and '`' for the text of one
+    let mut stack = [0u8; MAX_DEPTH];
+    let mut opened = [0usize; MAX_DEPTH];
+    let mut depth = 0;
+
+    // the last byte that is not whitespace or part of a comment, to tell regular expressions from divisions
+    let mut prev = 0u8;
+    let mut prev_end = 0;
+    let mut i = 0;
+
+    while i < code.len() {
+        let b = code[i];
+
+        // the text of a template literal, until its end or the next substitution
+        if depth > 0 && stack[depth - 1] == b'`' {
+            if b == b'\\' {
+                i += 2;
+                continue;
+            } else if b == b'`' {
+                depth -= 1;
+                prev = b'`';
+                prev_end = i + 1;
+            } else if b == b'$' && i + 1 < code.len() && code[i + 1] == b'{' {
+                if depth == MAX_DEPTH {
+                    return unbalanced("brackets nested too deeply", i);
+                }
+                stack[depth] = b'$';
+                opened[depth] = i;
+                depth += 1;
+                prev = b'{';
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        match b {
+            b'(' | b'[' | b'{' | b'`' => {
+                if depth == MAX_DEPTH {
+                    return unbalanced("brackets nested too deeply", i);
+                }
+                stack[depth] = b;
+                opened[depth] = i;
+                depth += 1;
+            },
+            b')' | b']' | b'}' => {
+                if depth == 0 {
+                    return unbalanced("unmatched closing bracket", i);
+                }
+
+                let open = stack[depth - 1];
+                let matches = matches!((open, b), (b'(', b')') | (b'[', b']') | (b'{', b'}') | (b'$', b'}'));
+                if !matches {
+                    return unbalanced("mismatched closing bracket", i);
+                }
+                depth -= 1;
+            },
+            b'"' | b'\'' => {
+                let start = i;
+                i += 1;
+                while i < code.len() && code[i] != b {
+                    if code[i] == b'\n' {
+                        return unbalanced("unterminated string literal", start);
+                    } else if code[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+
+                if i >= code.len() {
+                    return unbalanced("unterminated string literal", start);
+                }
+            },
+            b'/' if i + 1 < code.len() && code[i + 1] == b'/' => {
+                while i < code.len() && code[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            },
+            b'/' if i + 1 < code.len() && code[i + 1] == b'*' => {
+                let start = i;
+                i += 2;
+                while i + 1 < code.len() && !(code[i] == b'*' && code[i + 1] == b'/') {
+                    i += 1;
+                }
+
+                if i + 1 >= code.len() {
+                    return unbalanced("unterminated comment", start);
+                }
+                i += 2;
+                continue;
+            },
+            b'/' => {
+                let regex = match prev {
+                    0 | b'(' | b',' | b'=' | b':' | b'[' | b'!' | b'&' | b'|' | b'?' | b'{' | b'}' | b';' | b'+'
+                    | b'-' | b'*' | b'%' | b'<' | b'>' | b'~' | b'^' => true,
+                    prev => is_ident(prev) && is_keyword_before_regex(code, prev_end),
+                };
+
+                if regex {
+                    let start = i;
+                    let mut class = false;
+                    i += 1;
+                    while i < code.len() && (class || code[i] != b'/') {
+                        match code[i] {
+                            b'\n' => return unbalanced("unterminated regular expression", start),
+                            b'\\' => i += 1,
+                            b'[' => class = true,
+                            b']' => class = false,
+                            _ => {},
+                        }
+                        i += 1;
+                    }
+
+                    if i >= code.len() {
+                        return unbalanced("unterminated regular expression", start);
+                    }
+
+                    // the flags are an identifier, which is not a keyword in front of a regular expression
+                    prev = b'/';
+                    prev_end = i + 1;
+                    i += 1;
+                    continue;
+                }
+            },
+            _ => {},
+        }
+
+        if !b.is_ascii_whitespace() {
+            prev = b;
+            prev_end = i + 1;
+        }
+        i += 1;
+    }
+
+    if depth > 0 {
+        return match stack[depth - 1] {
+            b'`' => unbalanced("unterminated template literal", opened[depth - 1]),
+            _ => unbalanced("unclosed bracket", opened[depth - 1]),
+        };
+    }
+
+    Ok(())
+}
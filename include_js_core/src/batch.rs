@@ -0,0 +1,142 @@
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::iter::FromIterator;
+
+use crate::{JSString, JSTemplate, RenderError};
+
+/// Renders many templates, possibly of different types, into a single script.
+/// The rendered templates are joined with `;\n`, so every template ends up as its own statement.
+///
+/// # Examples
+///
+/// ```rust
+/// use include_js_core::{JSString, JSTemplate, RenderError, ScriptBatch};
+///
+/// struct Alert(&'static str);
+///
+/// impl JSTemplate for Alert {
+///     fn try_render_template(&self) -> Result<JSString, RenderError> {
+///         Ok(unsafe { JSString::new_unchecked(format!("alert({})", JSString::quote(self.0).as_str())) })
+///     }
+/// }
+///
+/// struct Reload;
+///
+/// impl JSTemplate for Reload {
+///     fn try_render_template(&self) -> Result<JSString, RenderError> {
+///         Ok(unsafe { JSString::new_unchecked("location.reload()".to_owned()) })
+///     }
+/// }
+///
+/// let commands: Vec<Box<dyn JSTemplate>> = vec![Box::new(Alert("bye")), Box::new(Reload)];
+///
+/// let mut batch = ScriptBatch::new();
+/// batch.extend(commands);
+/// # #[cfg(feature = "__validation")]
+/// assert_eq!(batch.render().as_str(), "alert(\"bye\");\nlocation.reload()");
+///
+/// // SAFETY: the templates don't declare anything
+/// let js = unsafe { batch.try_render_unchecked() }.unwrap();
+/// assert_eq!(js.as_str(), "alert(\"bye\");\nlocation.reload()");
+/// ```
+#[derive(Default)]
+pub struct ScriptBatch<'a> {
+    templates: Vec<Box<dyn JSTemplate + 'a>>,
+}
+
+impl<'a> ScriptBatch<'a> {
+    pub fn new() -> Self {
+        ScriptBatch { templates: Vec::new() }
+    }
+
+    /// Adds `template` to the end of the batch, references to templates can be added as well.
+    pub fn push(&mut self, template: impl JSTemplate + 'a) {
+        self.templates.push(Box::new(template));
+    }
+
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    /// Renders all templates into one script, which is validated once more, as joining valid scripts does not
+    /// necessarily result in one. Only available with runtime validation, i.e. the `validator-boa` or
+    /// `validator-ressa` feature, see `try_render_unchecked` otherwise. Only ressa reports names that are declared
+    /// twice at the top level, boa accepts them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::{JSString, JSTemplate, RenderError, ScriptBatch};
+    ///
+    /// struct Declare;
+    ///
+    /// impl JSTemplate for Declare {
+    ///     fn try_render_template(&self) -> Result<JSString, RenderError> {
+    ///         Ok(unsafe { JSString::new_unchecked("let x = 1".to_owned()) })
+    ///     }
+    /// }
+    ///
+    /// // `x` is declared twice
+    /// let batch: ScriptBatch = vec![Declare, Declare].into_iter().collect();
+    /// # #[cfg(feature = "validator-ressa")]
+    /// assert!(matches!(batch.try_render(), Err(RenderError::Syntax(_))));
+    /// ```
+    #[cfg(feature = "__validation")]
+    pub fn try_render(&self) -> Result<JSString, RenderError> {
+        JSString::new(self.join()?).map_err(RenderError::Syntax)
+    }
+
+    /// Renders all templates into one script.
+    ///
+    /// # Panics
+    /// Panics if `try_render` returns an error.
+    #[cfg(feature = "__validation")]
+    pub fn render(&self) -> JSString {
+        match self.try_render() {
+            Ok(js) => js,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Renders all templates into one script without validating it.
+    ///
+    /// # Safety
+    /// The joined script must be syntactically valid Javascript. As every template is valid on its own and becomes
+    /// its own statement, that is the case unless two templates declare the same name at the top level and one of
+    /// the declarations is a `let`, `const` or `class`, or a template other than the first starts with a `#!` line.
+    pub unsafe fn try_render_unchecked(&self) -> Result<JSString, RenderError> {
+        Ok(JSString::new_unchecked(self.join()?))
+    }
+
+    /// The rendered templates joined with `;\n`
+    fn join(&self) -> Result<String, RenderError> {
+        let mut script = String::new();
+
+        for (i, template) in self.templates.iter().enumerate() {
+            if i > 0 {
+                script.push_str(";\n");
+            }
+
+            template.try_render_into(&mut script)?;
+        }
+
+        Ok(script)
+    }
+}
+
+impl<'a, T: JSTemplate + 'a> Extend<T> for ScriptBatch<'a> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.templates.extend(iter.into_iter().map(|t| Box::new(t) as Box<dyn JSTemplate + 'a>));
+    }
+}
+
+impl<'a, T: JSTemplate + 'a> FromIterator<T> for ScriptBatch<'a> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut batch = ScriptBatch::new();
+        batch.extend(iter);
+        batch
+    }
+}
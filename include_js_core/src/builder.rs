@@ -0,0 +1,78 @@
+use crate::{end_statement, JSStr, JSString, JSTemplate, RenderError};
+
+/// Builds a script from fragments that are validated together once the script is finished,
+/// instead of validating the script after every fragment like `JSString::push_statement`.
+///
+/// Raw fragments are appended as they are, so they may be incomplete like the start of a function that a later
+/// raw fragment ends. All other fragments are appended as separate statements, the code before them is terminated
+/// with `;` if it isn't already.
+///
+/// # Examples
+///
+/// ```rust
+/// use include_js_core::{JsBuilder, JSStr};
+///
+/// let js = JsBuilder::new()
+///     .raw("(function() {\n")
+///     .js(JSStr::new("let n = 0").unwrap())
+///     .stmt("n += 1")
+///     .raw("\n})();")
+///     .finish()
+///     .unwrap();
+///
+/// assert_eq!(js, "(function() {\nlet n = 0;\nn += 1\n})();");
+///
+/// assert!(JsBuilder::new().raw("(function() {").stmt("init()").finish().is_err());
+/// ```
+#[derive(Default)]
+pub struct JsBuilder {
+    code: String,
+    /// the first template that failed to render, the remaining fragments are ignored
+    error: Option<RenderError>,
+}
+
+impl JsBuilder {
+    pub fn new() -> Self {
+        JsBuilder::default()
+    }
+
+    /// Appends `code` as it is.
+    pub fn raw(mut self, code: &str) -> Self {
+        self.code.push_str(code);
+        self
+    }
+
+    /// Appends `js` as a separate statement.
+    pub fn js(self, js: &JSStr) -> Self {
+        self.stmt(js.as_str())
+    }
+
+    /// Appends the code `stmt` as a separate statement.
+    pub fn stmt(mut self, stmt: &str) -> Self {
+        end_statement(&mut self.code);
+        self.code.push_str(stmt);
+        self
+    }
+
+    /// Renders `template` into the script as a separate statement.
+    /// If rendering fails, `finish` returns the error.
+    pub fn template(mut self, template: &impl JSTemplate) -> Self {
+        if self.error.is_none() {
+            end_statement(&mut self.code);
+
+            if let Err(e) = template.try_render_into(&mut self.code) {
+                self.error = Some(e);
+            }
+        }
+
+        self
+    }
+
+    /// Checks the script and returns it, or the error of the first template that failed to render.
+    pub fn finish(self) -> Result<JSString, RenderError> {
+        match self.error {
+            Some(e) => Err(e),
+            None => JSString::new(self.code).map_err(RenderError::Syntax),
+        }
+    }
+}
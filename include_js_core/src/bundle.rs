@@ -0,0 +1,267 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    module::{self, ItemKind, ModuleItem},
+    validator, Error, JSString,
+};
+
+/// Error returned by `bundle`, `E` is the error type of the loader.
+#[derive(Debug)]
+pub enum BundleError<E> {
+    /// The loader failed to load an imported module
+    Load(E),
+    /// A module is not syntactically valid
+    Syntax { module: String, error: Error },
+    /// A module uses a construct that can't be bundled
+    Unsupported { module: String, message: &'static str },
+    /// The modules import each other in a cycle, given as the ids of the modules in it
+    Cycle(Vec<String>),
+}
+
+impl<E: fmt::Display> fmt::Display for BundleError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::Load(e) => write!(f, "could not load module: {}", e),
+            BundleError::Syntax { module, error } => write!(f, "syntax error in '{}': {}", module, error),
+            BundleError::Unsupported { module, message } => write!(f, "can't bundle '{}': {}", module, message),
+            BundleError::Cycle(ids) => write!(f, "import cycle: {}", ids.join(" -> ")),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for BundleError<E> {}
+
+struct Module {
+    id: String,
+    src: String,
+    items: Vec<ModuleItem>,
+    /// the index of the module for every specifier imported by this one
+    dependencies: HashMap<String, usize>,
+}
+
+struct Bundler<L> {
+    load: L,
+    modules: Vec<Module>,
+    /// the index of every module that was already loaded, by id
+    loaded: HashMap<String, usize>,
+    /// the ids of the modules that are currently being loaded, to detect cycles
+    stack: Vec<String>,
+}
+
+impl<L, E> Bundler<L>
+where
+    L: FnMut(&str, &str) -> Result<(String, String), E>,
+{
+    /// Adds the module and everything it imports, dependencies end up before the modules importing them.
+    fn add(&mut self, id: String, src: String) -> Result<usize, BundleError<E>> {
+        let syntax = |e| BundleError::Syntax { module: id.clone(), error: Error::new(&src, e) };
+        let script = module::to_script_goal(&src).map_err(syntax)?;
+        validator::parse_script(&script).map_err(syntax)?;
+
+        let items = module::module_items(&src).map_err(syntax)?;
+        self.stack.push(id.clone());
+
+        let mut dependencies = HashMap::new();
+        for item in &items {
+            let specifier = match &item.kind {
+                ItemKind::Import { specifier, .. } | ItemKind::ExportFrom { specifier, .. } | ItemKind::ExportAll { specifier } => {
+                    specifier
+                },
+                _ => continue,
+            };
+
+            if dependencies.contains_key(specifier) {
+                continue;
+            }
+
+            let (dep_id, dep_src) = (self.load)(&id, specifier).map_err(BundleError::Load)?;
+
+            if let Some(cycle_start) = self.stack.iter().position(|s| s == &dep_id) {
+                let mut cycle = self.stack[cycle_start..].to_vec();
+                cycle.push(dep_id);
+                return Err(BundleError::Cycle(cycle));
+            }
+
+            let index = match self.loaded.get(&dep_id) {
+                Some(&index) => index,
+                None => self.add(dep_id, dep_src)?,
+            };
+
+            dependencies.insert(specifier.clone(), index);
+        }
+
+        self.stack.pop();
+        self.modules.push(Module { id: id.clone(), src, items, dependencies });
+        self.loaded.insert(id, self.modules.len() - 1);
+
+        Ok(self.modules.len() - 1)
+    }
+}
+
+/// The name of the variable holding the exports of the module with `index`
+fn exports_var(index: usize) -> String {
+    format!("$module{}", index)
+}
+
+/// Property access of `name` on `object`, `name` is the source text of the name so it may be a quoted string
+fn property(object: &str, name: &str) -> String {
+    if name.starts_with(['"', '\'']) {
+        format!("{}[{}]", object, name)
+    } else {
+        format!("{}.{}", object, name)
+    }
+}
+
+/// Property key for `name` in an assignment to `$exports[...]`
+fn key(name: &str) -> String {
+    if name.starts_with(['"', '\'']) {
+        name.to_owned()
+    } else {
+        format!("\"{}\"", name)
+    }
+}
+
+/// Rewrites `module` into a script, for every module but the entry the script is the body of a function
+/// that returns an object with the exports of the module.
+fn rewrite<E>(module: &Module, is_entry: bool) -> Result<String, BundleError<E>> {
+    let unsupported = |message| BundleError::Unsupported { module: module.id.clone(), message };
+    let dependency = |specifier: &String| exports_var(module.dependencies[specifier]);
+
+    let mut imports = String::new();
+    let mut body = module.src.as_bytes().to_vec();
+    let mut replacements = Vec::new();
+    let mut exports = Vec::new();
+    let mut star_exports = Vec::new();
+
+    for item in &module.items {
+        module::mask_range(&mut body, item.start, item.end);
+
+        match &item.kind {
+            // imports are hoisted, so their bindings are initialized before the rest of the module
+            ItemKind::Import { specifier, bindings } => {
+                for (imported, local) in bindings {
+                    let value = match imported.as_str() {
+                        "*" => dependency(specifier),
+                        imported => property(&dependency(specifier), imported),
+                    };
+                    imports.push_str(&format!("const {} = {};\n", local, value));
+                }
+            },
+            ItemKind::ExportList(names) => {
+                exports.extend(names.iter().map(|(local, exported)| (exported.clone(), local.clone())));
+            },
+            ItemKind::ExportFrom { specifier, names } => {
+                for (imported, exported) in names {
+                    let value = match imported.as_str() {
+                        "*" => dependency(specifier),
+                        imported => property(&dependency(specifier), imported),
+                    };
+                    exports.push((exported.clone(), value));
+                }
+            },
+            ItemKind::ExportAll { specifier } => star_exports.push(dependency(specifier)),
+            ItemKind::ExportDeclaration(names) => {
+                let names = names.as_ref().ok_or_else(|| unsupported("exported declarations have to declare plain identifiers"))?;
+                exports.extend(names.iter().map(|name| (name.clone(), name.clone())));
+            },
+            ItemKind::ExportDefaultExpression if is_entry => replacements.push((item.start, "void".to_owned())),
+            ItemKind::ExportDefaultExpression => {
+                replacements.push((item.start, "const $default =".to_owned()));
+                exports.push(("default".to_owned(), "$default".to_owned()));
+            },
+            ItemKind::ExportDefaultDeclaration(name) => exports.push(("default".to_owned(), name.clone())),
+            ItemKind::ExportDefaultAnonymous(keywords) => {
+                replacements.push((item.start, format!("{} $default", keywords)));
+                exports.push(("default".to_owned(), "$default".to_owned()));
+            },
+            ItemKind::DynamicImport => {
+                // keep the `import` that was just masked
+                body[item.start..item.end].copy_from_slice(b"import");
+            },
+        }
+    }
+
+    // the replacements can be longer than the masked ranges, so they are spliced in back to front
+    // SAFETY: masking replaces every byte of a range with ASCII, so `body` is still UTF-8
+    let mut body = unsafe { String::from_utf8_unchecked(body) };
+    for (start, replacement) in replacements.into_iter().rev() {
+        body.insert_str(start, &replacement);
+    }
+
+    let mut script = imports;
+    script.push_str(&body);
+
+    if !is_entry {
+        script.push_str("\nvar $exports = {};\n");
+        for dependency in star_exports {
+            script.push_str(&format!(
+                "for (var $key in {0}) if ($key !== \"default\") $exports[$key] = {0}[$key];\n",
+                dependency
+            ));
+        }
+        for (exported, value) in exports {
+            script.push_str(&format!("$exports[{}] = {};\n", key(&exported), value));
+        }
+        script.push_str("return $exports;\n");
+    }
+
+    Ok(script)
+}
+
+/// Bundles the module `entry` and all modules it imports into a single script.
+///
+/// `load` is called with the id of the importing module and the specifier of the import and returns the id
+/// and the source of the imported module. Modules with the same id are only included once.
+/// Every imported module is wrapped in a function that is called once, before the modules importing it,
+/// and returns an object with its exports. The entry module itself is not wrapped and its exports are dropped.
+///
+/// Unlike real modules, imported bindings are not live: they are copied when the imported module
+/// has finished executing. Imports can't be cyclic and exported declarations can't use destructuring.
+///
+/// # Examples
+///
+/// ```rust
+/// use include_js_core::bundle;
+///
+/// let main = "import { square } from './math.js';\nconsole.log(square(4));";
+///
+/// let js = bundle("main.js", main, |_importer, specifier| match specifier {
+///     "./math.js" => Ok(("math.js".to_owned(), "export function square(x) { return x * x; }".to_owned())),
+///     _ => Err(format!("unknown module {}", specifier)),
+/// })
+/// .unwrap();
+///
+/// assert!(js.as_str().starts_with("const $module0 = (function () {"));
+/// ```
+pub fn bundle<L, E>(entry: &str, src: &str, load: L) -> Result<JSString, BundleError<E>>
+where
+    L: FnMut(&str, &str) -> Result<(String, String), E>,
+{
+    let mut bundler = Bundler {
+        load,
+        modules: Vec::new(),
+        loaded: HashMap::new(),
+        stack: Vec::new(),
+    };
+
+    bundler.add(entry.to_owned(), src.to_owned())?;
+
+    let (entry, dependencies) = bundler.modules.split_last().expect("the entry module was just added");
+    let mut code = String::new();
+
+    for (index, module) in dependencies.iter().enumerate() {
+        code.push_str(&format!("const {} = (function () {{\n", exports_var(index)));
+        code.push_str(&rewrite(module, false)?);
+        code.push_str("})();\n");
+    }
+
+    code.push_str(&rewrite(entry, true)?);
+
+    // the rewritten modules only contain script syntax, so the result is a valid script if they were valid modules
+    let syntax = |e| BundleError::Syntax { module: entry.id.clone(), error: Error::new(&code, e) };
+    let script = module::to_script_goal(&code).map_err(syntax)?;
+    validator::parse_script(&script).map_err(syntax)?;
+
+    // SAFETY: checked right above
+    Ok(unsafe { JSString::new_unchecked(code) })
+}
@@ -0,0 +1,45 @@
+//! Conversions for running Javascript in a deno_core `JsRuntime`.
+//!
+//! `&'static JSStr` and `JSString` convert into `FastString`, which is also `ModuleCodeString`,
+//! without copying the code, so they can be passed to every `JsRuntime` method that takes source code.
+
+use deno_core::{error::JsError, v8, FastString, JsRuntime};
+
+use crate::{JSStr, JSString};
+
+impl From<&'static JSStr> for FastString {
+    fn from(js: &'static JSStr) -> Self {
+        FastString::from_static(js.as_str())
+    }
+}
+
+impl From<JSString> for FastString {
+    fn from(js: JSString) -> Self {
+        FastString::from(js.code)
+    }
+}
+
+/// Extension methods for `JsRuntime` that take Javascript that is known to be valid.
+pub trait JsRuntimeExt {
+    /// Executes `js` as a classic script in the main realm, like `JsRuntime::execute_script`.
+    /// `name` is the file name shown in stack traces.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use deno_core::{JsRuntime, RuntimeOptions};
+    /// use include_js_core::{JSStr, JsRuntimeExt};
+    ///
+    /// let mut runtime = JsRuntime::new(RuntimeOptions::default());
+    /// let js = JSStr::new("globalThis.answer = 42;").unwrap();
+    ///
+    /// runtime.execute_js("answer.js", js).unwrap();
+    /// ```
+    fn execute_js(&mut self, name: &'static str, js: &JSStr) -> Result<v8::Global<v8::Value>, Box<JsError>>;
+}
+
+impl JsRuntimeExt for JsRuntime {
+    fn execute_js(&mut self, name: &'static str, js: &JSStr) -> Result<v8::Global<v8::Value>, Box<JsError>> {
+        self.execute_script(name, js.as_str().to_owned())
+    }
+}
@@ -0,0 +1,218 @@
+use std::fmt;
+
+#[cfg(all(feature = "validator-boa", not(feature = "validator-ressa")))]
+use boa::syntax::{lexer, parser};
+
+/// The kind of a `ParseError`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// A token other than the expected ones was found
+    Expected,
+    /// A token was found where none was allowed
+    Unexpected,
+    /// The code ended in the middle of a construct
+    UnexpectedEnd,
+    /// The code contains characters that don't form a valid token, e.g. an unterminated string literal
+    Lexical,
+    /// Any other syntax error
+    General,
+    /// The code uses syntax the parser does not support yet
+    Unsupported,
+}
+
+/// A syntax error found by the parser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl ParseError {
+    /// An error of the kind `General` at byte `offset` of `source`, for the checks that are not done by the parser
+    pub(crate) fn general(source: &str, message: impl Into<String>, offset: usize) -> Self {
+        let (line, column) = position_of(source, offset);
+        ParseError { kind: ParseErrorKind::General, line, column, message: message.into() }
+    }
+
+    /// Converts an error of boa, `source` is the code that was parsed and used to locate errors without a position.
+    #[cfg(all(feature = "validator-boa", not(feature = "validator-ressa")))]
+    pub(crate) fn from_boa(source: &str, e: parser::ParseError) -> Self {
+        let (kind, position) = match &e {
+            parser::ParseError::Expected { found, .. } => (ParseErrorKind::Expected, Some(found.span().start())),
+            parser::ParseError::Unexpected { found, .. } => (ParseErrorKind::Unexpected, Some(found.span().start())),
+            parser::ParseError::AbruptEnd => (ParseErrorKind::UnexpectedEnd, None),
+            parser::ParseError::Lex { err: lexer::Error::Syntax(_, position) } => (ParseErrorKind::Lexical, Some(*position)),
+            parser::ParseError::Lex { .. } => (ParseErrorKind::Lexical, None),
+            parser::ParseError::General { position, .. } => (ParseErrorKind::General, Some(*position)),
+            parser::ParseError::Unimplemented { position, .. } => (ParseErrorKind::Unsupported, Some(*position)),
+        };
+
+        // errors without a position happen at the end of the input
+        let (line, column) = match position {
+            Some(position) => (position.line_number() as usize, position.column_number() as usize),
+            None => position_of(source, source.len()),
+        };
+
+        // boa appends the position to its messages, it is kept separately here
+        let message = match e {
+            parser::ParseError::AbruptEnd => "unexpected end of input".to_owned(),
+            parser::ParseError::Lex { err: lexer::Error::Syntax(message, _) } => message.into_string(),
+            parser::ParseError::Lex { err: lexer::Error::IO(e) } => e.to_string(),
+            e => {
+                let message = e.to_string();
+                let suffix = format!(" at line {}, col {}", line, column);
+                message.strip_suffix(&suffix).map(str::to_owned).unwrap_or(message)
+            },
+        };
+
+        ParseError { kind, line, column, message }
+    }
+
+    /// Converts an error of ressa, `source` is the code that was parsed and used to locate errors without a position.
+    #[cfg(feature = "validator-ressa")]
+    pub(crate) fn from_ressa(source: &str, e: ressa::Error) -> Self {
+        use ressa::Error as E;
+
+        let kind = match &e {
+            E::UnexpectedToken(..) | E::InvalidStartOfExpressionStmt(..) => ParseErrorKind::Unexpected,
+            E::UnexpectedEoF | E::ParseAfterEoF => ParseErrorKind::UnexpectedEnd,
+            E::Scanner(_) | E::InvalidEscape(..) | E::InvalidRegEx(..) | E::OctalLiteral(_) => ParseErrorKind::Lexical,
+            _ => ParseErrorKind::General,
+        };
+
+        let (line, column) = match e.position() {
+            Some(position) => (position.line, position.column),
+            None => position_of(source, source.len()),
+        };
+
+        // ressa puts the position into the middle of its messages, so they are made up here where it does
+        let message = match e {
+            E::UnexpectedEoF | E::ParseAfterEoF => "unexpected end of input".to_owned(),
+            E::UnexpectedToken(_, message) => message,
+            E::Scanner(e) => e.msg,
+            e => e.to_string(),
+        };
+
+        ParseError { kind, line, column, message }
+    }
+
+    /// What went wrong
+    pub fn kind(&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    /// The line of the error, starting at 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column of the error in characters, starting at 1
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// The description of the error, without its position
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}, col {}", self.message, self.line, self.column)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// The line and column of byte `offset` in `src`, both starting at 1 and the column in characters
+pub(crate) fn position_of(src: &str, offset: usize) -> (usize, usize) {
+    let before = &src[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+
+    (line, column)
+}
+
+/// Error returned when code is not syntactically valid Javascript.
+///
+/// Besides the `ParseError` it keeps the offending source, so it can be displayed
+/// with a code frame that points at the error.
+///
+/// # Examples
+///
+/// ```rust
+/// use include_js_core::{JSStr, ParseErrorKind};
+///
+/// let error = JSStr::new("let a = 1;\nlet b = ;").err().unwrap();
+///
+/// assert_eq!((error.line(), error.column()), (2, 9));
+/// assert_eq!(error.parse_error().kind(), ParseErrorKind::Unexpected);
+/// assert!(error.to_string().ends_with("2 | let b = ;\n  |         ^"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Error {
+    source: String,
+    parse_error: ParseError,
+}
+
+impl Error {
+    pub(crate) fn new(source: &str, parse_error: ParseError) -> Self {
+        Error { source: source.to_owned(), parse_error }
+    }
+
+    /// The code that failed to parse
+    pub fn source_code(&self) -> &str {
+        &self.source
+    }
+
+    /// The line of the error, starting at 1
+    pub fn line(&self) -> usize {
+        self.parse_error.line
+    }
+
+    /// The column of the error in characters, starting at 1
+    pub fn column(&self) -> usize {
+        self.parse_error.column
+    }
+
+    /// The error of the parser
+    pub fn parse_error(&self) -> &ParseError {
+        &self.parse_error
+    }
+
+    /// Converts the error into the error of the parser
+    pub fn into_parse_error(self) -> ParseError {
+        self.parse_error
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.parse_error)?;
+
+        let code = match self.source.split('\n').nth(self.line() - 1) {
+            Some(code) => code.trim_end_matches('\r'),
+            None => return Ok(()),
+        };
+
+        // the caret is indented with the whitespace of the line so it lines up even if the line contains tabs
+        let indent: String = code
+            .chars()
+            .take(self.column() - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let width = self.line().to_string().len();
+        write!(f, "\n{:w$} |\n{} | {}\n{:w$} | {}^", "", self.line(), code, "", indent, w = width)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.parse_error)
+    }
+}
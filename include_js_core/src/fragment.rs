@@ -0,0 +1,341 @@
+//! Fragments of Javascript that are not whole scripts: single expressions and single statements.
+//!
+//! A `JSStr` can't be put just anywhere, e.g. `1; 2` is a valid script but not a valid function argument.
+//! An expression can be put into parentheses and used wherever a value is expected, so expressions compose
+//! without being parsed again. Every statement is also a script, so a `JSStmt` dereferences to `JSStr`.
+//! A `JSFunction` is an expression that evaluates to a function, which can be called with Rust values.
+
+use alloc::{borrow::ToOwned, format, string::String, vec::Vec};
+#[cfg(feature = "__validation")]
+use core::convert::TryFrom;
+use core::{fmt, marker::PhantomData, ops::Deref};
+
+#[cfg(feature = "__validation")]
+use crate::{module, statements, validator, Error, ParseError};
+use crate::{JSStr, JSString, ToJsArgs};
+
+/// Wrapper around `str` that ensures it contains a single, _syntactically_ valid Javascript expression,
+/// e.g. `1 + 2`, `{ width: 800 }` or `function (x) { return x; }`.
+#[repr(transparent)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JSExpr {
+    data: str,
+}
+
+/// Wrapper around `str` that ensures it contains a single, _syntactically_ valid Javascript statement,
+/// e.g. `let x = 1;` or `if (x) { f(); }`.
+#[repr(transparent)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JSStmt {
+    data: str,
+}
+
+/// An expression that evaluates to a function taking the arguments `Args`, a tuple of `ToJsLiteral` values,
+/// e.g. `(width, name) => resize(width, name)`. The arguments are only a promise about what the function expects,
+/// Javascript doesn't check them, but they make calls from Rust typed. Dereferences to the `JSExpr`.
+///
+/// # Examples
+///
+/// ```rust
+/// use include_js_core::JSFunction;
+///
+/// let resize: &JSFunction<(u32, String)> = unsafe { JSFunction::new_unchecked("(width, name) => resize(name, width)") };
+/// assert_eq!(resize.call_with((800, "main".into())), r#"((width, name) => resize(name, width))(800, "main");"#);
+/// ```
+#[repr(transparent)]
+pub struct JSFunction<Args> {
+    args: PhantomData<fn(Args)>,
+    code: JSExpr,
+}
+
+/// The error for `src` that is not the kind of fragment it should be, with `message` at byte `offset`
+#[cfg(feature = "__validation")]
+fn fragment_error(src: &str, message: &'static str, offset: usize) -> Error {
+    Error::new(src, ParseError::general(src, message, offset))
+}
+
+/// `expr` in parentheses, with the closing one on a new line if the last line could end in a comment
+fn parenthesized(expr: &str) -> String {
+    let last_line = expr.rsplit('\n').next().unwrap_or_default();
+
+    match ["//", "<!--", "-->"].iter().any(|comment| last_line.contains(comment)) {
+        true => format!("({}\n)", expr),
+        false => format!("({})", expr),
+    }
+}
+
+/// `expr` as an operand, in parentheses unless it is an identifier, a number or a chain of property accesses
+fn operand(expr: &str) -> String {
+    let simple = !expr.is_empty() && expr.bytes().all(|b| b.is_ascii_alphanumeric() || b"_$.".contains(&b));
+
+    match simple {
+        true => expr.to_owned(),
+        false => parenthesized(expr),
+    }
+}
+
+impl JSExpr {
+    /// Checks if `js` is a single syntactically valid expression before coersing it to `&JSExpr`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSExpr;
+    ///
+    /// assert!(JSExpr::new("{ width: 800, height: 600 }").is_ok());
+    /// assert!(JSExpr::new("1; 2").is_err());
+    ///
+    /// // valid in parentheses, but not an expression
+    /// assert_eq!(JSExpr::new("a)(b").err().unwrap().parse_error().message(), "unmatched closing bracket");
+    /// ```
+    #[cfg(feature = "__validation")]
+    pub fn new(js: &str) -> Result<&Self, Error> {
+        let tokens = module::tokenize(js).map_err(|e| Error::new(js, e))?;
+
+        // a closing bracket without an opening one could end the parentheses the expression is checked in
+        let mut depth = 0usize;
+        for token in &tokens {
+            match token.kind {
+                module::TokenKind::Punct(b'(' | b'[' | b'{') => depth += 1,
+                module::TokenKind::Punct(b')' | b']' | b'}') => match depth.checked_sub(1) {
+                    Some(outer) => depth = outer,
+                    None => return Err(fragment_error(js, "unmatched closing bracket", token.start)),
+                },
+                _ => (),
+            }
+        }
+
+        if tokens.is_empty() {
+            return Err(fragment_error(js, "expected an expression", js.len()));
+        }
+
+        if validator::parse_script(&format!("({}\n)", js)).is_err() {
+            // the error of the code on its own has the right position, unless the code is a valid statement
+            return Err(match validator::parse_script(js) {
+                Err(e) => Error::new(js, e),
+                Ok(_) => fragment_error(js, "expected an expression", tokens[0].start),
+            });
+        }
+
+        // SAFETY: follows from safety of `new_unchecked` and from the lines above
+        Ok(unsafe { JSExpr::new_unchecked(js) })
+    }
+
+    /// Coerses `js` directly into a `&JSExpr` without checking for validity
+    ///
+    /// # Safety
+    /// `js` must be a single syntactically valid Javascript expression
+    pub const unsafe fn new_unchecked(js: &str) -> &Self {
+        // SAFETY: JSExpr is repr(transparent) and contains `str` so transmuting from &str to &JSExpr is safe
+        core::mem::transmute(js)
+    }
+
+    /// Converts the `&JSExpr` back into an `&str`, this should be a noop.
+    pub fn as_str(&self) -> &str {
+        &self.data
+    }
+
+    /// The expression as an expression statement, in parentheses so an object or function is not parsed
+    /// as a block or declaration.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSExpr;
+    ///
+    /// let expr = unsafe { JSExpr::new_unchecked("{ width: 800 }") };
+    /// assert_eq!(expr.to_statement(), "({ width: 800 });");
+    /// ```
+    pub fn to_statement(&self) -> JSString {
+        // SAFETY: an expression in parentheses is a valid expression statement
+        unsafe { JSString::new_unchecked(format!("{};", parenthesized(&self.data))) }
+    }
+
+    /// A statement that calls the expression with `args`. Operands that aren't identifiers, numbers or property
+    /// accesses are put into parentheses, so e.g. `a, b` is a single argument.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSExpr;
+    ///
+    /// let f = unsafe { JSExpr::new_unchecked("global.move_window") };
+    /// let args = unsafe { [JSExpr::new_unchecked("800"), JSExpr::new_unchecked("'main' + suffix")] };
+    /// assert_eq!(f.call(&args), "global.move_window(800, ('main' + suffix));");
+    /// ```
+    pub fn call(&self, args: &[&JSExpr]) -> JSString {
+        let args: Vec<_> = args.iter().map(|arg| operand(&arg.data)).collect();
+
+        // SAFETY: the callee and the arguments are valid expressions that can't be split or merged
+        unsafe { JSString::new_unchecked(format!("{}({});", operand(&self.data), args.join(", "))) }
+    }
+}
+
+impl JSStmt {
+    /// Checks if `js` is a single syntactically valid statement before coersing it to `&JSStmt`.
+    /// Empty statements, i.e. lone `;`, are not counted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSStmt;
+    ///
+    /// assert!(JSStmt::new("if (x) { f(); g(); }").is_ok());
+    /// assert!(JSStmt::new("f();;").is_ok());
+    ///
+    /// let error = JSStmt::new("let x = 1;\nf(x);").err().unwrap();
+    /// assert_eq!((error.line(), error.column()), (2, 1));
+    /// ```
+    #[cfg(feature = "__validation")]
+    pub fn new(js: &str) -> Result<&Self, Error> {
+        let statements = validator::parse_script(js).map_err(|e| Error::new(js, e))?;
+
+        match statements.len() {
+            1 => (),
+            0 => return Err(fragment_error(js, "expected a statement", js.len())),
+            _ => {
+                let second = statements::statements(js).get(1).map_or(0, |stmt| stmt.as_ptr() as usize - js.as_ptr() as usize);
+                return Err(fragment_error(js, "expected a single statement", second));
+            },
+        }
+
+        // SAFETY: follows from safety of `new_unchecked` and from the lines above
+        Ok(unsafe { JSStmt::new_unchecked(js) })
+    }
+
+    /// Coerses `js` directly into a `&JSStmt` without checking for validity
+    ///
+    /// # Safety
+    /// `js` must be a single syntactically valid Javascript statement
+    pub const unsafe fn new_unchecked(js: &str) -> &Self {
+        // SAFETY: JSStmt is repr(transparent) and contains `str` so transmuting from &str to &JSStmt is safe
+        core::mem::transmute(js)
+    }
+
+    /// Converts the `&JSStmt` back into an `&str`, this should be a noop.
+    pub fn as_str(&self) -> &str {
+        &self.data
+    }
+}
+
+impl<Args> JSFunction<Args> {
+    /// Checks if `js` is a single syntactically valid expression before coersing it to `&JSFunction`.
+    /// Whether it evaluates to a function that takes `Args` can't be checked.
+    #[cfg(feature = "__validation")]
+    pub fn new(js: &str) -> Result<&Self, Error> {
+        JSExpr::new(js)?;
+
+        // SAFETY: follows from safety of `new_unchecked` and from the line above
+        Ok(unsafe { JSFunction::new_unchecked(js) })
+    }
+
+    /// Coerses `js` directly into a `&JSFunction` without checking for validity
+    ///
+    /// # Safety
+    /// `js` must be a single syntactically valid Javascript expression
+    pub const unsafe fn new_unchecked(js: &str) -> &Self {
+        // SAFETY: JSFunction is repr(transparent) and contains `JSExpr`, which contains `str`
+        &*(js as *const str as *const JSFunction<Args>)
+    }
+
+    /// A statement that calls the function with `args`, which are converted with `ToJsLiteral`
+    pub fn call_with(&self, args: Args) -> JSString
+    where
+        Args: ToJsArgs,
+    {
+        // literals are never comma expressions, so they don't need parentheses
+        let args: Vec<_> = args.to_js_args().into_iter().map(String::from).collect();
+
+        // SAFETY: the function and the arguments are valid expressions that can't be split or merged
+        unsafe { JSString::new_unchecked(format!("{}({});", operand(self.as_str()), args.join(", "))) }
+    }
+}
+
+impl<Args> Deref for JSFunction<Args> {
+    type Target = JSExpr;
+
+    fn deref(&self) -> &Self::Target {
+        &self.code
+    }
+}
+
+impl<Args> fmt::Debug for JSFunction<Args> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.code, f)
+    }
+}
+
+impl<Args> fmt::Display for JSFunction<Args> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.code, f)
+    }
+}
+
+impl Deref for JSStmt {
+    type Target = JSStr;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: a statement is a valid script
+        unsafe { JSStr::new_unchecked(&self.data) }
+    }
+}
+
+impl AsRef<JSStr> for JSStmt {
+    fn as_ref(&self) -> &JSStr {
+        self
+    }
+}
+
+impl AsRef<str> for JSExpr {
+    fn as_ref(&self) -> &str {
+        &self.data
+    }
+}
+
+impl AsRef<str> for JSStmt {
+    fn as_ref(&self) -> &str {
+        &self.data
+    }
+}
+
+#[cfg(feature = "__validation")]
+impl<'a> TryFrom<&'a str> for &'a JSExpr {
+    type Error = Error;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        JSExpr::new(value)
+    }
+}
+
+#[cfg(feature = "__validation")]
+impl<'a> TryFrom<&'a str> for &'a JSStmt {
+    type Error = Error;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        JSStmt::new(value)
+    }
+}
+
+impl fmt::Debug for JSExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.data, f)
+    }
+}
+
+impl fmt::Display for JSExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.data, f)
+    }
+}
+
+impl fmt::Debug for JSStmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.data, f)
+    }
+}
+
+impl fmt::Display for JSStmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.data, f)
+    }
+}
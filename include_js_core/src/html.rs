@@ -0,0 +1,199 @@
+//! Escaping of Javascript for the content of a `<script>` element.
+//!
+//! The HTML parser ends the element at the first `</script` followed by whitespace, `/` or `>`, and after `<!--`
+//! a `<script` makes it skip the next `</script>`. It doesn't know about Javascript, so these sequences are changed
+//! wherever they are, without changing what the code means. In string, template and regex literals and in comments
+//! the `<` is replaced with `\x3C`, which means the same there. Outside of them a `<` followed by `script` can
+//! only be the less-than operator, so a space after it is enough, and the `<!--` comment of scripts becomes `//--`.
+//! Whether a `/` starts a regular expression is guessed like in `check_balanced`.
+
+use alloc::{string::String, vec::Vec};
+
+use crate::balance::{is_ident, is_keyword_before_regex};
+
+/// Whether the code at the start of `rest` is `<script` or `</script` as the HTML parser sees it as a tag,
+/// i.e. followed by whitespace, `/`, `>` or the end of the code
+fn is_script_tag(rest: &[u8]) -> bool {
+    let rest = rest.strip_prefix(b"</").or_else(|| rest.strip_prefix(b"<")).unwrap_or(rest);
+
+    rest.len() >= 6
+        && rest[..6].eq_ignore_ascii_case(b"script")
+        && rest.get(6).is_none_or(|&b| matches!(b, b'\t' | b'\n' | b'\x0c' | b'\r' | b' ' | b'/' | b'>'))
+}
+
+/// Whether the code at the start of `rest` is one of the sequences that are escaped in literals and comments.
+/// Every `<script` and `</script` is escaped there, as it is just as valid and the rule for tags is easy to get wrong.
+fn is_markup(rest: &[u8]) -> bool {
+    rest.starts_with(b"<!--")
+        || [&b"</script"[..], b"<script"]
+            .iter()
+            .any(|seq| rest.get(..seq.len()).is_some_and(|start| start.eq_ignore_ascii_case(seq)))
+}
+
+struct Escaper<'a> {
+    code: &'a str,
+    out: String,
+    /// the end of the code that was already copied to `out`
+    copied: usize,
+}
+
+impl Escaper<'_> {
+    /// Escapes the `<` at `i` if it starts one of the sequences, `literal` is whether it is in a literal or comment
+    fn less_than(&mut self, i: usize, literal: bool) {
+        let rest = &self.code.as_bytes()[i..];
+
+        let replacement = match literal {
+            true if is_markup(rest) => "\\x3C",
+            false if is_script_tag(rest) => "< ",
+            _ => return,
+        };
+
+        self.out.push_str(&self.code[self.copied..i]);
+        self.out.push_str(replacement);
+        self.copied = i + 1;
+    }
+
+    /// Escapes the `<` in the literal or comment from `start` up to `end`
+    fn literal(&mut self, start: usize, end: usize) {
+        for i in start..end {
+            if self.code.as_bytes()[i] == b'<' {
+                self.less_than(i, true);
+            }
+        }
+    }
+}
+
+/// Escapes `code` so the HTML parser finds the end of a `<script>` element with it as its content
+pub(crate) fn escape_script(code: &str) -> String {
+    let bytes = code.as_bytes();
+    let mut escaper = Escaper { code, out: String::with_capacity(code.len()), copied: 0 };
+
+    // the open braces, with `$` for the `${` of a template literal
+    let mut braces = Vec::new();
+    // the last byte that is not whitespace or part of a comment, to tell regular expressions from divisions
+    let mut prev = 0u8;
+    let mut prev_end = 0;
+    let mut i = 0;
+
+    // the text of a template literal up to its end or the next substitution, returns where the code continues
+    let template = |escaper: &mut Escaper, braces: &mut Vec<u8>, start: usize| {
+        let mut i = start;
+        while i < bytes.len() && bytes[i] != b'`' && !(bytes[i] == b'$' && bytes.get(i + 1) == Some(&b'{')) {
+            i += if bytes[i] == b'\\' { 2 } else { 1 };
+        }
+
+        let i = i.min(bytes.len());
+        escaper.literal(start, i);
+
+        match bytes.get(i) {
+            Some(b'$') => {
+                braces.push(b'$');
+                i + 2
+            },
+            _ => i + 1,
+        }
+    };
+
+    while i < bytes.len() {
+        let b = bytes[i];
+
+        match b {
+            b'"' | b'\'' => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i] != b && bytes[i] != b'\n' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                escaper.literal(start, i.min(bytes.len()));
+            },
+            b'`' => {
+                i = template(&mut escaper, &mut braces, i + 1);
+                prev = b'`';
+                prev_end = i;
+                continue;
+            },
+            b'{' => braces.push(b'{'),
+            b'}' if braces.last() == Some(&b'$') => {
+                braces.pop();
+                i = template(&mut escaper, &mut braces, i + 1);
+                prev = b'`';
+                prev_end = i;
+                continue;
+            },
+            b'}' => {
+                braces.pop();
+            },
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                let start = i;
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                escaper.literal(start, i);
+                continue;
+            },
+            // `<!--` starts a comment to the end of the line in scripts, like `//`
+            b'<' if bytes[i..].starts_with(b"<!--") => {
+                // `//--` is the same comment, but doesn't start an HTML comment
+                escaper.out.push_str(&code[escaper.copied..i]);
+                escaper.out.push_str("//");
+                escaper.copied = i + 2;
+
+                let start = i + 4;
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                escaper.literal(start, i);
+                continue;
+            },
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let start = i;
+                i += 2;
+                while i + 1 < bytes.len() && !(bytes[i] == b'*' && bytes[i + 1] == b'/') {
+                    i += 1;
+                }
+                i = (i + 2).min(bytes.len());
+                escaper.literal(start, i);
+                continue;
+            },
+            b'/' => {
+                let regex = match prev {
+                    0 | b'(' | b',' | b'=' | b':' | b'[' | b'!' | b'&' | b'|' | b'?' | b'{' | b'}' | b';' | b'+'
+                    | b'-' | b'*' | b'%' | b'<' | b'>' | b'~' | b'^' => true,
+                    prev => is_ident(prev) && is_keyword_before_regex(bytes, prev_end),
+                };
+
+                if regex {
+                    let start = i;
+                    let mut class = false;
+                    i += 1;
+                    while i < bytes.len() && bytes[i] != b'\n' && (class || bytes[i] != b'/') {
+                        match bytes[i] {
+                            b'\\' => i += 1,
+                            b'[' => class = true,
+                            b']' => class = false,
+                            _ => {},
+                        }
+                        i += 1;
+                    }
+                    escaper.literal(start, i.min(bytes.len()));
+
+                    prev = b'/';
+                    prev_end = i + 1;
+                    i += 1;
+                    continue;
+                }
+            },
+            b'<' => escaper.less_than(i, false),
+            _ => {},
+        }
+
+        if !b.is_ascii_whitespace() {
+            prev = b;
+            prev_end = i + 1;
+        }
+        i += 1;
+    }
+
+    escaper.out.push_str(&code[escaper.copied..]);
+    escaper.out
+}
@@ -1,27 +1,367 @@
-#![feature(const_fn_transmute)]
+//! The types of `include_js`, which ensure that they contain syntactically valid Javascript.
+//!
+//! Without the default `std` feature the crate is `no_std` and only needs `alloc`. The types and the code that is
+//! embedded with the macros are available then, but not the features that parse or run code at runtime.
+//!
+//! Code is parsed at runtime, e.g. by `JSStr::new`, with the parser chosen by a feature: `validator-boa`, the
+//! default, or `validator-ressa`, which is much smaller to compile and link. Both check the same things, only the
+//! messages of syntax errors differ. `exec` and `ast` use boa and enable it regardless. The `runtime-validation`
+//! feature of earlier versions is the same as `validator-boa`.
 
-use std::{borrow::Borrow, ops::Deref};
-use std::convert::TryFrom;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "analysis")]
+use alloc::collections::BTreeSet;
+use alloc::{
+    borrow::{Borrow, Cow, ToOwned},
+    boxed::Box,
+    ffi::CString,
+    format,
+    rc::Rc,
+    string::String,
+    sync::Arc,
+};
+#[cfg(feature = "__validation")]
+use alloc::vec::Vec;
+use core::{fmt, ops::Deref};
+#[cfg(feature = "__validation")]
+use core::{
+    convert::TryFrom,
+    ops::{Add, AddAssign},
+    str::FromStr,
+};
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+#[cfg(all(feature = "__validation", not(any(feature = "validator-boa", feature = "validator-ressa"))))]
+compile_error!("runtime validation needs a parser, enable the `validator-boa` or the `validator-ressa` feature");
+
+mod balance;
+mod batch;
+#[cfg(feature = "__validation")]
+mod builder;
+#[cfg(feature = "__validation")]
+mod bundle;
+#[cfg(feature = "__validation")]
+mod error;
+#[cfg(feature = "deno")]
+mod deno;
+mod fragment;
+mod html;
+mod literal;
+#[cfg(feature = "__validation")]
+mod module;
+
+#[cfg(feature = "analysis")]
+mod scope;
+#[cfg(feature = "serde")]
+mod ser;
+#[cfg(feature = "__validation")]
+mod statements;
+#[cfg(feature = "__validation")]
+mod validator;
+#[cfg(feature = "serde")]
+mod serde_impls;
+
+pub use balance::{check_balanced, Unbalanced};
+pub use batch::ScriptBatch;
+pub use fragment::{JSExpr, JSFunction, JSStmt};
+pub use literal::{ToJsArgs, ToJsLiteral};
+
+/// The paths the code generated by the macros uses, so it works in `no_std` crates as well. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::string::String;
+}
+
+#[cfg(feature = "__validation")]
+pub use builder::JsBuilder;
+#[cfg(feature = "__validation")]
+pub use bundle::{bundle, BundleError};
+#[cfg(feature = "__validation")]
+pub use error::{Error, ParseError, ParseErrorKind};
+
+#[cfg(feature = "serde")]
+pub use ser::{to_js_value, SerializeError};
+
+#[cfg(any(feature = "exec", feature = "ast"))]
+pub use boa;
+
+#[cfg(feature = "quickjs")]
+pub use rquickjs;
+
+#[cfg(feature = "wasm")]
+pub use js_sys;
+
+#[cfg(feature = "deno")]
+pub use deno::JsRuntimeExt;
 
-pub type JSParseError = boa::syntax::parser::ParseError;
 
 /// Wrapper around `str` that ensures it contains _syntactically_ valid Javascript.
 /// This is the borrowed version of `JSString` so `&JSStr` is to `JSString` what `&str` is to `String`
 #[repr(transparent)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct JSStr {
     data: str,
 }
 
+/// A whole Javascript program, as opposed to a single `JSExpr` or `JSStmt`.
+/// Any sequence of statements is a program, so this is just another name for `JSStr`.
+pub type JSProgram = JSStr;
+
 /// Wrapper around `String` that ensures it contains _syntactically_ valid Javascript.
 /// See docs for `JSStr` for more info.
+///
+/// Both types compare, order and hash like the code they contain and can be compared to strings.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::collections::HashSet;
+/// use include_js_core::JSString;
+///
+/// let js = JSString::quote("hi");
+/// assert_eq!(js, "\"hi\"");
+/// assert_eq!(js.to_string(), "\"hi\"");
+/// assert_eq!(format!("{:?}", js), r#""\"hi\"""#);
+///
+/// let scripts: HashSet<JSString> = vec![js.clone(), js].into_iter().collect();
+/// assert_eq!(scripts.len(), 1);
+/// ```
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct JSString {
     code: String,
 }
 
+/// Wrapper around `str` that ensures it contains a _syntactically_ valid Javascript module,
+/// i.e. code that is parsed with the module goal and may contain `import` and `export` declarations.
+/// A module is not necessarily a valid script, which is why this is a separate type from `JSStr`.
+#[repr(transparent)]
+pub struct JSModule {
+    data: str,
+}
+
+/// An export of a module, see `JSModule::exports`.
+/// The names are borrowed for exports known at compiletime, e.g. the ones `include_js_module!` emits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ExportInfo {
+    /// An export with the given name
+    Named(Cow<'static, str>),
+    /// The default export
+    Default,
+    /// `export * from "specifier"` with the specifier, which re-exports all named exports of that module
+    All(Cow<'static, str>),
+}
+
+/// Javascript together with its subresource integrity hashes, as created by `include_js_with_integrity!`.
+/// The hashes are formatted like the `integrity` attribute of a `<script>` element expects them,
+/// e.g. `sha384-<base64 digest>`, and are computed over exactly the bytes of `code`.
+#[derive(Clone, Copy)]
+pub struct JSWithIntegrity {
+    pub code: &'static JSStr,
+    pub sha256: &'static str,
+    pub sha384: &'static str,
+}
+
+/// Javascript together with its source map, as created by `include_js_with_map!`.
+/// The source map is JSON and maps `code` to the files it was created from, e.g. the TypeScript it was compiled from.
+#[derive(Clone, Copy)]
+pub struct JSWithSourceMap {
+    pub code: &'static JSStr,
+    pub source_map: &'static str,
+}
+
+/// Javascript that is put together from its parts the first time it is used, as created by `include_js_lazy!`.
+/// Dereferences to `JSStr`, the code is concatenated once and lives as long as the `LazyJSStr`,
+/// so it is meant to be kept in a `static` rather than a `const`.
+#[cfg(feature = "std")]
+pub struct LazyJSStr {
+    parts: &'static [&'static str],
+    code: OnceLock<String>,
+}
+
+/// Error returned when rendering a `JSTemplate` fails.
+/// `Syntax` only exists with a validator, so matches on it need a wildcard arm.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum RenderError {
+    /// The template engine could not render the template
+    Template(Box<dyn core::error::Error + Send + Sync>),
+    /// The rendered template is not syntactically valid Javascript
+    #[cfg(feature = "__validation")]
+    Syntax(Error),
+    /// The rendered template could not be written, see `JSTemplate::try_render_to`
+    Write(fmt::Error),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Template(e) => write!(f, "error rendering template: {}", e),
+            #[cfg(feature = "__validation")]
+            RenderError::Syntax(e) => write!(f, "rendered template is not valid javascript: {}", e),
+            RenderError::Write(e) => write!(f, "error writing rendered template: {}", e),
+        }
+    }
+}
+
+impl core::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            RenderError::Template(e) => Some(&**e),
+            #[cfg(feature = "__validation")]
+            RenderError::Syntax(e) => Some(e),
+            RenderError::Write(e) => Some(e),
+        }
+    }
+}
+
+/// Error returned by the `build` method of a builder generated with `#[include_js(builder)]`,
+/// if a field without a default was not set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingField {
+    /// The name of the template struct
+    pub template: &'static str,
+    /// The name of the field that was not set
+    pub field: &'static str,
+}
+
+impl fmt::Display for MissingField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "missing field `{}` of `{}`", self.field, self.template)
+    }
+}
+
+impl core::error::Error for MissingField {}
+
+/// A template that renders to Javascript, usually implemented with `#[derive(JSTemplate)]`.
+///
+/// It is also implemented for references and smart pointers to templates,
+/// so they can be passed to code that is generic over `impl JSTemplate` as well.
+/// The trait is object safe, templates of different types can be rendered together as `dyn JSTemplate`,
+/// see `ScriptBatch`.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::rc::Rc;
+/// use include_js_core::{JSString, JSTemplate, RenderError};
+///
+/// struct Alert(&'static str);
+///
+/// impl JSTemplate for Alert {
+///     fn try_render_template(&self) -> Result<JSString, RenderError> {
+///         Ok(unsafe { JSString::new_unchecked(format!("alert({});", JSString::quote(self.0).as_str())) })
+///     }
+/// }
+///
+/// fn render_all<T: JSTemplate>(templates: &[T]) -> String {
+///     let mut script = String::new();
+///     for template in templates {
+///         template.render_into(&mut script);
+///     }
+///     script
+/// }
+///
+/// let alert = Alert("hello");
+/// assert_eq!(render_all(&[&alert, &alert]), "alert(\"hello\");alert(\"hello\");");
+/// assert_eq!(render_all(&[Box::new(Alert("a"))]), render_all(&[Rc::new(Alert("a"))]));
+/// ```
 pub trait JSTemplate {
-    fn render_template(&self) -> JSString;
+    /// Renders the template, returning an error instead of panicking if that fails.
+    fn try_render_template(&self) -> Result<JSString, RenderError>;
+
+    /// Renders the template.
+    ///
+    /// # Panics
+    /// Panics if `try_render_template` returns an error.
+    fn render_template(&self) -> JSString {
+        match self.try_render_template() {
+            Ok(js) => js,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Renders the template by appending it to `buf`, so the buffer can be reused for many renders.
+    /// Returns the appended Javascript, `buf` is left as it was if rendering fails.
+    ///
+    /// The default implementation appends the result of `try_render_template`,
+    /// the derived implementations render into `buf` directly.
+    fn try_render_into<'b>(&self, buf: &'b mut String) -> Result<&'b JSStr, RenderError> {
+        let js = self.try_render_template()?;
+        let start = buf.len();
+        buf.push_str(js.as_str());
+
+        // SAFETY: the appended part is exactly `js`
+        Ok(unsafe { JSStr::new_unchecked(&buf[start..]) })
+    }
+
+    /// Renders the template by appending it to `buf`.
+    ///
+    /// # Panics
+    /// Panics if `try_render_into` returns an error.
+    fn render_into<'b>(&self, buf: &'b mut String) -> &'b JSStr {
+        match self.try_render_into(buf) {
+            Ok(js) => js,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Renders the template and writes it to `w`, e.g. a `fmt::Formatter`.
+    fn try_render_to<W: fmt::Write + ?Sized>(&self, w: &mut W) -> Result<(), RenderError>
+    where
+        Self: Sized,
+    {
+        let mut buf = String::new();
+        let js = self.try_render_into(&mut buf)?;
+        w.write_str(js.as_str()).map_err(RenderError::Write)
+    }
+
+    /// Renders the template and writes it to `w`.
+    ///
+    /// # Panics
+    /// Panics if `try_render_to` returns an error.
+    fn render_to<W: fmt::Write + ?Sized>(&self, w: &mut W)
+    where
+        Self: Sized,
+    {
+        if let Err(e) = self.try_render_to(w) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Renders the template and evaluates it in the QuickJS context `ctx`, see `JSStr::eval_quickjs`.
+    ///
+    /// # Panics
+    /// Panics if `try_render_template` returns an error.
+    #[cfg(feature = "quickjs")]
+    fn render_and_eval<'js, V: rquickjs::FromJs<'js>>(&self, ctx: &rquickjs::Ctx<'js>) -> rquickjs::Result<V>
+    where
+        Self: Sized,
+    {
+        self.render_template().eval_quickjs(ctx)
+    }
 }
 
+macro_rules! impl_template_deref {
+    ($($t:ty),*) => {
+        $(impl<T: JSTemplate + ?Sized> JSTemplate for $t {
+            fn try_render_template(&self) -> Result<JSString, RenderError> {
+                (**self).try_render_template()
+            }
+
+            // forwarded as well so the derived implementation is used instead of the default one
+            fn try_render_into<'b>(&self, buf: &'b mut String) -> Result<&'b JSStr, RenderError> {
+                (**self).try_render_into(buf)
+            }
+        })*
+    };
+}
+
+impl_template_deref!(&T, Box<T>, Rc<T>, Arc<T>);
+
 
 impl JSStr {
     /// Checks if the content of `js` is syntactically valid Javascript before
@@ -30,45 +370,258 @@ impl JSStr {
     /// # Examples
     ///
     /// ```rust
-    /// use include_js::JSStr;
+    /// use include_js_core::JSStr;
     /// 
     /// let js_str = JSStr::new("function f() {}");
     /// assert!(js_str.is_ok());
     /// ```
     /// 
     /// ```rust
-    /// use include_js::JSStr;
+    /// use include_js_core::JSStr;
     ///
     /// let js_str = JSStr::new("#include <vector>");
     /// assert!(js_str.is_err());
     /// ```
-    pub fn new(js: &str) -> Result<&Self, JSParseError> {
-        let _ = boa::parse(js, false)?;
+    #[cfg(feature = "__validation")]
+    pub fn new(js: &str) -> Result<&Self, Error> {
+        validator::parse_script(js).map_err(|e| Error::new(js, e))?;
 
         // SAFETY: follows from safety of `new_unchecked` and from the line above
         Ok(unsafe { JSStr::new_unchecked(js) })
     }
 
+    /// Concatenates `parts` without anything in between and checks the result once.
+    /// Valid parts don't necessarily result in valid Javascript, e.g. `f()` and `g()` concatenate to `f()g()`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSStr;
+    ///
+    /// let parts = [JSStr::new("let x = 1;\n").unwrap(), JSStr::new("x += 1;").unwrap()];
+    /// assert_eq!(JSStr::concat(&parts).unwrap(), "let x = 1;\nx += 1;");
+    /// ```
+    #[cfg(feature = "__validation")]
+    pub fn concat(parts: &[&JSStr]) -> Result<JSString, Error> {
+        let code: String = parts.iter().map(|js| js.as_str()).collect();
+        JSString::new(code)
+    }
+
+    /// Returns the top level statements of the code, without the whitespace and comments between them.
+    /// The statements are found with the parser, so `;` in literals or nested functions don't split the code.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSStr;
+    ///
+    /// let js = JSStr::new("let s = 'a; b';\nfunction f() { return 1; }\n// comment\nf()\n(s)").unwrap();
+    /// let statements: Vec<&str> = js.statements().map(JSStr::as_str).collect();
+    /// assert_eq!(statements, ["let s = 'a; b';", "function f() { return 1; }", "f()\n(s)"]);
+    /// ```
+    #[cfg(feature = "__validation")]
+    pub fn statements(&self) -> impl Iterator<Item = &JSStr> {
+        // SAFETY: `statements::statements` parses every statement it returns
+        statements::statements(&self.data).into_iter().map(|stmt| unsafe { JSStr::new_unchecked(stmt) })
+    }
+
+    /// Parses the code into boa's syntax tree, e.g. to analyze it.
+    /// The code is parsed again, as `&JSStr` has nowhere to keep the result of the validation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::{boa::syntax::ast::Node, JSStr};
+    ///
+    /// let js = JSStr::new("function f() {}\nfunction g() {}\nf();").unwrap();
+    /// let functions = js.ast().items().iter().filter(|node| matches!(node, Node::FunctionDecl(_))).count();
+    /// assert_eq!(functions, 2);
+    /// ```
+    #[cfg(feature = "ast")]
+    pub fn ast(&self) -> boa::syntax::ast::node::StatementList {
+        boa::parse(&self.data, false).expect("JSStr contains valid javascript")
+    }
+
+    /// Returns the identifiers the code references without declaring them, i.e. the globals it expects to exist.
+    /// Builtins like `undefined` or `Math` are included as well.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSStr;
+    ///
+    /// let js = JSStr::new("let x = Meta.get(global);\nfunction f(y) { return x + y + arguments.length; }").unwrap();
+    /// assert_eq!(js.free_variables().into_iter().collect::<Vec<_>>(), ["Meta", "global"]);
+    /// ```
+    #[cfg(feature = "analysis")]
+    pub fn free_variables(&self) -> BTreeSet<String> {
+        scope::free_variables(&self.data, false).expect("JSStr contains valid javascript")
+    }
+
     /// Coerses `js` directly into a `&JSStr` without checking for validity
+    ///
+    /// # Safety
+    /// `js` must be syntactically valid Javascript
     pub const unsafe fn new_unchecked(js: &str) -> &Self {
         // SAFETY: JSStr is repr(transparent) and contains `str` so transmuting from &str to &JSStr is safe
-        std::mem::transmute(js)
+        core::mem::transmute(js)
     }
 
     /// Converts the `&JSStr` back into an `&str`, this should be a noop.
     pub fn as_str(&self) -> &str {
         &self.data
     }
+
+    /// Runs the code in a fresh boa context and returns the value of the last statement.
+    /// Exceptions thrown by the code are returned as the error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSStr;
+    ///
+    /// let js = JSStr::new("[1, 2, 3].map(x => x * 2).join()").unwrap();
+    /// assert_eq!(js.eval().unwrap().as_string().unwrap().as_str(), "2,4,6");
+    /// ```
+    #[cfg(feature = "exec")]
+    pub fn eval(&self) -> boa::Result<boa::Value> {
+        self.eval_in(&mut boa::Context::new())
+    }
+
+    /// Runs the code in `context`, e.g. to keep global variables between scripts or to provide native functions.
+    #[cfg(feature = "exec")]
+    pub fn eval_in(&self, context: &mut boa::Context) -> boa::Result<boa::Value> {
+        context.eval(&self.data)
+    }
+
+    /// Evaluates the code in the QuickJS context `ctx` and converts the value of the last statement to `V`.
+    /// If the code throws, the error is `rquickjs::Error::Exception` and the thrown value can be retrieved with `ctx.catch()`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::{rquickjs::{Context, Runtime}, JSStr};
+    ///
+    /// let js = JSStr::new("[1, 2, 3].map(x => x * 2).join()").unwrap();
+    ///
+    /// let runtime = Runtime::new().unwrap();
+    /// let context = Context::full(&runtime).unwrap();
+    /// let result: String = context.with(|ctx| js.eval_quickjs(&ctx)).unwrap();
+    ///
+    /// assert_eq!(result, "2,4,6");
+    /// ```
+    #[cfg(feature = "quickjs")]
+    pub fn eval_quickjs<'js, V: rquickjs::FromJs<'js>>(&self, ctx: &rquickjs::Ctx<'js>) -> rquickjs::Result<V> {
+        ctx.eval(self.data.as_bytes())
+    }
+
+    /// Evaluates the code with the global `eval` of the Javascript host, only works on wasm targets.
+    /// Returns the value of the last statement or the thrown value if the code throws.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use include_js_core::JSStr;
+    ///
+    /// let js = JSStr::new("document.title").unwrap();
+    /// let title = js.eval_js_sys().unwrap();
+    /// ```
+    #[cfg(feature = "wasm")]
+    pub fn eval_js_sys(&self) -> Result<js_sys::wasm_bindgen::JsValue, js_sys::wasm_bindgen::JsValue> {
+        js_sys::eval(&self.data)
+    }
+
+    /// Copies the code into a nul-terminated C string, e.g. for `JSStringCreateWithUTF8CString` of JavaScriptCore.
+    /// Nul characters in literals are escaped as `\x00`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSStr;
+    ///
+    /// // SAFETY: valid Javascript
+    /// let js = unsafe { JSStr::new_unchecked("'a\0b'.length") };
+    /// assert_eq!(js.to_cstring().to_str().unwrap(), "'a\\x00b'.length");
+    /// ```
+    pub fn to_cstring(&self) -> CString {
+        nul_free(self.data.to_owned())
+    }
+
+    /// Wraps the code in a `<script>` element with the attributes `attrs` so it can be embedded into HTML.
+    ///
+    /// The HTML parser ends the element at the first `</script` and treats `<!--` and `<script` specially,
+    /// regardless of whether they are in a string or a comment. In string, template and regex literals and in
+    /// comments the `<` of these sequences is replaced with `\x3C`, which means the same there. Outside of them
+    /// `<script` and `</script` can only be a less-than operator, which gets a space after it, and the `<!--` comment
+    /// of scripts becomes `//--`. The result means the same as the code. The attribute values are escaped, the names
+    /// are written as is, so they must be valid attribute names.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSStr;
+    ///
+    /// // SAFETY: valid Javascript
+    /// let js = unsafe { JSStr::new_unchecked(r#"document.write("</script><script>alert(1)</script>");"#) };
+    ///
+    /// assert_eq!(
+    ///     js.to_script_tag(&[("nonce", "\"abc\"")]),
+    ///     r#"<script nonce="&quot;abc&quot;">document.write("\x3C/script>\x3Cscript>alert(1)\x3C/script>");</script>"#,
+    /// );
+    ///
+    /// // SAFETY: valid Javascript
+    /// let js = unsafe { JSStr::new_unchecked("for (var i=0;i<scripts.length;i++){} if (n<script/2) f(`</script>`);") };
+    ///
+    /// assert_eq!(
+    ///     js.to_script_tag(&[]),
+    ///     "<script>for (var i=0;i<scripts.length;i++){} if (n< script/2) f(`\\x3C/script>`);</script>",
+    /// );
+    /// ```
+    pub fn to_script_tag(&self, attrs: &[(&str, &str)]) -> String {
+        let mut html = String::with_capacity(self.data.len() + 17);
+        html.push_str("<script");
+
+        for (name, value) in attrs {
+            html.push(' ');
+            html.push_str(name);
+            html.push_str("=\"");
+            for c in value.chars() {
+                match c {
+                    '&' => html.push_str("&amp;"),
+                    '"' => html.push_str("&quot;"),
+                    '<' => html.push_str("&lt;"),
+                    '>' => html.push_str("&gt;"),
+                    c => html.push(c),
+                }
+            }
+            html.push('"');
+        }
+
+        html.push('>');
+        html.push_str(&html::escape_script(&self.data));
+        html.push_str("</script>");
+        html
+    }
 }
 
+#[cfg(feature = "__validation")]
 impl<'a> TryFrom<&'a str> for &'a JSStr {
-    type Error = JSParseError;
+    type Error = Error;
 
     fn try_from(value: &'a str) -> Result<Self, Self::Error> {
         JSStr::new(value)
     }
 }
 
+#[cfg(feature = "__validation")]
+impl<'a> TryFrom<&'a String> for &'a JSStr {
+    type Error = Error;
+
+    fn try_from(value: &'a String) -> Result<Self, Self::Error> {
+        JSStr::new(value)
+    }
+}
+
 impl AsRef<JSStr> for JSStr {
     fn as_ref(&self) -> &JSStr {
         self
@@ -81,9 +634,18 @@ impl AsRef<str> for JSStr {
     }
 }
 
-impl<'a> Into<&'a str> for &'a JSStr {
-    fn into(self) -> &'a str {
-        &self.data
+impl<'a> From<&'a JSStr> for &'a str {
+    fn from(js: &'a JSStr) -> Self {
+        &js.data
+    }
+}
+
+/// Creates a function without parameters whose body is the code, only works on wasm targets.
+/// Calling the function runs the code every time, it always returns `undefined`.
+#[cfg(feature = "wasm")]
+impl From<&JSStr> for js_sys::Function {
+    fn from(js: &JSStr) -> Self {
+        js_sys::Function::new_no_args(&js.data)
     }
 }
 
@@ -98,32 +660,321 @@ impl ToOwned for JSStr {
     }
 }
 
+#[cfg(feature = "std")]
+impl LazyJSStr {
+    /// Creates a `LazyJSStr` from its `parts` without checking the code they make up for validity
+    ///
+    /// # Safety
+    /// The concatenation of `parts` must be syntactically valid Javascript
+    pub const unsafe fn new_unchecked(parts: &'static [&'static str]) -> Self {
+        LazyJSStr { parts, code: OnceLock::new() }
+    }
+
+    /// The code, which is put together on the first call
+    pub fn get(&self) -> &JSStr {
+        let code = self.code.get_or_init(|| self.parts.concat());
+
+        // SAFETY: the concatenated parts are valid javascript, see `new_unchecked`
+        unsafe { JSStr::new_unchecked(code) }
+    }
+}
+
+impl JSModule {
+    /// Checks if the content of `js` is a syntactically valid Javascript module before
+    /// coersing it to `&JSModule`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSModule;
+    ///
+    /// let js_module = JSModule::new("import { f } from './f.js'; export default f();");
+    /// assert!(js_module.is_ok());
+    /// ```
+    ///
+    /// ```rust
+    /// use include_js_core::JSModule;
+    ///
+    /// let js_module = JSModule::new("export { f as };");
+    /// assert!(js_module.is_err());
+    /// ```
+    #[cfg(feature = "__validation")]
+    pub fn new(js: &str) -> Result<&Self, Error> {
+        // the script has the same lines and columns as `js`, so the errors are reported against `js`
+        let script = module::to_script_goal(js).map_err(|e| Error::new(js, e))?;
+        validator::parse_script(&script).map_err(|e| Error::new(js, e))?;
+
+        // SAFETY: follows from safety of `new_unchecked` and from the lines above
+        Ok(unsafe { JSModule::new_unchecked(js) })
+    }
+
+    /// Coerses `js` directly into a `&JSModule` without checking for validity
+    ///
+    /// # Safety
+    /// `js` must be a syntactically valid Javascript module
+    pub const unsafe fn new_unchecked(js: &str) -> &Self {
+        // SAFETY: JSModule is repr(transparent) and contains `str` so transmuting from &str to &JSModule is safe
+        core::mem::transmute(js)
+    }
+
+    /// Returns the exports of the module in the order they are declared in.
+    /// The names declared by destructuring, like `export const { a, b } = obj;`, are not known and left out.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::{ExportInfo, JSModule};
+    ///
+    /// let js = JSModule::new("export function f() {}\nexport { f as g };\nexport * from './h.js';\nexport default 1;").unwrap();
+    /// assert_eq!(js.exports(), [
+    ///     ExportInfo::Named("f".into()),
+    ///     ExportInfo::Named("g".into()),
+    ///     ExportInfo::All("./h.js".into()),
+    ///     ExportInfo::Default,
+    /// ]);
+    /// ```
+    #[cfg(feature = "__validation")]
+    pub fn exports(&self) -> Vec<ExportInfo> {
+        module::exports(&self.data).expect("JSModule contains a valid module")
+    }
+
+    /// Returns the identifiers the module references without declaring or importing them,
+    /// like `JSStr::free_variables` does for scripts.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSModule;
+    ///
+    /// let js = JSModule::new("import { f } from './f.js';\nexport const x = f(document);\nexport { x as y };").unwrap();
+    /// assert_eq!(js.free_variables().into_iter().collect::<Vec<_>>(), ["document"]);
+    /// ```
+    #[cfg(feature = "analysis")]
+    pub fn free_variables(&self) -> BTreeSet<String> {
+        scope::free_variables(&self.data, true).expect("JSModule contains a valid module")
+    }
+
+    /// Converts the `&JSModule` back into an `&str`, this should be a noop.
+    pub fn as_str(&self) -> &str {
+        &self.data
+    }
+}
+
+#[cfg(feature = "__validation")]
+impl<'a> TryFrom<&'a str> for &'a JSModule {
+    type Error = Error;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        JSModule::new(value)
+    }
+}
+
+impl AsRef<str> for JSModule {
+    fn as_ref(&self) -> &str {
+        &self.data
+    }
+}
+
+impl ExportInfo {
+    /// The export `name` known at compiletime, which can be created in `const` contexts unlike with `Cow`.
+    pub const fn named(name: &'static str) -> Self {
+        ExportInfo::Named(Cow::Borrowed(name))
+    }
+
+    /// `export * from "specifier"` with a specifier known at compiletime, see `named`.
+    pub const fn all(specifier: &'static str) -> Self {
+        ExportInfo::All(Cow::Borrowed(specifier))
+    }
+}
+
 impl JSString {
-    pub fn new(code: String) -> Result<Self, JSParseError> {
+    #[cfg(feature = "__validation")]
+    pub fn new(code: String) -> Result<Self, Error> {
         let _ = JSStr::new(&code)?;
         Ok(JSString{ code })
     }
 
+    /// Wraps `code` without checking for validity
+    ///
+    /// # Safety
+    /// `code` must be syntactically valid Javascript
     pub unsafe fn new_unchecked(code: String) -> Self {
         JSString{ code }
     }
 
+    /// Creates a Javascript string literal with the content `value`, escaping everything that
+    /// could end the literal early. `<` is escaped too, so that the literal can't close a surrounding
+    /// `<script>` element or open an HTML comment.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::JSString;
+    ///
+    /// let js = JSString::quote("\"); alert('pwned'); </script>\n");
+    /// assert_eq!(js.as_str(), r#""\"); alert(\'pwned\'); \x3C/script>\n""#);
+    /// ```
+    pub fn quote(value: &str) -> Self {
+        let mut code = String::with_capacity(value.len() + 2);
+        code.push('"');
+
+        for c in value.chars() {
+            match c {
+                '"' => code.push_str("\\\""),
+                '\'' => code.push_str("\\'"),
+                '\\' => code.push_str("\\\\"),
+                '\n' => code.push_str("\\n"),
+                '\r' => code.push_str("\\r"),
+                '\t' => code.push_str("\\t"),
+                '<' => code.push_str("\\x3C"),
+                '\u{2028}' => code.push_str("\\u2028"),
+                '\u{2029}' => code.push_str("\\u2029"),
+                c if c.is_control() => code.push_str(&format!("\\u{:04x}", c as u32)),
+                c => code.push(c),
+            }
+        }
+
+        code.push('"');
+
+        // SAFETY: everything that could terminate the literal is escaped above
+        unsafe { JSString::new_unchecked(code) }
+    }
+
+    /// Appends `other` as it is and checks the result, `self` is left unchanged if it is not valid.
+    #[cfg(feature = "__validation")]
+    pub fn push_js(&mut self, other: &JSStr) -> Result<(), Error> {
+        let len = self.code.len();
+        self.code.push_str(other.as_str());
+
+        if let Err(e) = JSStr::new(&self.code) {
+            self.code.truncate(len);
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Appends `stmt` on a new line, terminating the code before it with `;` if it isn't already.
+    /// Trailing whitespace of the code before it is removed.
+    /// Like `push_js`, `self` is left unchanged if the result is not valid.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use include_js_core::{JSStr, JSString};
+    ///
+    /// let mut js = JSString::new("let x = 1".to_owned()).unwrap();
+    /// js.push_statement(JSStr::new("x += 1;").unwrap()).unwrap();
+    /// js += JSStr::new("console.log(x)").unwrap();
+    /// assert_eq!(js, "let x = 1;\nx += 1;\nconsole.log(x)");
+    ///
+    ///
+    /// // `console.log(x)x` is not valid
+    /// assert!(js.push_js(JSStr::new("x").unwrap()).is_err());
+    /// assert_eq!(js, "let x = 1;\nx += 1;\nconsole.log(x)");
+    /// ```
+    #[cfg(feature = "__validation")]
+    pub fn push_statement(&mut self, stmt: &JSStr) -> Result<(), Error> {
+        let mut code = self.code.clone();
+        end_statement(&mut code);
+        code.push_str(stmt.as_str());
+        *self = JSString::new(code)?;
+
+        Ok(())
+    }
+
     pub fn into_string(self) -> String {
         self.code
     }
+
+    /// Converts the code into a nul-terminated C string, see `JSStr::to_cstring`.
+    pub fn into_cstring(self) -> CString {
+        nul_free(self.code)
+    }
+}
+
+/// Removes trailing whitespace from `code` and terminates it with `;` and a newline, so that the code
+/// appended next is a separate statement. Empty code is left empty and code that ends with the start of
+/// a block is not terminated.
+#[cfg(feature = "__validation")]
+pub(crate) fn end_statement(code: &mut String) {
+    code.truncate(code.trim_end().len());
+
+    if !code.is_empty() {
+        if !code.ends_with(';') && !code.ends_with('{') {
+            code.push(';');
+        }
+
+        code.push('\n');
+    }
+}
+
+/// Replaces every nul character in `code` with `\x00`.
+///
+/// Outside of literals and comments a nul character is a syntax error and inside of string, template
+/// and regex literals `\x00` means the same. The only observable difference is the raw value of templates.
+fn nul_free(code: String) -> CString {
+    let code = if code.contains('\0') { code.replace('\0', "\\x00") } else { code };
+    CString::new(code).expect("all nul characters were escaped")
 }
 
+#[cfg(feature = "__validation")]
 impl TryFrom<String> for JSString {
-    type Error = JSParseError;
+    type Error = Error;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
         JSString::new(value)
     }
 }
 
-impl Into<String> for JSString {
-    fn into(self) -> String {
-        self.code
+#[cfg(feature = "__validation")]
+impl<'a> TryFrom<Cow<'a, str>> for JSString {
+    type Error = Error;
+
+    fn try_from(value: Cow<'a, str>) -> Result<Self, Self::Error> {
+        JSString::new(value.into_owned())
+    }
+}
+
+/// # Examples
+///
+/// ```rust
+/// use include_js_core::JSString;
+///
+/// let js: JSString = "let x = 1;".parse().unwrap();
+/// assert_eq!(js, "let x = 1;");
+/// assert!("let x = ;".parse::<JSString>().is_err());
+/// ```
+#[cfg(feature = "__validation")]
+impl FromStr for JSString {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JSString::new(s.to_owned())
+    }
+}
+
+impl From<&JSStr> for JSString {
+    fn from(js: &JSStr) -> Self {
+        js.to_owned()
+    }
+}
+
+impl<'a> From<&'a JSStr> for Cow<'a, JSStr> {
+    fn from(js: &'a JSStr) -> Self {
+        Cow::Borrowed(js)
+    }
+}
+
+impl From<JSString> for Cow<'_, JSStr> {
+    fn from(js: JSString) -> Self {
+        Cow::Owned(js)
+    }
+}
+
+impl From<JSString> for String {
+    fn from(js: JSString) -> Self {
+        js.code
     }
 }
 
@@ -140,6 +991,39 @@ impl AsRef<JSStr> for JSString {
     }
 }
 
+#[cfg(feature = "__validation")]
+impl AddAssign<&JSStr> for JSString {
+    /// Appends `rhs` as a separate statement, see `push_statement`.
+    ///
+    /// # Panics
+    /// Panics if the result is not valid Javascript.
+    fn add_assign(&mut self, rhs: &JSStr) {
+        if let Err(e) = self.push_statement(rhs) {
+            panic!("{}", e);
+        }
+    }
+}
+
+#[cfg(feature = "__validation")]
+impl Add<&JSStr> for JSString {
+    type Output = JSString;
+
+    /// Appends `rhs` as a separate statement, see `push_statement`.
+    ///
+    /// # Panics
+    /// Panics if the result is not valid Javascript.
+    fn add(mut self, rhs: &JSStr) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
+impl AsRef<str> for JSString {
+    fn as_ref(&self) -> &str {
+        &self.code
+    }
+}
+
 impl Deref for JSString {
     type Target = JSStr;
 
@@ -148,3 +1032,91 @@ impl Deref for JSString {
         unsafe { JSStr::new_unchecked(&self.code) }
     }
 }
+
+#[cfg(feature = "std")]
+impl Deref for LazyJSStr {
+    type Target = JSStr;
+
+    fn deref(&self) -> &Self::Target {
+        self.get()
+    }
+}
+
+#[cfg(feature = "std")]
+impl AsRef<JSStr> for LazyJSStr {
+    fn as_ref(&self) -> &JSStr {
+        self.get()
+    }
+}
+
+#[cfg(feature = "std")]
+impl AsRef<str> for LazyJSStr {
+    fn as_ref(&self) -> &str {
+        self.get().as_str()
+    }
+}
+
+impl fmt::Debug for JSStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.data, f)
+    }
+}
+
+impl fmt::Display for JSStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.data, f)
+    }
+}
+
+impl fmt::Debug for JSString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.code, f)
+    }
+}
+
+impl fmt::Display for JSString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.code, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for LazyJSStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.get(), f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for LazyJSStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.get(), f)
+    }
+}
+
+macro_rules! impl_str_eq {
+    ($($a:ty, $b:ty;)*) => {
+        $(impl<'a> PartialEq<$b> for $a {
+            fn eq(&self, other: &$b) -> bool {
+                AsRef::<str>::as_ref(self) == AsRef::<str>::as_ref(other)
+            }
+        }
+
+        impl<'a> PartialEq<$a> for $b {
+            fn eq(&self, other: &$a) -> bool {
+                AsRef::<str>::as_ref(self) == AsRef::<str>::as_ref(other)
+            }
+        })*
+    };
+}
+
+impl_str_eq! {
+    JSStr, str;
+    JSStr, &'a str;
+    JSStr, String;
+    JSString, str;
+    JSString, &'a str;
+    JSString, String;
+    JSString, JSStr;
+    JSString, &'a JSStr;
+}
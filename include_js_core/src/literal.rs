@@ -0,0 +1,231 @@
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::convert::TryFrom;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::JSString;
+
+/// Conversion of Rust values into Javascript source code that evaluates to an equivalent value.
+/// Integers beyond `Number.MAX_SAFE_INTEGER`, i.e. ±(2^53 - 1), would be rounded as a number,
+/// so they are written as BigInt literals like `18446744073709551615n`.
+///
+/// # Examples
+///
+/// ```rust
+/// use include_js_core::ToJsLiteral;
+///
+/// assert_eq!(vec![Some(1.5), None, Some(f64::NAN)].to_js_literal().as_str(), "[1.5, null, NaN]");
+/// assert_eq!("it's".to_js_literal().as_str(), r#""it\'s""#);
+///
+/// assert_eq!(9007199254740991u64.to_js_literal().as_str(), "9007199254740991");
+/// assert_eq!(9007199254740992u64.to_js_literal().as_str(), "9007199254740992n");
+/// assert_eq!((-9007199254740992i64).to_js_literal().as_str(), "-9007199254740992n");
+/// assert_eq!(u64::MAX.to_js_literal().as_str(), "18446744073709551615n");
+/// assert_eq!(u128::MAX.to_js_literal().as_str(), "340282366920938463463374607431768211455n");
+/// ```
+pub trait ToJsLiteral {
+    /// Appends the Javascript expression for `self` to `out`.
+    /// The expression may be an object literal, so it is not necessarily a valid statement on its own.
+    fn write_js_literal(&self, out: &mut String);
+
+    /// Returns the Javascript expression for `self`. Object literals are wrapped in parentheses,
+    /// so the result is valid Javascript even in statement position.
+    fn to_js_literal(&self) -> JSString {
+        let mut code = String::new();
+        self.write_js_literal(&mut code);
+
+        if code.starts_with('{') {
+            code.insert(0, '(');
+            code.push(')');
+        }
+
+        // SAFETY: all implementations write a single valid expression
+        unsafe { JSString::new_unchecked(code) }
+    }
+}
+
+/// The largest integer a Javascript number represents exactly, `Number.MAX_SAFE_INTEGER`
+const MAX_SAFE_INTEGER: u128 = (1 << 53) - 1;
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(impl ToJsLiteral for $t {
+            fn write_js_literal(&self, out: &mut String) {
+                out.push_str(&self.to_string());
+
+                // a number would be rounded, so the value is written as a BigInt
+                let safe = match i128::try_from(*self) {
+                    Ok(value) => value.unsigned_abs() <= MAX_SAFE_INTEGER,
+                    Err(_) => false,
+                };
+                if !safe {
+                    out.push('n');
+                }
+            }
+        })*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_float {
+    ($($t:ty),*) => {
+        $(impl ToJsLiteral for $t {
+            fn write_js_literal(&self, out: &mut String) {
+                if self.is_nan() {
+                    out.push_str("NaN");
+                } else if self.is_infinite() {
+                    out.push_str(if self.is_sign_positive() { "Infinity" } else { "-Infinity" });
+                } else {
+                    out.push_str(&self.to_string());
+                }
+            }
+        })*
+    };
+}
+
+impl_float!(f32, f64);
+
+impl ToJsLiteral for bool {
+    fn write_js_literal(&self, out: &mut String) {
+        out.push_str(if *self { "true" } else { "false" });
+    }
+}
+
+impl ToJsLiteral for str {
+    fn write_js_literal(&self, out: &mut String) {
+        out.push_str(JSString::quote(self).as_str());
+    }
+}
+
+impl ToJsLiteral for String {
+    fn write_js_literal(&self, out: &mut String) {
+        self.as_str().write_js_literal(out);
+    }
+}
+
+impl ToJsLiteral for char {
+    fn write_js_literal(&self, out: &mut String) {
+        self.encode_utf8(&mut [0; 4]).write_js_literal(out);
+    }
+}
+
+impl<T: ToJsLiteral + ?Sized> ToJsLiteral for &T {
+    fn write_js_literal(&self, out: &mut String) {
+        (**self).write_js_literal(out);
+    }
+}
+
+impl<T: ToJsLiteral> ToJsLiteral for Option<T> {
+    fn write_js_literal(&self, out: &mut String) {
+        match self {
+            Some(value) => value.write_js_literal(out),
+            None => out.push_str("null"),
+        }
+    }
+}
+
+impl<T: ToJsLiteral> ToJsLiteral for [T] {
+    fn write_js_literal(&self, out: &mut String) {
+        out.push('[');
+        for (i, value) in self.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            value.write_js_literal(out);
+        }
+        out.push(']');
+    }
+}
+
+impl<T: ToJsLiteral, const N: usize> ToJsLiteral for [T; N] {
+    fn write_js_literal(&self, out: &mut String) {
+        self[..].write_js_literal(out);
+    }
+}
+
+impl<T: ToJsLiteral> ToJsLiteral for Vec<T> {
+    fn write_js_literal(&self, out: &mut String) {
+        self[..].write_js_literal(out);
+    }
+}
+
+fn write_object_literal<'a, K, V, I>(properties: I, out: &mut String)
+where
+    K: AsRef<str> + 'a,
+    V: ToJsLiteral + 'a,
+    I: IntoIterator<Item = (K, &'a V)>,
+{
+    out.push('{');
+    for (i, (key, value)) in properties.into_iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        key.as_ref().write_js_literal(out);
+        out.push_str(": ");
+        value.write_js_literal(out);
+    }
+    out.push('}');
+}
+
+#[cfg(feature = "std")]
+impl<K: AsRef<str>, V: ToJsLiteral, S> ToJsLiteral for HashMap<K, V, S> {
+    fn write_js_literal(&self, out: &mut String) {
+        write_object_literal(self.iter().map(|(k, v)| (k.as_ref(), v)), out);
+    }
+}
+
+impl<K: AsRef<str>, V: ToJsLiteral> ToJsLiteral for BTreeMap<K, V> {
+    fn write_js_literal(&self, out: &mut String) {
+        write_object_literal(self.iter().map(|(k, v)| (k.as_ref(), v)), out);
+    }
+}
+
+/// Conversion of Rust values into the arguments of a Javascript function call, see `JSFunction::call_with`.
+/// Implemented for tuples of up to 12 `ToJsLiteral` values, with one argument per element.
+///
+/// # Examples
+///
+/// ```rust
+/// use include_js_core::ToJsArgs;
+///
+/// let args: Vec<_> = (800, "main", [1, 2]).to_js_args().into_iter().map(|arg| arg.to_string()).collect();
+/// assert_eq!(args, ["800", r#""main""#, "[1, 2]"]);
+/// ```
+pub trait ToJsArgs {
+    /// Returns the Javascript expressions for the arguments, in order
+    fn to_js_args(&self) -> Vec<JSString>;
+}
+
+macro_rules! impl_args {
+    ($(($($name:ident),*)),*) => {
+        $(impl<$($name: ToJsLiteral),*> ToJsArgs for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn to_js_args(&self) -> Vec<JSString> {
+                let ($($name,)*) = self;
+                vec![$($name.to_js_literal()),*]
+            }
+        })*
+    };
+}
+
+impl_args!(
+    (),
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F),
+    (A, B, C, D, E, F, G),
+    (A, B, C, D, E, F, G, H),
+    (A, B, C, D, E, F, G, H, I),
+    (A, B, C, D, E, F, G, H, I, J),
+    (A, B, C, D, E, F, G, H, I, J, K),
+    (A, B, C, D, E, F, G, H, I, J, K, L)
+);
@@ -0,0 +1,622 @@
+//! Support for the module goal of the ECMAScript grammar.
+//!
+//! boa only implements the script goal, so module-only syntax (`import` and `export` declarations)
+//! is checked here and then masked out, leaving a script that can be handed to the regular parser.
+//! This is done with ressa as well, so modules are checked the same way with either parser.
+//! Masking overwrites with whitespace, which keeps the line/column information of later syntax errors intact.
+
+use std::borrow::Cow;
+
+use crate::{ExportInfo, ParseError};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Ident,
+    Str,
+    Template,
+    Regex,
+    Number,
+    Punct(u8),
+}
+
+/// A token of the source, `depth` is the number of brackets and template substitutions it is nested in
+/// after the token, so a closing bracket at the top level has depth 0
+#[derive(Clone, Copy)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) depth: usize,
+}
+
+enum Nesting {
+    Bracket,
+    TemplateSubstitution,
+}
+
+struct Tokenizer<'a> {
+    src: &'a [u8],
+    pos: usize,
+    nesting: Vec<Nesting>,
+    tokens: Vec<Token>,
+}
+
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b == b'\\' || b >= 0x80
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(src: &'a str) -> Self {
+        Tokenizer {
+            src: src.as_bytes(),
+            pos: 0,
+            nesting: Vec::new(),
+            tokens: Vec::new(),
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.src.get(self.pos + offset).copied()
+    }
+
+    fn push(&mut self, kind: TokenKind, start: usize) {
+        let depth = self.nesting.len();
+        self.tokens.push(Token { kind, start, end: self.pos, depth });
+    }
+
+    /// Decides whether a `/` starts a regular expression literal or is a division,
+    /// based on the previous significant token.
+    fn regex_allowed(&self) -> bool {
+        match self.tokens.last() {
+            None => true,
+            Some(t) => match t.kind {
+                TokenKind::Ident => {
+                    let word = &self.src[t.start..t.end];
+                    matches!(
+                        word,
+                        b"return" | b"typeof" | b"instanceof" | b"in" | b"of" | b"new" | b"delete"
+                            | b"void" | b"throw" | b"case" | b"do" | b"else" | b"yield" | b"await"
+                    )
+                },
+                TokenKind::Punct(b')') | TokenKind::Punct(b']') => false,
+                TokenKind::Punct(_) => true,
+                _ => false,
+            },
+        }
+    }
+
+    fn skip_quoted(&mut self, quote: u8) -> Result<(), usize> {
+        let start = self.pos;
+        self.pos += 1;
+
+        while let Some(b) = self.peek(0) {
+            self.pos += 1;
+            match b {
+                b'\\' => self.pos += 1,
+                b'\n' if quote != b'/' => return Err(start),
+                b'[' if quote == b'/' => {
+                    // character classes may contain unescaped slashes
+                    while let Some(b) = self.peek(0) {
+                        self.pos += 1;
+                        match b {
+                            b'\\' => self.pos += 1,
+                            b']' => break,
+                            _ => (),
+                        }
+                    }
+                },
+                _ if b == quote => return Ok(()),
+                _ => (),
+            }
+        }
+
+        Err(start)
+    }
+
+    /// Scans template characters up to and including the closing backtick or the next `${`.
+    fn skip_template_chars(&mut self) -> Result<(), usize> {
+        let start = self.pos;
+
+        while let Some(b) = self.peek(0) {
+            self.pos += 1;
+            match b {
+                b'\\' => self.pos += 1,
+                b'`' => return Ok(()),
+                b'$' if self.peek(0) == Some(b'{') => {
+                    self.pos += 1;
+                    self.nesting.push(Nesting::TemplateSubstitution);
+                    return Ok(());
+                },
+                _ => (),
+            }
+        }
+
+        Err(start)
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>, usize> {
+        while let Some(b) = self.peek(0) {
+            let start = self.pos;
+
+            match b {
+                b if b.is_ascii_whitespace() => self.pos += 1,
+                b'/' if self.peek(1) == Some(b'/') => {
+                    while !matches!(self.peek(0), None | Some(b'\n')) {
+                        self.pos += 1;
+                    }
+                },
+                b'/' if self.peek(1) == Some(b'*') => {
+                    self.pos += 2;
+                    while !(self.peek(0) == Some(b'*') && self.peek(1) == Some(b'/')) {
+                        if self.peek(0).is_none() {
+                            return Err(start);
+                        }
+                        self.pos += 1;
+                    }
+                    self.pos += 2;
+                },
+                b'/' if self.regex_allowed() => {
+                    self.skip_quoted(b'/')?;
+                    while self.peek(0).is_some_and(is_ident_byte) {
+                        self.pos += 1;
+                    }
+                    self.push(TokenKind::Regex, start);
+                },
+                b'"' | b'\'' => {
+                    self.skip_quoted(b)?;
+                    self.push(TokenKind::Str, start);
+                },
+                b'`' => {
+                    self.pos += 1;
+                    self.skip_template_chars()?;
+                    self.push(TokenKind::Template, start);
+                },
+                b'0'..=b'9' => {
+                    while self.peek(0).is_some_and(|b| is_ident_byte(b) || b == b'.') {
+                        self.pos += 1;
+                    }
+                    self.push(TokenKind::Number, start);
+                },
+                b if is_ident_byte(b) => {
+                    while self.peek(0).is_some_and(is_ident_byte) {
+                        self.pos += 1;
+                    }
+                    self.push(TokenKind::Ident, start);
+                },
+                b'}' if matches!(self.nesting.last(), Some(Nesting::TemplateSubstitution)) => {
+                    self.nesting.pop();
+                    self.pos += 1;
+                    self.skip_template_chars()?;
+                    self.push(TokenKind::Template, start);
+                },
+                _ => {
+                    self.pos += 1;
+                    match b {
+                        b'(' | b'[' | b'{' => self.nesting.push(Nesting::Bracket),
+                        b')' | b']' | b'}' => {
+                            self.nesting.pop();
+                        },
+                        _ => (),
+                    }
+                    self.push(TokenKind::Punct(b), start);
+                },
+            }
+        }
+
+        Ok(self.tokens)
+    }
+}
+
+/// A module item that imports from or exports to other modules, `start..end` is its range in the source
+pub(crate) struct ModuleItem {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) kind: ItemKind,
+}
+
+/// Names in the pairs below are the source text of the name, so names given as string literals keep their quotes.
+pub(crate) enum ItemKind {
+    /// `import ... from "specifier"` with `(imported, local)` pairs, `imported` is `*` for namespace imports
+    Import { specifier: String, bindings: Vec<(String, String)> },
+    /// `export { ... }` with `(local, exported)` pairs
+    ExportList(Vec<(String, String)>),
+    /// `export { ... } from "specifier"` and `export * as ns from "specifier"` with `(imported, exported)` pairs
+    ExportFrom { specifier: String, names: Vec<(String, String)> },
+    /// `export * from "specifier"`
+    ExportAll { specifier: String },
+    /// `export` in front of a declaration, the range is only the keyword.
+    /// The declared names are `None` if they can't be determined, e.g. for destructuring.
+    ExportDeclaration(Option<Vec<String>>),
+    /// `export default` in front of an expression
+    ExportDefaultExpression,
+    /// `export default` in front of a named function or class declaration
+    ExportDefaultDeclaration(String),
+    /// `export default` in front of an anonymous function or class declaration, the range includes
+    /// the keywords of the declaration, e.g. `async function*`, which are given separated by spaces
+    ExportDefaultAnonymous(String),
+    /// the `import` of a dynamic `import(...)` or `import.meta`
+    DynamicImport,
+}
+
+struct ModuleItems<'a> {
+    src: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> ModuleItems<'a> {
+    fn error(&self, message: &'static str) -> ParseError {
+        let offset = self.tokens.get(self.pos).map_or(self.src.len(), |t| t.start);
+        ParseError::general(self.src, message, offset)
+    }
+
+    fn text(&self, token: &Token) -> &'a str {
+        &self.src[token.start..token.end]
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        match self.peek() {
+            Some(t) if t.kind == TokenKind::Ident && self.text(&t) == word => {
+                self.pos += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn eat_punct(&mut self, punct: u8) -> bool {
+        match self.peek() {
+            Some(t) if t.kind == TokenKind::Punct(punct) => {
+                self.pos += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind, message: &'static str) -> Result<&'a str, ParseError> {
+        match self.peek() {
+            Some(t) if t.kind == kind => {
+                self.pos += 1;
+                Ok(self.text(&t))
+            },
+            _ => Err(self.error(message)),
+        }
+    }
+
+    fn name(&mut self, message: &'static str) -> Result<&'a str, ParseError> {
+        match self.peek() {
+            Some(t) if matches!(t.kind, TokenKind::Ident | TokenKind::Str) => {
+                self.pos += 1;
+                Ok(self.text(&t))
+            },
+            _ => Err(self.error(message)),
+        }
+    }
+
+    /// `{ a, b as c, "d" as e, }` as `(name, alias)` pairs, the opening brace has already been consumed
+    fn named_bindings(&mut self) -> Result<Vec<(String, String)>, ParseError> {
+        let mut bindings = Vec::new();
+
+        while !self.eat_punct(b'}') {
+            let name = self.name("expected binding name in module item")?;
+            let alias = if self.eat_word("as") { self.name("expected binding name after 'as'")? } else { name };
+            bindings.push((name.to_owned(), alias.to_owned()));
+
+            if !self.eat_punct(b',') && !matches!(self.peek(), Some(t) if t.kind == TokenKind::Punct(b'}')) {
+                return Err(self.error("expected ',' or '}' in module item"));
+            }
+        }
+
+        Ok(bindings)
+    }
+
+    /// `from "specifier"`, returns the specifier without quotes
+    fn parse_from_clause(&mut self) -> Result<String, ParseError> {
+        if !self.eat_word("from") {
+            return Err(self.error("expected 'from' in module item"));
+        }
+        self.module_specifier()
+    }
+
+    fn module_specifier(&mut self) -> Result<String, ParseError> {
+        let specifier = self.expect(TokenKind::Str, "expected module specifier string")?;
+
+        // import attributes: `with { type: "json" }`
+        if self.eat_word("with") || self.eat_word("assert") {
+            self.expect(TokenKind::Punct(b'{'), "expected '{' after 'with'")?;
+            while !self.eat_punct(b'}') {
+                if self.peek().is_none() {
+                    return Err(self.error("unterminated import attributes"));
+                }
+                self.pos += 1;
+            }
+        }
+
+        Ok(specifier[1..specifier.len() - 1].to_owned())
+    }
+
+    fn import_declaration(&mut self) -> Result<ItemKind, ParseError> {
+        let mut bindings = Vec::new();
+
+        if self.eat_punct(b'{') {
+            bindings = self.named_bindings()?;
+            let specifier = self.parse_from_clause()?;
+            return Ok(ItemKind::Import { specifier, bindings });
+        }
+
+        match self.peek() {
+            Some(t) if t.kind == TokenKind::Str => {
+                let specifier = self.module_specifier()?;
+                return Ok(ItemKind::Import { specifier, bindings });
+            },
+            Some(t) if t.kind == TokenKind::Ident => {
+                self.pos += 1;
+                bindings.push(("default".to_owned(), self.text(&t).to_owned()));
+                if !self.eat_punct(b',') {
+                    let specifier = self.parse_from_clause()?;
+                    return Ok(ItemKind::Import { specifier, bindings });
+                }
+            },
+            Some(t) if t.kind == TokenKind::Punct(b'*') => (),
+            _ => return Err(self.error("expected import clause")),
+        }
+
+        if self.eat_punct(b'*') {
+            if !self.eat_word("as") {
+                return Err(self.error("expected 'as' after '*'"));
+            }
+            let local = self.expect(TokenKind::Ident, "expected namespace binding name")?;
+            bindings.push(("*".to_owned(), local.to_owned()));
+        } else if self.eat_punct(b'{') {
+            bindings.extend(self.named_bindings()?);
+        } else {
+            return Err(self.error("expected namespace import or named imports"));
+        }
+
+        let specifier = self.parse_from_clause()?;
+        Ok(ItemKind::Import { specifier, bindings })
+    }
+
+    /// Parses what follows `export default`, which has already been consumed, and returns the end of the item range.
+    fn export_default(&mut self) -> (usize, ItemKind) {
+        let default_end = self.tokens[self.pos - 1].end;
+        let declaration_start = self.pos;
+
+        self.eat_word("async");
+        let is_declaration = self.eat_word("function") || (self.pos == declaration_start && self.eat_word("class"));
+
+        if !is_declaration {
+            self.pos = declaration_start;
+            return (default_end, ItemKind::ExportDefaultExpression);
+        }
+
+        self.eat_punct(b'*');
+
+        match self.peek() {
+            Some(n) if n.kind == TokenKind::Ident && self.text(&n) != "extends" => {
+                (default_end, ItemKind::ExportDefaultDeclaration(self.text(&n).to_owned()))
+            },
+            _ => {
+                let keywords = self.tokens[declaration_start..self.pos]
+                    .iter()
+                    .map(|t| self.text(t))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                (self.tokens[self.pos - 1].end, ItemKind::ExportDefaultAnonymous(keywords))
+            },
+        }
+    }
+
+    /// The names declared by the declaration starting at the current token, without consuming anything.
+    fn declared_names(&self) -> Option<Vec<String>> {
+        let mut i = self.pos;
+        let word = |i: usize| self.tokens.get(i).filter(|t| t.kind == TokenKind::Ident).map(|t| self.text(t));
+
+        if word(i) == Some("async") {
+            i += 1;
+        }
+
+        match word(i)? {
+            "function" | "class" => {
+                i += 1;
+                if self.tokens.get(i).is_some_and(|t| t.kind == TokenKind::Punct(b'*')) {
+                    i += 1;
+                }
+                word(i).map(|name| vec![name.to_owned()])
+            },
+            "var" | "let" | "const" => {
+                let mut names = vec![word(i + 1)?.to_owned()];
+                i += 2;
+
+                // the declarators are separated by commas outside of any brackets and the declaration
+                // ends with a semicolon or, by automatic semicolon insertion, a line break before a new statement
+                while let Some(t) = self.tokens.get(i) {
+                    let prev = &self.tokens[i - 1];
+                    let line_break = self.src[prev.end..t.start].contains('\n');
+                    let prev_ends_expression =
+                        !matches!(prev.kind, TokenKind::Punct(p) if !matches!(p, b')' | b']' | b'}'));
+
+                    if t.depth == 0 && t.kind == TokenKind::Punct(b',') {
+                        names.push(word(i + 1)?.to_owned());
+                        i += 2;
+                    } else if t.depth == 0
+                        && (t.kind == TokenKind::Punct(b';')
+                            || (line_break && prev_ends_expression && !matches!(t.kind, TokenKind::Punct(_))))
+                    {
+                        break;
+                    } else {
+                        i += 1;
+                    }
+                }
+
+                Some(names)
+            },
+            _ => None,
+        }
+    }
+
+    /// Parses all top level module items and returns the first malformed one as an error.
+    fn parse(mut self) -> Result<Vec<ModuleItem>, ParseError> {
+        let mut items = Vec::new();
+
+        while let Some(t) = self.peek() {
+            let after_dot = matches!(self.pos.checked_sub(1).map(|i| self.tokens[i].kind), Some(TokenKind::Punct(b'.')));
+            self.pos += 1;
+
+            if t.kind != TokenKind::Ident || after_dot {
+                continue;
+            }
+
+            // dynamic `import(...)` and `import.meta` are expressions and not module items
+            if self.text(&t) == "import"
+                && matches!(self.peek(), Some(n) if matches!(n.kind, TokenKind::Punct(b'(') | TokenKind::Punct(b'.')))
+            {
+                items.push(ModuleItem { start: t.start, end: t.end, kind: ItemKind::DynamicImport });
+                continue;
+            }
+
+            if t.depth != 0 {
+                continue;
+            }
+
+            let start = t.start;
+            let kind = match self.text(&t) {
+                "import" => self.import_declaration()?,
+                "export" => {
+                    if self.eat_word("default") {
+                        let (end, kind) = self.export_default();
+                        items.push(ModuleItem { start, end, kind });
+                        continue;
+                    }
+
+                    if self.eat_punct(b'*') {
+                        if self.eat_word("as") {
+                            let exported = self.name("expected namespace export name")?.to_owned();
+                            let specifier = self.parse_from_clause()?;
+                            ItemKind::ExportFrom { specifier, names: vec![("*".to_owned(), exported)] }
+                        } else {
+                            ItemKind::ExportAll { specifier: self.parse_from_clause()? }
+                        }
+                    } else if self.eat_punct(b'{') {
+                        let names = self.named_bindings()?;
+                        if matches!(self.peek(), Some(n) if n.kind == TokenKind::Ident && self.text(&n) == "from") {
+                            ItemKind::ExportFrom { specifier: self.parse_from_clause()?, names }
+                        } else {
+                            ItemKind::ExportList(names)
+                        }
+                    } else {
+                        match self.peek() {
+                            Some(n) if n.kind == TokenKind::Ident
+                                && matches!(self.text(&n), "var" | "let" | "const" | "function" | "class" | "async") =>
+                            {
+                                // only the keyword itself is part of the item, the declaration is valid script syntax
+                                let kind = ItemKind::ExportDeclaration(self.declared_names());
+                                items.push(ModuleItem { start, end: t.end, kind });
+                            },
+                            _ => return Err(self.error("expected declaration after 'export'")),
+                        }
+                        continue;
+                    }
+                },
+                _ => continue,
+            };
+
+            self.eat_punct(b';');
+            let end = self.tokens[self.pos - 1].end;
+            items.push(ModuleItem { start, end, kind });
+        }
+
+        Ok(items)
+    }
+}
+
+pub(crate) fn mask_range(out: &mut [u8], start: usize, end: usize) {
+    for b in &mut out[start..end] {
+        if *b != b'\n' {
+            *b = b' ';
+        }
+    }
+}
+
+/// Splits `src` into tokens, leaving out whitespace and comments.
+/// The part of a template literal after each substitution is a token of its own.
+pub(crate) fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
+    Tokenizer::new(src)
+        .tokenize()
+        .map_err(|offset| ParseError::general(src, "unterminated literal or comment", offset))
+}
+
+/// Tokenizes `src` and returns its module items.
+pub(crate) fn module_items(src: &str) -> Result<Vec<ModuleItem>, ParseError> {
+    let tokens = tokenize(src)?;
+
+    ModuleItems { src, tokens: &tokens, pos: 0 }.parse()
+}
+
+/// The export for the exported name `name`, which keeps its quotes if it is a string literal
+fn export_name(name: &str) -> ExportInfo {
+    let name = match name.as_bytes().first() {
+        Some(b'"') | Some(b'\'') => &name[1..name.len() - 1],
+        _ => name,
+    };
+
+    match name {
+        "default" => ExportInfo::Default,
+        _ => ExportInfo::Named(Cow::Owned(name.to_owned())),
+    }
+}
+
+/// Returns the exports of the module `src` in the order they are declared in.
+pub(crate) fn exports(src: &str) -> Result<Vec<ExportInfo>, ParseError> {
+    let mut exports = Vec::new();
+
+    for item in module_items(src)? {
+        match item.kind {
+            ItemKind::ExportList(names) | ItemKind::ExportFrom { names, .. } => {
+                exports.extend(names.iter().map(|(_, exported)| export_name(exported)));
+            },
+            ItemKind::ExportAll { specifier } => exports.push(ExportInfo::All(Cow::Owned(specifier))),
+            ItemKind::ExportDeclaration(names) => {
+                exports.extend(names.unwrap_or_default().iter().map(|name| export_name(name)));
+            },
+            ItemKind::ExportDefaultExpression
+            | ItemKind::ExportDefaultDeclaration(_)
+            | ItemKind::ExportDefaultAnonymous(_) => exports.push(ExportInfo::Default),
+            ItemKind::Import { .. } | ItemKind::DynamicImport => (),
+        }
+    }
+
+    Ok(exports)
+}
+
+/// Checks the module-only syntax of `src` and returns a script that is syntactically valid
+/// if and only if `src` is a syntactically valid module.
+pub(crate) fn to_script_goal(src: &str) -> Result<String, ParseError> {
+    let mut out = src.as_bytes().to_vec();
+
+    for item in module_items(src)? {
+        mask_range(&mut out, item.start, item.end);
+
+        match item.kind {
+            // `export default x` becomes `void x`
+            ItemKind::ExportDefaultExpression => out[item.start..item.start + 4].copy_from_slice(b"void"),
+            // anonymous declarations only exist in modules, so they get a name instead
+            ItemKind::ExportDefaultAnonymous(keywords) => {
+                let replacement = format!("{} $default", keywords);
+                out[item.start..item.start + replacement.len()].copy_from_slice(replacement.as_bytes());
+            },
+            // boa does not know about dynamic imports, as far as syntax goes they behave like any other identifier
+            ItemKind::DynamicImport => out[item.start] = b'I',
+            _ => (),
+        }
+    }
+
+    // SAFETY: masking replaces every byte of a range with ASCII, so `out` is still UTF-8
+    Ok(unsafe { String::from_utf8_unchecked(out) })
+}
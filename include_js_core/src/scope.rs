@@ -0,0 +1,327 @@
+//! Finding the free variables of a script or module, i.e. the identifiers it references without declaring them.
+//!
+//! boa's syntax tree does not expose everything needed for this, so the script is parsed with swc.
+//! Declarations are collected when entering a scope, as `var`, functions and the temporal dead zone of
+//! `let`, `const` and `class` make them visible in the whole scope and not just after the declaration.
+
+use std::collections::{BTreeSet, HashSet};
+
+use swc_common::{sync::Lrc, FileName, SourceMap};
+use swc_ecma_ast::*;
+use swc_ecma_parser::{Parser, StringInput, Syntax};
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// Returns the free variables of `src`, or `None` if swc can't parse it as a script or module.
+pub(crate) fn free_variables(src: &str, module: bool) -> Option<BTreeSet<String>> {
+    let cm: Lrc<SourceMap> = Default::default();
+    let file = cm.new_source_file(Lrc::new(FileName::Anon), src.to_owned());
+    let mut parser = Parser::new(Syntax::Es(Default::default()), StringInput::from(&*file), None);
+
+    let program = if module {
+        parser.parse_module().map(Program::Module)
+    } else {
+        parser.parse_script().map(Program::Script)
+    };
+    let program = program.ok()?;
+
+    // swc recovers from some errors and only reports them afterwards
+    if !parser.take_errors().is_empty() {
+        return None;
+    }
+
+    let mut resolver = Resolver { scopes: Vec::new(), free: BTreeSet::new() };
+    program.visit_with(&mut resolver);
+
+    Some(resolver.free)
+}
+
+/// Adds the names bound by `pat` to `names`.
+fn pat_names(pat: &Pat, names: &mut HashSet<String>) {
+    match pat {
+        Pat::Ident(ident) => {
+            names.insert(ident.sym.to_string());
+        },
+        Pat::Array(array) => array.elems.iter().flatten().for_each(|elem| pat_names(elem, names)),
+        Pat::Rest(rest) => pat_names(&rest.arg, names),
+        Pat::Object(object) => {
+            for prop in &object.props {
+                match prop {
+                    ObjectPatProp::KeyValue(prop) => pat_names(&prop.value, names),
+                    ObjectPatProp::Assign(prop) => {
+                        names.insert(prop.key.sym.to_string());
+                    },
+                    ObjectPatProp::Rest(rest) => pat_names(&rest.arg, names),
+                }
+            }
+        },
+        Pat::Assign(assign) => pat_names(&assign.left, names),
+        Pat::Invalid(_) | Pat::Expr(_) => (),
+    }
+}
+
+/// The names declared by `let`, `const`, `class` and function declarations directly in `stmts`.
+fn lexical_names(stmts: &[Stmt], names: &mut HashSet<String>) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Decl(Decl::Var(var)) if var.kind != VarDeclKind::Var => {
+                var.decls.iter().for_each(|decl| pat_names(&decl.name, names));
+            },
+            Stmt::Decl(Decl::Class(class)) => {
+                names.insert(class.ident.sym.to_string());
+            },
+            Stmt::Decl(Decl::Fn(function)) => {
+                names.insert(function.ident.sym.to_string());
+            },
+            _ => (),
+        }
+    }
+}
+
+/// The names declared by `var` in `stmts` and the functions declared in nested blocks, which are visible
+/// in the whole function in sloppy mode. Nested functions and classes are not entered.
+fn var_names(stmts: &[Stmt], names: &mut HashSet<String>) {
+    struct VarCollector<'a>(&'a mut HashSet<String>);
+
+    impl Visit for VarCollector<'_> {
+        fn visit_var_decl(&mut self, node: &VarDecl) {
+            if node.kind == VarDeclKind::Var {
+                node.decls.iter().for_each(|decl| pat_names(&decl.name, self.0));
+            }
+
+            node.visit_children_with(self);
+        }
+
+        fn visit_fn_decl(&mut self, node: &FnDecl) {
+            self.0.insert(node.ident.sym.to_string());
+        }
+
+        fn visit_function(&mut self, _: &Function) {}
+        fn visit_arrow_expr(&mut self, _: &ArrowExpr) {}
+        fn visit_class(&mut self, _: &Class) {}
+    }
+
+    stmts.visit_with(&mut VarCollector(names));
+}
+
+/// The names declared at the top level of a module, i.e. like for a script plus the imports
+/// and the exported declarations.
+fn module_names(items: &[ModuleItem], names: &mut HashSet<String>) {
+    for item in items {
+        match item {
+            ModuleItem::Stmt(stmt) => {
+                var_names(std::slice::from_ref(stmt), names);
+                lexical_names(std::slice::from_ref(stmt), names);
+            },
+            ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => {
+                for specifier in &import.specifiers {
+                    let local = match specifier {
+                        ImportSpecifier::Named(specifier) => &specifier.local,
+                        ImportSpecifier::Default(specifier) => &specifier.local,
+                        ImportSpecifier::Namespace(specifier) => &specifier.local,
+                    };
+                    names.insert(local.sym.to_string());
+                }
+            },
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)) => match &export.decl {
+                Decl::Var(var) => var.decls.iter().for_each(|decl| pat_names(&decl.name, names)),
+                Decl::Fn(function) => {
+                    names.insert(function.ident.sym.to_string());
+                },
+                Decl::Class(class) => {
+                    names.insert(class.ident.sym.to_string());
+                },
+                _ => (),
+            },
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(export)) => {
+                let ident = match &export.decl {
+                    DefaultDecl::Fn(function) => function.ident.as_ref(),
+                    DefaultDecl::Class(class) => class.ident.as_ref(),
+                    DefaultDecl::TsInterfaceDecl(_) => None,
+                };
+                names.extend(ident.map(|ident| ident.sym.to_string()));
+            },
+            _ => (),
+        }
+    }
+}
+
+/// The names visible in a function body with `params`
+fn function_scope<'a>(params: impl IntoIterator<Item = &'a Pat>, body: Option<&[Stmt]>) -> HashSet<String> {
+    let mut names = HashSet::new();
+    names.insert("arguments".to_owned());
+
+    params.into_iter().for_each(|param| pat_names(param, &mut names));
+
+    if let Some(stmts) = body {
+        var_names(stmts, &mut names);
+        lexical_names(stmts, &mut names);
+    }
+
+    names
+}
+
+struct Resolver {
+    /// the names declared in each scope that is currently entered, innermost last
+    scopes: Vec<HashSet<String>>,
+    free: BTreeSet<String>,
+}
+
+impl Resolver {
+    fn scoped<N: VisitWith<Self> + ?Sized>(&mut self, names: HashSet<String>, node: &N) {
+        self.scopes.push(names);
+        node.visit_children_with(self);
+        self.scopes.pop();
+    }
+}
+
+impl Visit for Resolver {
+    fn visit_script(&mut self, node: &Script) {
+        let mut names = HashSet::new();
+        var_names(&node.body, &mut names);
+        lexical_names(&node.body, &mut names);
+
+        self.scoped(names, node);
+    }
+
+    fn visit_module(&mut self, node: &Module) {
+        let mut names = HashSet::new();
+        module_names(&node.body, &mut names);
+
+        self.scoped(names, node);
+    }
+
+    // the names in imports and re-exports are bindings or refer to other modules
+    fn visit_import_decl(&mut self, _: &ImportDecl) {}
+
+    fn visit_named_export(&mut self, node: &NamedExport) {
+        if node.src.is_none() {
+            node.visit_children_with(self);
+        }
+    }
+
+    // only the local name of `export { local as exported }` is a reference
+    fn visit_export_named_specifier(&mut self, node: &ExportNamedSpecifier) {
+        node.orig.visit_with(self);
+    }
+
+    fn visit_ident(&mut self, node: &Ident) {
+        let name = &*node.sym;
+
+        if !self.scopes.iter().any(|scope| scope.contains(name)) {
+            self.free.insert(name.to_owned());
+        }
+    }
+
+    fn visit_function(&mut self, node: &Function) {
+        let body = node.body.as_ref().map(|body| &body.stmts[..]);
+        self.scoped(function_scope(node.params.iter().map(|param| &param.pat), body), node);
+    }
+
+    fn visit_constructor(&mut self, node: &Constructor) {
+        let params = node.params.iter().filter_map(|param| match param {
+            ParamOrTsParamProp::Param(param) => Some(&param.pat),
+            ParamOrTsParamProp::TsParamProp(_) => None,
+        });
+        let body = node.body.as_ref().map(|body| &body.stmts[..]);
+
+        self.scoped(function_scope(params, body), node);
+    }
+
+    fn visit_arrow_expr(&mut self, node: &ArrowExpr) {
+        let body = match &*node.body {
+            ArrowFunctionBody::FunctionBody(body) => Some(&body.stmts[..]),
+            ArrowFunctionBody::Expr(_) => None,
+        };
+
+        let mut names = function_scope(&node.params, body);
+        // arrow functions see the `arguments` of the function around them
+        names.remove("arguments");
+
+        self.scoped(names, node);
+    }
+
+    fn visit_fn_expr(&mut self, node: &FnExpr) {
+        // the name of a function expression is only visible inside of it
+        let names = node.ident.iter().map(|ident| ident.sym.to_string()).collect();
+        self.scoped(names, node);
+    }
+
+    fn visit_class_expr(&mut self, node: &ClassExpr) {
+        let names = node.ident.iter().map(|ident| ident.sym.to_string()).collect();
+        self.scoped(names, node);
+    }
+
+    fn visit_block_stmt(&mut self, node: &BlockStmt) {
+        let mut names = HashSet::new();
+        lexical_names(&node.stmts, &mut names);
+
+        self.scoped(names, node);
+    }
+
+    fn visit_static_block(&mut self, node: &StaticBlock) {
+        let mut names = HashSet::new();
+        var_names(&node.body.stmts, &mut names);
+
+        self.scoped(names, node);
+    }
+
+    fn visit_switch_stmt(&mut self, node: &SwitchStmt) {
+        // all cases share one scope
+        let mut names = HashSet::new();
+        node.cases.iter().for_each(|case| lexical_names(&case.cons, &mut names));
+
+        self.scoped(names, node);
+    }
+
+    fn visit_for_stmt(&mut self, node: &ForStmt) {
+        let mut names = HashSet::new();
+
+        if let Some(VarDeclOrExpr::VarDecl(var)) = &node.init {
+            if var.kind != VarDeclKind::Var {
+                var.decls.iter().for_each(|decl| pat_names(&decl.name, &mut names));
+            }
+        }
+
+        self.scoped(names, node);
+    }
+
+    fn visit_for_in_stmt(&mut self, node: &ForInStmt) {
+        self.scoped(for_head_names(&node.left), node);
+    }
+
+    fn visit_for_of_stmt(&mut self, node: &ForOfStmt) {
+        self.scoped(for_head_names(&node.left), node);
+    }
+
+    fn visit_catch_clause(&mut self, node: &CatchClause) {
+        let mut names = HashSet::new();
+
+        if let Some(param) = &node.param {
+            pat_names(param, &mut names);
+        }
+
+        self.scoped(names, node);
+    }
+
+    // labels are not variables
+    fn visit_labeled_stmt(&mut self, node: &LabeledStmt) {
+        node.body.visit_with(self);
+    }
+
+    fn visit_break_stmt(&mut self, _: &BreakStmt) {}
+
+    fn visit_continue_stmt(&mut self, _: &ContinueStmt) {}
+}
+
+/// The names declared with `let` or `const` in the head of a `for ... in` or `for ... of` loop
+fn for_head_names(head: &ForHead) -> HashSet<String> {
+    let mut names = HashSet::new();
+
+    if let ForHead::VarDecl(var) = head {
+        if var.kind != VarDeclKind::Var {
+            var.decls.iter().for_each(|decl| pat_names(&decl.name, &mut names));
+        }
+    }
+
+    names
+}
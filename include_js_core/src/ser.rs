@@ -0,0 +1,528 @@
+//! A serde `Serializer` that produces Javascript expressions.
+
+use std::fmt;
+
+use serde::ser::{self, Serialize};
+
+use crate::{JSString, ToJsLiteral};
+
+/// Error returned by `to_js_value` if a value can't be represented in Javascript,
+/// e.g. a map with keys that are not strings or numbers.
+#[derive(Debug)]
+pub struct SerializeError(String);
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+impl ser::Error for SerializeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerializeError(msg.to_string())
+    }
+}
+
+/// Serializes `value` into a Javascript expression, following the same data model as `serde_json`.
+/// Unlike JSON, floats that are NaN or infinite are supported.
+///
+/// # Examples
+///
+/// ```rust
+/// use include_js_core::to_js_value;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Config {
+///     name: &'static str,
+///     scale: f64,
+///     tags: Vec<&'static str>,
+/// }
+///
+/// let js = to_js_value(&Config { name: "main", scale: 1.5, tags: vec!["a", "b"] }).unwrap();
+/// assert_eq!(js.as_str(), r#"({"name": "main", "scale": 1.5, "tags": ["a", "b"]})"#);
+/// ```
+pub fn to_js_value<T: Serialize + ?Sized>(value: &T) -> Result<JSString, SerializeError> {
+    let mut code = String::new();
+    value.serialize(&mut Serializer { out: &mut code })?;
+
+    if code.starts_with('{') {
+        code.insert(0, '(');
+        code.push(')');
+    }
+
+    // SAFETY: the serializer only produces a single valid expression
+    Ok(unsafe { JSString::new_unchecked(code) })
+}
+
+struct Serializer<'a> {
+    out: &'a mut String,
+}
+
+impl<'a> Serializer<'a> {
+    fn write<T: ToJsLiteral + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        value.write_js_literal(self.out);
+        Ok(())
+    }
+
+    fn begin_variant(&mut self, variant: &str) {
+        self.out.push('{');
+        variant.write_js_literal(self.out);
+        self.out.push_str(": ");
+    }
+}
+
+/// Serializes the elements of arrays and the properties of objects
+struct Compound<'a, 'b> {
+    ser: &'b mut Serializer<'a>,
+    first: bool,
+    /// whether an additional `}` has to be written at the end because this is the content of an enum variant
+    variant: bool,
+}
+
+impl<'a, 'b> Compound<'a, 'b> {
+    fn separator(&mut self) {
+        if !self.first {
+            self.ser.out.push_str(", ");
+        }
+        self.first = false;
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.separator();
+        value.serialize(&mut *self.ser)
+    }
+
+    fn property<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), SerializeError> {
+        self.separator();
+        key.write_js_literal(self.ser.out);
+        self.ser.out.push_str(": ");
+        value.serialize(&mut *self.ser)
+    }
+
+    fn close(self, delimiter: char) -> Result<(), SerializeError> {
+        self.ser.out.push(delimiter);
+        if self.variant {
+            self.ser.out.push('}');
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'b> ser::Serializer for &'b mut Serializer<'a> {
+    type Ok = ();
+    type Error = SerializeError;
+    type SerializeSeq = Compound<'a, 'b>;
+    type SerializeTuple = Compound<'a, 'b>;
+    type SerializeTupleStruct = Compound<'a, 'b>;
+    type SerializeTupleVariant = Compound<'a, 'b>;
+    type SerializeMap = Compound<'a, 'b>;
+    type SerializeStruct = Compound<'a, 'b>;
+    type SerializeStructVariant = Compound<'a, 'b>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), SerializeError> {
+        self.write(&v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), SerializeError> {
+        self.write(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), SerializeError> {
+        self.write(v)
+    }
+
+    fn serialize_none(self) -> Result<(), SerializeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerializeError> {
+        self.out.push_str("null");
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerializeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<(), SerializeError> {
+        self.write(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.begin_variant(variant);
+        value.serialize(&mut *self)?;
+        self.out.push('}');
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerializeError> {
+        self.out.push('[');
+        Ok(Compound { ser: self, first: true, variant: false })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        self.begin_variant(variant);
+        self.out.push('[');
+        Ok(Compound { ser: self, first: true, variant: true })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerializeError> {
+        self.out.push('{');
+        Ok(Compound { ser: self, first: true, variant: false })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct, SerializeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        self.begin_variant(variant);
+        self.out.push('{');
+        Ok(Compound { ser: self, first: true, variant: true })
+    }
+}
+
+impl<'a, 'b> ser::SerializeSeq for Compound<'a, 'b> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.close(']')
+    }
+}
+
+impl<'a, 'b> ser::SerializeTuple for Compound<'a, 'b> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.close(']')
+    }
+}
+
+impl<'a, 'b> ser::SerializeTupleStruct for Compound<'a, 'b> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.close(']')
+    }
+}
+
+impl<'a, 'b> ser::SerializeTupleVariant for Compound<'a, 'b> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.close(']')
+    }
+}
+
+impl<'a, 'b> ser::SerializeMap for Compound<'a, 'b> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        self.separator();
+        let key = key.serialize(MapKeySerializer)?;
+        key.write_js_literal(self.ser.out);
+        self.ser.out.push_str(": ");
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.close('}')
+    }
+}
+
+impl<'a, 'b> ser::SerializeStruct for Compound<'a, 'b> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerializeError> {
+        self.property(key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.close('}')
+    }
+}
+
+impl<'a, 'b> ser::SerializeStructVariant for Compound<'a, 'b> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerializeError> {
+        self.property(key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.close('}')
+    }
+}
+
+/// Turns map keys into property names, only strings and numbers are allowed
+struct MapKeySerializer;
+
+fn key_must_be_a_string() -> SerializeError {
+    SerializeError("map keys must be strings or numbers".to_owned())
+}
+
+impl ser::Serializer for MapKeySerializer {
+    type Ok = String;
+    type Error = SerializeError;
+    type SerializeSeq = ser::Impossible<String, SerializeError>;
+    type SerializeTuple = ser::Impossible<String, SerializeError>;
+    type SerializeTupleStruct = ser::Impossible<String, SerializeError>;
+    type SerializeTupleVariant = ser::Impossible<String, SerializeError>;
+    type SerializeMap = ser::Impossible<String, SerializeError>;
+    type SerializeStruct = ser::Impossible<String, SerializeError>;
+    type SerializeStructVariant = ser::Impossible<String, SerializeError>;
+
+    fn serialize_bool(self, _v: bool) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, SerializeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, SerializeError> {
+        Ok(v.to_owned())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_none(self) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit(self) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<String, SerializeError> {
+        Ok(variant.to_owned())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<String, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        Err(key_must_be_a_string())
+    }
+}
@@ -0,0 +1,47 @@
+//! `Serialize` and `Deserialize` for the Javascript wrappers, they are represented as the plain code.
+//! Deserializing validates the code, so it is only available with the `validator-boa` or `validator-ressa` feature.
+
+use serde::{Serialize, Serializer};
+#[cfg(feature = "__validation")]
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+use crate::{JSStr, JSString};
+
+impl Serialize for JSStr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl Serialize for JSString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// # Examples
+///
+/// ```rust
+/// use include_js_core::JSString;
+///
+/// let js: JSString = serde_json::from_str(r#""alert(1)""#).unwrap();
+/// assert_eq!(serde_json::to_string(&js).unwrap(), r#""alert(1)""#);
+///
+/// assert!(serde_json::from_str::<JSString>(r#""alert(1""#).is_err());
+/// ```
+#[cfg(feature = "__validation")]
+impl<'de> Deserialize<'de> for JSString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        JSString::new(code).map_err(D::Error::custom)
+    }
+}
+
+/// Borrows the code from the input, which only works for strings without escape sequences.
+#[cfg(feature = "__validation")]
+impl<'de: 'a, 'a> Deserialize<'de> for &'a JSStr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = <&str>::deserialize(deserializer)?;
+        JSStr::new(code).map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,66 @@
+//! Splitting a script into its top level statements.
+//!
+//! Statements end with a `;` or a `}`, or by automatic semicolon insertion at a line break or the end of the script.
+//! Not every such place ends a statement, so every candidate is parsed and only accepted if it results in the
+//! same statement as parsing the whole script, e.g. `a` is not the first statement of `a\n(b)`.
+//! boa moves function declarations before all other statements, so they are compared separately.
+
+use crate::{
+    module::{self, TokenKind},
+    validator::{self, Statement},
+};
+
+/// Returns the top level statements of `src` without the whitespace and comments between them.
+/// Every statement is parsed on its own, so they are all syntactically valid.
+pub(crate) fn statements(src: &str) -> Vec<&str> {
+    let (tokens, whole) = match (module::tokenize(src), validator::parse_script(src)) {
+        (Ok(tokens), Ok(whole)) => (tokens, whole),
+        _ => return Vec::new(),
+    };
+
+    let (hoisted, others): (Vec<&Statement>, Vec<&Statement>) = whole.iter().partition(|stmt| validator::is_hoisted(stmt));
+    let mut hoisted = hoisted.into_iter().peekable();
+    let mut others = others.into_iter().peekable();
+
+    let mut statements = Vec::new();
+    let mut start = None;
+
+    for (i, token) in tokens.iter().enumerate() {
+        // empty statements, which boa leaves out and fails to parse on their own
+        if start.is_none() && token.kind == TokenKind::Punct(b';') {
+            continue;
+        }
+
+        let stmt_start = *start.get_or_insert(token.start);
+
+        let candidate = token.depth == 0
+            && match tokens.get(i + 1) {
+                None => true,
+                Some(following) => {
+                    matches!(token.kind, TokenKind::Punct(b';') | TokenKind::Punct(b'}'))
+                        || src[token.end..following.start].contains('\n')
+                },
+            };
+
+        if !candidate {
+            continue;
+        }
+
+        let code = &src[stmt_start..token.end];
+        match validator::parse_script(code) {
+            Ok(parsed) if parsed.len() == 1 => {
+                let stmt = &parsed[0];
+                let expected = if validator::is_hoisted(stmt) { &mut hoisted } else { &mut others };
+
+                if expected.peek() == Some(&stmt) {
+                    expected.next();
+                    statements.push(code);
+                    start = None;
+                }
+            },
+            _ => (),
+        }
+    }
+
+    statements
+}
@@ -0,0 +1,54 @@
+//! The parser that validates code at runtime, boa with the `validator-boa` feature or ressa with `validator-ressa`.
+//!
+//! The rest of the crate only needs to know whether a script parses and what its top level statements are,
+//! so the checks give the same guarantees with either parser. Modules are turned into scripts with
+//! `module::to_script_goal` first in both cases. If both features are enabled, e.g. because another crate in the
+//! build enables the default one, ressa is used.
+
+#[cfg(all(feature = "validator-boa", not(feature = "validator-ressa")))]
+mod backend {
+    use boa::syntax::ast::Node;
+
+    use crate::ParseError;
+
+    /// A top level statement of a script, statements compare equal if they are the same code
+    pub(crate) type Statement<'a> = Node;
+
+    /// Parses `src` as a script and returns its top level statements
+    pub(crate) fn parse_script(src: &str) -> Result<Vec<Statement<'_>>, ParseError> {
+        let list = boa::parse(src, false).map_err(|e| ParseError::from_boa(src, e))?;
+        Ok(list.items().to_vec())
+    }
+
+    /// Whether the parser moves `stmt` in front of all other statements, boa does that with function declarations
+    pub(crate) fn is_hoisted(stmt: &Statement<'_>) -> bool {
+        matches!(stmt, Node::FunctionDecl(_))
+    }
+}
+
+#[cfg(feature = "validator-ressa")]
+mod backend {
+    use resast::{stmt::Stmt, ProgramPart};
+    use ressa::Parser;
+
+    use crate::ParseError;
+
+    /// A top level statement of a script, statements compare equal if they are the same code
+    pub(crate) type Statement<'a> = ProgramPart<'a>;
+
+    /// Parses `src` as a script and returns its top level statements. Empty statements are left out like boa does,
+    /// so e.g. `f();;` is a single statement with both parsers.
+    pub(crate) fn parse_script(src: &str) -> Result<Vec<Statement<'_>>, ParseError> {
+        // the parser yields the statements one by one, `Parser::parse` would tie them to the parser
+        Parser::new(src)
+            .and_then(|parser| parser.filter(|part| !matches!(part, Ok(ProgramPart::Stmt(Stmt::Empty)))).collect())
+            .map_err(|e| ParseError::from_ressa(src, e))
+    }
+
+    /// Whether the parser moves `stmt` in front of all other statements, ressa keeps them in order
+    pub(crate) fn is_hoisted(_: &Statement<'_>) -> bool {
+        false
+    }
+}
+
+pub(crate) use backend::{is_hoisted, parse_script, Statement};